@@ -0,0 +1,554 @@
+mod noise;
+mod pulse;
+mod wave;
+use noise::NoiseChannel;
+use pulse::PulseChannel;
+use wave::WaveChannel;
+
+/// NR10, the first sound register
+pub(crate) const SOUND_START: u16 = 0xFF10;
+const MASTER_VOLUME_ADDRESS: u16 = 0xFF24;
+const PANNING_ADDRESS: u16 = 0xFF25;
+pub(crate) const SOUND_CONTROL_ADDRESS: u16 = 0xFF26;
+const WAVE_RAM_START: u16 = 0xFF30;
+/// End of the wave RAM, the last address the APU owns
+pub(crate) const SOUND_END: u16 = 0xFF3F;
+
+pub(crate) const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+/// T-cycles per second
+const CLOCK_RATE: u32 = 4_194_304;
+const T_CYCLES_PER_M_CYCLE: u8 = 4;
+
+pub(crate) const CHANNELS: usize = 4;
+/// Each channel has 5 registers NRx0-NRx4, channel 2 and 4 lack the first one
+const CHANNEL_REGISTERS: u16 = 5;
+/// Bits that read as 1, indexed by the offset from NR10. Write-only and unused registers read as
+/// 0xFF
+const READ_MASKS: [u8; 32] = [
+    0x80, 0x3F, 0x00, 0xFF, 0xBF, // NR10-NR14
+    0xFF, 0x3F, 0x00, 0xFF, 0xBF, // NR20-NR24
+    0x7F, 0xFF, 0x9F, 0xFF, 0xBF, // NR30-NR34
+    0xFF, 0xFF, 0x00, 0x00, 0xBF, // NR40-NR44
+    0x00, 0x00, 0x70, // NR50-NR52
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+];
+
+const POWER_BIT: u8 = 0b1000_0000;
+/// Bit 7 of NRx4 restarts the channel
+const TRIGGER_BIT: u8 = 0b1000_0000;
+/// Bit 6 of NRx4 lets the length counter disable the channel
+const LENGTH_ENABLE_BIT: u8 = 0b0100_0000;
+
+/// The frame sequencer steps on falling edges of this DIV bit, which happen at 512 Hz
+const FRAME_SEQUENCER_DIV_BIT: u8 = 4;
+const FRAME_SEQUENCER_STEPS: u8 = 8;
+/// Step of the frame sequencer that clocks the envelopes
+const ENVELOPE_STEP: u8 = 7;
+
+/// How much charge the capacitor of the high-pass filter keeps per T-cycle
+const HIGH_PASS_CHARGE_FACTOR: f64 = 0.999958;
+
+/// Disables its channel once the length expired, clocked at 256 Hz
+#[derive(Debug)]
+struct LengthCounter {
+    enabled: bool,
+    remaining: u16,
+    max: u16,
+}
+
+impl LengthCounter {
+    fn new(max: u16) -> Self {
+        Self {
+            enabled: false,
+            remaining: 0,
+            max,
+        }
+    }
+
+    /// Loads the length bits of NRx1, the counter counts up from them to the max
+    fn load(&mut self, length: u8) {
+        self.remaining = self.max - length as u16;
+    }
+
+    /// Returns whether the length expired and the channel is disabled
+    fn clock(&mut self) -> bool {
+        if self.enabled && self.remaining > 0 {
+            self.remaining -= 1;
+            return self.remaining == 0;
+        }
+        false
+    }
+
+    /// Applies a write to NRx4, returns whether the length expired and the channel is disabled.
+    /// `first_half` is set when the next frame sequencer step does not clock the length, in that
+    /// case enabling the counter or triggering the channel clocks it one extra time
+    fn write_control(&mut self, byte: u8, first_half: bool) -> bool {
+        let was_enabled = self.enabled;
+        self.enabled = byte & LENGTH_ENABLE_BIT != 0;
+        let trigger = byte & TRIGGER_BIT != 0;
+        let extra_clock = first_half && self.enabled;
+        let mut expired = false;
+        if extra_clock && !was_enabled && self.remaining > 0 {
+            self.remaining -= 1;
+            expired = self.remaining == 0;
+        }
+        if trigger && self.remaining == 0 {
+            self.remaining = self.max;
+            if extra_clock {
+                self.remaining -= 1;
+            }
+        }
+        expired && !trigger
+    }
+}
+
+/// Volume envelope (NRx2) of the pulse and noise channels, clocked at 64 Hz
+#[derive(Debug, Default)]
+struct Envelope {
+    register: u8,
+    volume: u8,
+    timer: u8,
+}
+
+impl Envelope {
+    /// The upper 5 bits of NRx2 power the DAC
+    fn dac_enabled(&self) -> bool {
+        self.register & 0b1111_1000 != 0
+    }
+
+    fn period(&self) -> u8 {
+        self.register & 0b0000_0111
+    }
+
+    fn trigger(&mut self) {
+        self.volume = self.register >> 4;
+        self.timer = self.period();
+    }
+
+    fn clock(&mut self) {
+        if self.period() == 0 {
+            return;
+        }
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer == 0 {
+            self.timer = self.period();
+            if self.register & 0b0000_1000 != 0 {
+                self.volume = (self.volume + 1).min(0x0F);
+            } else {
+                self.volume = self.volume.saturating_sub(1);
+            }
+        }
+    }
+}
+
+/// Converts a channel output of 0-15 to an analog level between -1 and 1
+fn dac(output: u8) -> f32 {
+    output as f32 / 7.5 - 1.0
+}
+
+/// The period of the pulse and wave channels is set by an 11-bit value in NRx3 and NRx4
+fn write_frequency(frequency: &mut u16, register: u16, byte: u8) {
+    *frequency = match register {
+        3 => *frequency & 0x0700 | byte as u16,
+        _ => *frequency & 0x00FF | ((byte as u16 & 0b0000_0111) << 8),
+    };
+}
+
+/// Two pulse channels, a wave channel and a noise channel mixed to stereo samples
+#[derive(Debug)]
+pub(crate) struct Apu {
+    pulse_1: PulseChannel,
+    pulse_2: PulseChannel,
+    wave: WaveChannel,
+    noise: NoiseChannel,
+    /// NR50
+    master_volume: u8,
+    /// NR51, the upper nibble sends the channels to the left and the lower nibble to the right
+    panning: u8,
+    powered: bool,
+    /// Channels that are mixed into the samples, muting them does not affect the emulation
+    enabled_channels: [bool; CHANNELS],
+    /// Frame sequencer step executed next
+    frame_step: u8,
+    /// DIV bit that clocks the frame sequencer, as of the last tick
+    div_bit: bool,
+    sample_rate: u32,
+    /// Grows by the sample rate each T-cycle, a sample is due whenever it reaches the clock rate
+    sample_clock: u32,
+    /// Charge kept by the high-pass filter per sample
+    high_pass_factor: f32,
+    /// Capacitor charges of the high-pass filter, left and right
+    capacitors: [f32; 2],
+    /// Interleaved left and right samples
+    samples: Vec<f32>,
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAMPLE_RATE)
+    }
+}
+
+impl Apu {
+    pub(crate) fn new(sample_rate: u32) -> Self {
+        let mut apu = Self {
+            pulse_1: PulseChannel::new(true),
+            pulse_2: PulseChannel::new(false),
+            wave: WaveChannel::default(),
+            noise: NoiseChannel::default(),
+            master_volume: 0,
+            panning: 0,
+            powered: false,
+            enabled_channels: [true; CHANNELS],
+            frame_step: 0,
+            div_bit: false,
+            sample_rate: 0,
+            sample_clock: 0,
+            high_pass_factor: 0.0,
+            capacitors: [0.0; 2],
+            samples: Vec::new(),
+        };
+        apu.set_sample_rate(sample_rate);
+        apu
+    }
+
+    pub(crate) fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.sample_clock = 0;
+        self.high_pass_factor =
+            HIGH_PASS_CHARGE_FACTOR.powf(CLOCK_RATE as f64 / sample_rate as f64) as f32;
+    }
+
+    /// Enables or mutes one of the channels 0-3 in the output
+    pub(crate) fn set_channel_enabled(&mut self, channel: usize, enabled: bool) {
+        self.enabled_channels[channel] = enabled;
+    }
+
+    /// Returns the samples produced since the last call, interleaved left and right
+    pub(crate) fn take_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.samples)
+    }
+
+    /// Advances by one machine cycle. `div` is the value of DIV, which clocks the frame sequencer
+    pub(crate) fn tick(&mut self, div: u8) {
+        let div_bit = div >> FRAME_SEQUENCER_DIV_BIT & 1 != 0;
+        if self.powered && self.div_bit && !div_bit {
+            self.step_frame_sequencer();
+        }
+        self.div_bit = div_bit;
+
+        for _ in 0..T_CYCLES_PER_M_CYCLE {
+            if self.powered {
+                self.pulse_1.tick();
+                self.pulse_2.tick();
+                self.wave.tick();
+                self.noise.tick();
+            }
+            self.sample_clock += self.sample_rate;
+            if self.sample_clock >= CLOCK_RATE {
+                self.sample_clock -= CLOCK_RATE;
+                self.push_sample();
+            }
+        }
+    }
+
+    /// Clocks the length counters at 256 Hz, the sweep at 128 Hz and the envelopes at 64 Hz
+    fn step_frame_sequencer(&mut self) {
+        if self.frame_step.is_multiple_of(2) {
+            self.pulse_1.clock_length();
+            self.pulse_2.clock_length();
+            self.wave.clock_length();
+            self.noise.clock_length();
+        }
+        if self.frame_step % 4 == 2 {
+            self.pulse_1.clock_sweep();
+        }
+        if self.frame_step == ENVELOPE_STEP {
+            self.pulse_1.clock_envelope();
+            self.pulse_2.clock_envelope();
+            self.noise.clock_envelope();
+        }
+        self.frame_step = (self.frame_step + 1) % FRAME_SEQUENCER_STEPS;
+    }
+
+    /// Whether the next frame sequencer step does not clock the length counters
+    fn length_first_half(&self) -> bool {
+        self.frame_step % 2 == 1
+    }
+
+    fn push_sample(&mut self) {
+        let outputs = [
+            self.pulse_1.output(),
+            self.pulse_2.output(),
+            self.wave.output(),
+            self.noise.output(),
+        ];
+        let mut mixed = [0.0; 2];
+        for (channel, output) in outputs.into_iter().enumerate() {
+            let Some(output) = output.filter(|_| self.enabled_channels[channel]) else {
+                continue;
+            };
+            if self.panning & (0x10 << channel) != 0 {
+                mixed[0] += dac(output);
+            }
+            if self.panning & (0x01 << channel) != 0 {
+                mixed[1] += dac(output);
+            }
+        }
+        let volumes = [self.master_volume >> 4 & 0x07, self.master_volume & 0x07];
+        let any_dac_enabled = outputs.iter().any(Option::is_some);
+        for (side, sample) in mixed.into_iter().enumerate() {
+            // Each channel adds up to 1, the master volume scales by 1/8 to 8/8
+            let sample = sample / 4.0 * (volumes[side] + 1) as f32 / 8.0;
+            let filtered = if any_dac_enabled {
+                let filtered = sample - self.capacitors[side];
+                self.capacitors[side] = sample - filtered * self.high_pass_factor;
+                filtered
+            } else {
+                0.0
+            };
+            self.samples.push(filtered);
+        }
+    }
+
+    /// Turning the APU off clears all registers but the wave RAM
+    fn power_off(&mut self) {
+        self.pulse_1 = PulseChannel::new(true);
+        self.pulse_2 = PulseChannel::new(false);
+        self.wave.power_off();
+        self.noise = NoiseChannel::default();
+        self.master_volume = 0;
+        self.panning = 0;
+        self.powered = false;
+    }
+
+    pub(crate) fn read(&self, address: u16) -> u8 {
+        let offset = address - SOUND_START;
+        let value = match address {
+            WAVE_RAM_START..=SOUND_END => return self.wave.read_ram(address - WAVE_RAM_START),
+            MASTER_VOLUME_ADDRESS => self.master_volume,
+            PANNING_ADDRESS => self.panning,
+            SOUND_CONTROL_ADDRESS => {
+                let active = [
+                    self.pulse_1.active(),
+                    self.pulse_2.active(),
+                    self.wave.active(),
+                    self.noise.active(),
+                ];
+                active
+                    .into_iter()
+                    .enumerate()
+                    .fold((self.powered as u8) << 7, |byte, (channel, active)| {
+                        byte | (active as u8) << channel
+                    })
+            }
+            _ => match offset / CHANNEL_REGISTERS {
+                0 => self.pulse_1.read(offset % CHANNEL_REGISTERS),
+                1 => self.pulse_2.read(offset % CHANNEL_REGISTERS),
+                2 => self.wave.read(offset % CHANNEL_REGISTERS),
+                3 => self.noise.read(offset % CHANNEL_REGISTERS),
+                _ => 0x00,
+            },
+        };
+        value | READ_MASKS[offset as usize]
+    }
+
+    pub(crate) fn write(&mut self, address: u16, byte: u8) {
+        match address {
+            WAVE_RAM_START..=SOUND_END => self.wave.write_ram(address - WAVE_RAM_START, byte),
+            SOUND_CONTROL_ADDRESS => {
+                let power = byte & POWER_BIT != 0;
+                if self.powered && !power {
+                    self.power_off();
+                } else if !self.powered && power {
+                    self.powered = true;
+                    self.frame_step = 0;
+                }
+            }
+            // Only NR52 and the wave RAM are writable while the APU is off
+            _ if !self.powered => {}
+            MASTER_VOLUME_ADDRESS => self.master_volume = byte,
+            PANNING_ADDRESS => self.panning = byte,
+            _ => {
+                let offset = address - SOUND_START;
+                let first_half = self.length_first_half();
+                let register = offset % CHANNEL_REGISTERS;
+                match offset / CHANNEL_REGISTERS {
+                    0 => self.pulse_1.write(register, byte, first_half),
+                    1 => self.pulse_2.write(register, byte, first_half),
+                    2 => self.wave.write(register, byte, first_half),
+                    3 => self.noise.write(register, byte, first_half),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the frame sequencer for `steps` steps by toggling the DIV bit that clocks it
+    fn step_frame_sequencer(apu: &mut Apu, steps: u8) {
+        for _ in 0..steps {
+            apu.tick(1 << FRAME_SEQUENCER_DIV_BIT);
+            apu.tick(0);
+        }
+    }
+
+    fn powered_apu() -> Apu {
+        let mut apu = Apu::default();
+        apu.write(SOUND_CONTROL_ADDRESS, 0x80);
+        apu
+    }
+
+    #[test]
+    fn read_masks() {
+        let mut apu = powered_apu();
+        for address in SOUND_START..WAVE_RAM_START {
+            if address != SOUND_CONTROL_ADDRESS {
+                apu.write(address, 0x00);
+            }
+        }
+        let registers: Vec<u8> = (SOUND_START..WAVE_RAM_START)
+            .map(|address| apu.read(address))
+            .collect();
+        assert_eq!(registers[..22], READ_MASKS[..22]);
+        assert_eq!(registers[22], 0xF0, "NR52 with the APU on");
+        assert!(registers[23..].iter().all(|&byte| byte == 0xFF));
+    }
+
+    #[test]
+    fn power_off_clears_registers() {
+        let mut apu = powered_apu();
+        apu.write(MASTER_VOLUME_ADDRESS, 0x77);
+        apu.write(0xFF12, 0xF3);
+        apu.write(WAVE_RAM_START, 0x12);
+        apu.write(SOUND_CONTROL_ADDRESS, 0x00);
+        assert_eq!(apu.read(SOUND_CONTROL_ADDRESS), 0x70);
+        assert_eq!(apu.read(MASTER_VOLUME_ADDRESS), 0x00);
+        assert_eq!(apu.read(0xFF12), 0x00);
+        assert_eq!(apu.read(WAVE_RAM_START), 0x12, "Wave RAM is kept");
+
+        apu.write(MASTER_VOLUME_ADDRESS, 0x77);
+        assert_eq!(apu.read(MASTER_VOLUME_ADDRESS), 0x00, "Ignored while off");
+    }
+
+    #[test]
+    fn trigger_needs_dac() {
+        let mut apu = powered_apu();
+        apu.write(0xFF19, TRIGGER_BIT);
+        assert_eq!(apu.read(SOUND_CONTROL_ADDRESS), 0xF0, "DAC is off");
+        apu.write(0xFF17, 0xF0);
+        apu.write(0xFF19, TRIGGER_BIT);
+        assert_eq!(apu.read(SOUND_CONTROL_ADDRESS), 0xF2);
+        apu.write(0xFF17, 0x00);
+        assert_eq!(apu.read(SOUND_CONTROL_ADDRESS), 0xF0, "Turning the DAC off");
+    }
+
+    #[test]
+    fn length_counter() {
+        let mut apu = powered_apu();
+        apu.write(0xFF12, 0xF0);
+        apu.write(0xFF11, 62);
+        // Step 0 is executed next, so enabling the length does not clock it
+        apu.write(0xFF14, TRIGGER_BIT | LENGTH_ENABLE_BIT);
+        step_frame_sequencer(&mut apu, 2);
+        assert_eq!(apu.read(SOUND_CONTROL_ADDRESS), 0xF1);
+        step_frame_sequencer(&mut apu, 1);
+        assert_eq!(apu.read(SOUND_CONTROL_ADDRESS), 0xF0, "Length expired");
+
+        // Now step 3 is next, the extra clock expires a length of 1 right away
+        apu.write(0xFF11, 63);
+        apu.write(0xFF14, TRIGGER_BIT);
+        assert_eq!(apu.read(SOUND_CONTROL_ADDRESS), 0xF1);
+        apu.write(0xFF14, LENGTH_ENABLE_BIT);
+        assert_eq!(apu.read(SOUND_CONTROL_ADDRESS), 0xF0);
+    }
+
+    #[test]
+    fn frame_sequencer_follows_div() {
+        let mut apu = powered_apu();
+        apu.write(0xFF12, 0xF0);
+        apu.write(0xFF11, 63);
+        apu.write(0xFF14, TRIGGER_BIT | LENGTH_ENABLE_BIT);
+        apu.tick(0x10);
+        apu.tick(0x1F);
+        assert_eq!(apu.read(SOUND_CONTROL_ADDRESS), 0xF1, "No falling edge");
+        apu.tick(0x20);
+        assert_eq!(apu.read(SOUND_CONTROL_ADDRESS), 0xF0);
+    }
+
+    #[test]
+    fn sweep_overflow() {
+        let mut apu = powered_apu();
+        apu.write(0xFF12, 0xF0);
+        // Period 1, addition, shift 1
+        apu.write(0xFF10, 0x11);
+        apu.write(0xFF13, 0x00);
+        apu.write(0xFF14, TRIGGER_BIT | 0x05);
+        assert_eq!(apu.read(SOUND_CONTROL_ADDRESS), 0xF1);
+        // 0x500 -> 0x780 -> overflow checked with 0xB40
+        step_frame_sequencer(&mut apu, 3);
+        assert_eq!(apu.read(SOUND_CONTROL_ADDRESS), 0xF0);
+
+        // Leaving subtraction mode after a calculation used it
+        apu.write(0xFF10, 0x19);
+        apu.write(0xFF14, TRIGGER_BIT | 0x05);
+        assert_eq!(apu.read(SOUND_CONTROL_ADDRESS), 0xF1);
+        apu.write(0xFF10, 0x11);
+        assert_eq!(apu.read(SOUND_CONTROL_ADDRESS), 0xF0);
+    }
+
+    #[test]
+    fn samples_follow_cycles() {
+        let mut apu = Apu::new(32_768);
+        // One sample every 128 T-cycles, stereo
+        for _ in 0..128 {
+            apu.tick(0);
+        }
+        assert_eq!(apu.take_samples().len(), 8);
+        assert!(apu.take_samples().is_empty());
+    }
+
+    #[test]
+    fn panning_and_master_volume() {
+        let mut apu = Apu::new(CLOCK_RATE / 4);
+        apu.write(SOUND_CONTROL_ADDRESS, 0x80);
+        apu.write(MASTER_VOLUME_ADDRESS, 0x70);
+        // Channel 2 left only, at full volume with 75% duty
+        apu.write(PANNING_ADDRESS, 0x20);
+        apu.write(0xFF16, 0xC0);
+        apu.write(0xFF17, 0xF0);
+        apu.write(0xFF19, TRIGGER_BIT);
+        for _ in 0..0x1000 {
+            apu.tick(0);
+        }
+        let samples = apu.take_samples();
+        let (left, right): (Vec<f32>, Vec<f32>) = samples
+            .chunks(2)
+            .map(|sample| (sample[0], sample[1]))
+            .unzip();
+        assert!(left.iter().any(|&sample| sample > 0.1));
+        assert!(left.iter().any(|&sample| sample < -0.1));
+        assert!(right.iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn muted_channel() {
+        let mut apu = Apu::new(CLOCK_RATE / 4);
+        apu.write(SOUND_CONTROL_ADDRESS, 0x80);
+        apu.write(MASTER_VOLUME_ADDRESS, 0x77);
+        apu.write(PANNING_ADDRESS, 0xFF);
+        apu.write(0xFF16, 0xC0);
+        apu.write(0xFF17, 0xF0);
+        apu.write(0xFF19, TRIGGER_BIT);
+        apu.set_channel_enabled(1, false);
+        for _ in 0..0x1000 {
+            apu.tick(0);
+        }
+        assert!(apu.take_samples().iter().all(|&sample| sample == 0.0));
+        assert_eq!(apu.read(SOUND_CONTROL_ADDRESS), 0xF2, "Still running");
+    }
+}
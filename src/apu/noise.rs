@@ -0,0 +1,182 @@
+use super::{Envelope, LengthCounter};
+
+const MAX_LENGTH: u16 = 64;
+/// T-cycles between LFSR clocks before the shift of NR43, indexed by its divisor bits
+const DIVISORS: [u32; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
+/// With a shift of 14 or 15 the LFSR is not clocked at all
+const MAX_CLOCK_SHIFT: u8 = 13;
+/// Bit 3 of NR43 shortens the LFSR to 7 bits, which makes the noise periodic
+const SHORT_MODE_BIT: u8 = 0b0000_1000;
+const LFSR_TRIGGER_VALUE: u16 = 0x7FFF;
+
+/// Channel 4, which outputs the lowest bit of a linear feedback shift register
+#[derive(Debug)]
+pub(super) struct NoiseChannel {
+    length: LengthCounter,
+    envelope: Envelope,
+    /// NR43
+    register: u8,
+    /// T-cycles until the next LFSR clock
+    timer: u32,
+    lfsr: u16,
+    active: bool,
+}
+
+impl Default for NoiseChannel {
+    fn default() -> Self {
+        Self {
+            length: LengthCounter::new(MAX_LENGTH),
+            envelope: Envelope::default(),
+            register: 0,
+            timer: 0,
+            lfsr: 0,
+            active: false,
+        }
+    }
+}
+
+impl NoiseChannel {
+    pub(super) fn active(&self) -> bool {
+        self.active
+    }
+
+    fn shift(&self) -> u8 {
+        self.register >> 4
+    }
+
+    fn period(&self) -> u32 {
+        DIVISORS[(self.register & 0b0000_0111) as usize] << self.shift()
+    }
+
+    /// Advances by one T-cycle
+    pub(super) fn tick(&mut self) {
+        if self.timer > 1 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.period();
+        if self.shift() <= MAX_CLOCK_SHIFT {
+            self.clock_lfsr();
+        }
+    }
+
+    /// Shifts the XOR of the lowest two bits into bit 14, and bit 6 in short mode
+    fn clock_lfsr(&mut self) {
+        let bit = (self.lfsr ^ self.lfsr >> 1) & 1;
+        self.lfsr = self.lfsr >> 1 | bit << 14;
+        if self.register & SHORT_MODE_BIT != 0 {
+            self.lfsr = self.lfsr & !(1 << 6) | bit << 6;
+        }
+    }
+
+    /// The digital output of 0-15, `None` while the DAC is off
+    pub(super) fn output(&self) -> Option<u8> {
+        if !self.envelope.dac_enabled() {
+            return None;
+        }
+        Some(if self.active && self.lfsr & 1 == 0 {
+            self.envelope.volume
+        } else {
+            0
+        })
+    }
+
+    pub(super) fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.active = false;
+        }
+    }
+
+    pub(super) fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    fn trigger(&mut self) {
+        self.active = self.envelope.dac_enabled();
+        self.timer = self.period();
+        self.lfsr = LFSR_TRIGGER_VALUE;
+        self.envelope.trigger();
+    }
+
+    /// Reads NR40-NR44 by their index, the APU masks the bits that are not readable
+    pub(super) fn read(&self, register: u16) -> u8 {
+        match register {
+            2 => self.envelope.register,
+            3 => self.register,
+            4 => (self.length.enabled as u8) << 6,
+            _ => 0x00,
+        }
+    }
+
+    pub(super) fn write(&mut self, register: u16, byte: u8, length_first_half: bool) {
+        match register {
+            1 => self.length.load(byte & 0b0011_1111),
+            2 => {
+                self.envelope.register = byte;
+                if !self.envelope.dac_enabled() {
+                    self.active = false;
+                }
+            }
+            3 => self.register = byte,
+            4 => {
+                if self.length.write_control(byte, length_first_half) {
+                    self.active = false;
+                }
+                if byte & super::TRIGGER_BIT != 0 {
+                    self.trigger();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lfsr_sequence() {
+        let mut noise = NoiseChannel {
+            lfsr: LFSR_TRIGGER_VALUE,
+            ..Default::default()
+        };
+        let mut lfsr = Vec::new();
+        for _ in 0..3 {
+            noise.clock_lfsr();
+            lfsr.push(noise.lfsr);
+        }
+        // Only zeros are shifted in until the first 0 reaches the lowest bits
+        assert_eq!(lfsr, [0x3FFF, 0x1FFF, 0x0FFF]);
+
+        // The 7-bit LFSR repeats every 127 clocks
+        noise.register = SHORT_MODE_BIT;
+        noise.lfsr = LFSR_TRIGGER_VALUE;
+        let outputs: Vec<u16> = (0..254)
+            .map(|_| {
+                noise.clock_lfsr();
+                noise.lfsr & 1
+            })
+            .collect();
+        assert_eq!(outputs[..127], outputs[127..]);
+        assert!(outputs.contains(&0) && outputs.contains(&1));
+    }
+
+    #[test]
+    fn clock_rate() {
+        let mut noise = NoiseChannel {
+            register: 0x21,
+            ..Default::default()
+        };
+        assert_eq!(noise.period(), 64, "Divisor 16, shift 2");
+        noise.register = 0xE0;
+        noise.lfsr = LFSR_TRIGGER_VALUE;
+        for _ in 0..0x10000 {
+            noise.tick();
+        }
+        assert_eq!(
+            noise.lfsr, LFSR_TRIGGER_VALUE,
+            "Not clocked with a shift of 14"
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use super::{Envelope, LengthCounter, write_frequency};
+
+/// Waveforms selected by the duty bits of NRx1, played from the most significant bit
+const DUTY_PATTERNS: [u8; 4] = [0b0000_0001, 0b1000_0001, 0b1000_0111, 0b0111_1110];
+const DUTY_STEPS: u8 = 8;
+const MAX_LENGTH: u16 = 64;
+/// Frequencies are 11 bits, the period is the distance to this value
+const FREQUENCY_LIMIT: u16 = 2048;
+const T_CYCLES_PER_PERIOD_UNIT: u16 = 4;
+/// A sweep period of 0 reloads the timer with 8
+const SWEEP_ZERO_PERIOD: u8 = 8;
+const SWEEP_NEGATE_BIT: u8 = 0b0000_1000;
+
+/// Frequency sweep of channel 1 (NR10), clocked at 128 Hz
+#[derive(Debug, Default)]
+struct Sweep {
+    register: u8,
+    enabled: bool,
+    shadow_frequency: u16,
+    timer: u8,
+    /// Set when a calculation subtracted since the last trigger, clearing the negate bit
+    /// afterwards disables the channel
+    negated: bool,
+}
+
+impl Sweep {
+    fn period(&self) -> u8 {
+        self.register >> 4 & 0b0000_0111
+    }
+
+    fn shift(&self) -> u8 {
+        self.register & 0b0000_0111
+    }
+
+    fn negate(&self) -> bool {
+        self.register & SWEEP_NEGATE_BIT != 0
+    }
+
+    fn reload_timer(&mut self) {
+        self.timer = match self.period() {
+            0 => SWEEP_ZERO_PERIOD,
+            period => period,
+        };
+    }
+
+    /// The next frequency, `None` if it overflows, which disables the channel
+    fn calculate(&mut self) -> Option<u16> {
+        let delta = self.shadow_frequency >> self.shift();
+        let frequency = if self.negate() {
+            self.negated = true;
+            self.shadow_frequency - delta
+        } else {
+            self.shadow_frequency + delta
+        };
+        (frequency < FREQUENCY_LIMIT).then_some(frequency)
+    }
+}
+
+/// Square wave channels 1 (with sweep) and 2
+#[derive(Debug)]
+pub(super) struct PulseChannel {
+    sweep: Option<Sweep>,
+    length: LengthCounter,
+    envelope: Envelope,
+    duty: u8,
+    frequency: u16,
+    /// T-cycles until the next duty step
+    timer: u16,
+    duty_step: u8,
+    active: bool,
+}
+
+impl PulseChannel {
+    pub(super) fn new(with_sweep: bool) -> Self {
+        Self {
+            sweep: with_sweep.then(Sweep::default),
+            length: LengthCounter::new(MAX_LENGTH),
+            envelope: Envelope::default(),
+            duty: 0,
+            frequency: 0,
+            timer: 0,
+            duty_step: 0,
+            active: false,
+        }
+    }
+
+    pub(super) fn active(&self) -> bool {
+        self.active
+    }
+
+    fn period(&self) -> u16 {
+        (FREQUENCY_LIMIT - self.frequency) * T_CYCLES_PER_PERIOD_UNIT
+    }
+
+    /// Advances by one T-cycle
+    pub(super) fn tick(&mut self) {
+        if self.timer > 1 {
+            self.timer -= 1;
+        } else {
+            self.timer = self.period();
+            self.duty_step = (self.duty_step + 1) % DUTY_STEPS;
+        }
+    }
+
+    /// The digital output of 0-15, `None` while the DAC is off
+    pub(super) fn output(&self) -> Option<u8> {
+        if !self.envelope.dac_enabled() {
+            return None;
+        }
+        let high = DUTY_PATTERNS[self.duty as usize] >> (DUTY_STEPS - 1 - self.duty_step) & 1 != 0;
+        Some(if self.active && high {
+            self.envelope.volume
+        } else {
+            0
+        })
+    }
+
+    pub(super) fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.active = false;
+        }
+    }
+
+    pub(super) fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub(super) fn clock_sweep(&mut self) {
+        let Some(sweep) = self.sweep.as_mut() else {
+            return;
+        };
+        sweep.timer = sweep.timer.saturating_sub(1);
+        if sweep.timer > 0 {
+            return;
+        }
+        sweep.reload_timer();
+        if !sweep.enabled || sweep.period() == 0 {
+            return;
+        }
+        match sweep.calculate() {
+            None => self.active = false,
+            Some(frequency) if sweep.shift() != 0 => {
+                self.frequency = frequency;
+                sweep.shadow_frequency = frequency;
+                // The new frequency is checked for an overflow right away
+                if sweep.calculate().is_none() {
+                    self.active = false;
+                }
+            }
+            Some(_) => {}
+        }
+    }
+
+    fn trigger(&mut self) {
+        self.active = self.envelope.dac_enabled();
+        self.timer = self.period();
+        self.envelope.trigger();
+        if let Some(sweep) = self.sweep.as_mut() {
+            sweep.shadow_frequency = self.frequency;
+            sweep.negated = false;
+            sweep.reload_timer();
+            sweep.enabled = sweep.period() != 0 || sweep.shift() != 0;
+            if sweep.shift() != 0 && sweep.calculate().is_none() {
+                self.active = false;
+            }
+        }
+    }
+
+    /// Reads NRx0-NRx4 by their index, the APU masks the bits that are not readable
+    pub(super) fn read(&self, register: u16) -> u8 {
+        match register {
+            0 => self.sweep.as_ref().map_or(0x00, |sweep| sweep.register),
+            1 => self.duty << 6,
+            2 => self.envelope.register,
+            4 => (self.length.enabled as u8) << 6,
+            _ => 0x00,
+        }
+    }
+
+    pub(super) fn write(&mut self, register: u16, byte: u8, length_first_half: bool) {
+        match register {
+            0 => {
+                if let Some(sweep) = self.sweep.as_mut() {
+                    sweep.register = byte;
+                    if sweep.negated && !sweep.negate() {
+                        self.active = false;
+                    }
+                }
+            }
+            1 => {
+                self.duty = byte >> 6;
+                self.length.load(byte & 0b0011_1111);
+            }
+            2 => {
+                self.envelope.register = byte;
+                if !self.envelope.dac_enabled() {
+                    self.active = false;
+                }
+            }
+            3 => write_frequency(&mut self.frequency, register, byte),
+            _ => {
+                write_frequency(&mut self.frequency, register, byte);
+                if self.length.write_control(byte, length_first_half) {
+                    self.active = false;
+                }
+                if byte & super::TRIGGER_BIT != 0 {
+                    self.trigger();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use super::{LengthCounter, write_frequency};
+
+const MAX_LENGTH: u16 = 256;
+const WAVE_RAM_SIZE: usize = 16;
+/// Each byte of the wave RAM holds two 4-bit samples, the upper one is played first
+const SAMPLES: u8 = 32;
+const FREQUENCY_LIMIT: u16 = 2048;
+const T_CYCLES_PER_PERIOD_UNIT: u16 = 2;
+/// Right shifts of the samples for the output levels of NR32: mute, 100%, 50% and 25%
+const VOLUME_SHIFTS: [u8; 4] = [4, 0, 1, 2];
+const DAC_ENABLE_BIT: u8 = 0b1000_0000;
+
+/// Channel 3, which plays the 32 samples of the wave RAM
+#[derive(Debug)]
+pub(super) struct WaveChannel {
+    ram: [u8; WAVE_RAM_SIZE],
+    dac_enabled: bool,
+    length: LengthCounter,
+    /// Output level bits of NR32
+    volume: u8,
+    frequency: u16,
+    /// T-cycles until the next sample
+    timer: u16,
+    position: u8,
+    /// Last sample read from the wave RAM, triggering does not refresh it
+    sample: u8,
+    active: bool,
+}
+
+impl Default for WaveChannel {
+    fn default() -> Self {
+        Self {
+            ram: [0; WAVE_RAM_SIZE],
+            dac_enabled: false,
+            length: LengthCounter::new(MAX_LENGTH),
+            volume: 0,
+            frequency: 0,
+            timer: 0,
+            position: 0,
+            sample: 0,
+            active: false,
+        }
+    }
+}
+
+impl WaveChannel {
+    /// Clears the registers, the wave RAM keeps its contents
+    pub(super) fn power_off(&mut self) {
+        *self = Self {
+            ram: self.ram,
+            ..Self::default()
+        };
+    }
+
+    pub(super) fn active(&self) -> bool {
+        self.active
+    }
+
+    fn period(&self) -> u16 {
+        (FREQUENCY_LIMIT - self.frequency) * T_CYCLES_PER_PERIOD_UNIT
+    }
+
+    /// Advances by one T-cycle
+    pub(super) fn tick(&mut self) {
+        if self.timer > 1 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.period();
+        if self.active {
+            self.position = (self.position + 1) % SAMPLES;
+            let byte = self.ram[self.position as usize / 2];
+            self.sample = if self.position.is_multiple_of(2) {
+                byte >> 4
+            } else {
+                byte & 0x0F
+            };
+        }
+    }
+
+    /// The digital output of 0-15, `None` while the DAC is off
+    pub(super) fn output(&self) -> Option<u8> {
+        if !self.dac_enabled {
+            return None;
+        }
+        Some(if self.active {
+            self.sample >> VOLUME_SHIFTS[self.volume as usize]
+        } else {
+            0
+        })
+    }
+
+    pub(super) fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.active = false;
+        }
+    }
+
+    fn trigger(&mut self) {
+        self.active = self.dac_enabled;
+        self.timer = self.period();
+        self.position = 0;
+    }
+
+    pub(super) fn read_ram(&self, offset: u16) -> u8 {
+        self.ram[offset as usize]
+    }
+
+    pub(super) fn write_ram(&mut self, offset: u16, byte: u8) {
+        self.ram[offset as usize] = byte;
+    }
+
+    /// Reads NR30-NR34 by their index, the APU masks the bits that are not readable
+    pub(super) fn read(&self, register: u16) -> u8 {
+        match register {
+            0 => (self.dac_enabled as u8) << 7,
+            2 => self.volume << 5,
+            4 => (self.length.enabled as u8) << 6,
+            _ => 0x00,
+        }
+    }
+
+    pub(super) fn write(&mut self, register: u16, byte: u8, length_first_half: bool) {
+        match register {
+            0 => {
+                self.dac_enabled = byte & DAC_ENABLE_BIT != 0;
+                if !self.dac_enabled {
+                    self.active = false;
+                }
+            }
+            1 => self.length.load(byte),
+            2 => self.volume = byte >> 5 & 0b0000_0011,
+            3 => write_frequency(&mut self.frequency, register, byte),
+            _ => {
+                write_frequency(&mut self.frequency, register, byte);
+                if self.length.write_control(byte, length_first_half) {
+                    self.active = false;
+                }
+                if byte & super::TRIGGER_BIT != 0 {
+                    self.trigger();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,639 @@
+mod header;
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+mod rtc;
+
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use header::{HEADER_END, Header, Mbc};
+use mbc1::Mbc1;
+use mbc2::Mbc2;
+use mbc3::Mbc3;
+use mbc5::Mbc5;
+use rtc::{RTC_SAVE_SIZE, Rtc, SystemTimeSource, TimeSource};
+
+/// Extension of the file next to the ROM holding the battery-backed RAM
+const SAVE_EXTENSION: &str = "sav";
+/// The save is written to this file first and then renamed over the save file
+const SAVE_TEMPORARY_EXTENSION: &str = "sav.tmp";
+
+/// Value read from addresses that are not backed by the cartridge
+const OPEN_BUS: u8 = 0xFF;
+
+const ROM_BANK_SIZE: usize = 0x4000;
+const RAM_BANK_SIZE: usize = 0x2000;
+/// Memory bank controllers enable RAM when a value with this lower nibble is written to them
+const RAM_ENABLE_VALUE: u8 = 0x0A;
+
+#[derive(Debug)]
+pub(crate) enum CartridgeError {
+    Io(std::io::Error),
+    /// The file ends before the end of the header
+    Truncated {
+        length: usize,
+    },
+    /// The logo doesn't match the one the boot ROM checks
+    InvalidLogo,
+    HeaderChecksum {
+        expected: u8,
+        computed: u8,
+    },
+    UnknownCartridgeType(u8),
+    UnknownRomSize(u8),
+    UnknownRamSize(u8),
+    /// The cartridge type has no external RAM, but the header declares a RAM size
+    RamSizeMismatch {
+        cartridge_type: u8,
+        ram_size: usize,
+    },
+    /// The file is shorter than the ROM size in the header, longer overdumps are accepted
+    RomSizeMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    UnsupportedMbc(Mbc),
+    /// The save data doesn't fit the external RAM and RTC of the cartridge
+    SaveSizeMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    /// The save file exists but can't be read or written
+    SaveIo {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+}
+
+impl Display for CartridgeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CartridgeError::Io(error) => write!(f, "Could not read the ROM: {error}"),
+            CartridgeError::Truncated { length } => write!(
+                f,
+                "ROM is truncated, {length} bytes are too short for the header"
+            ),
+            CartridgeError::InvalidLogo => write!(f, "Nintendo logo in the header is invalid"),
+            CartridgeError::HeaderChecksum { expected, computed } => write!(
+                f,
+                "Header checksum is 0x{expected:02X} but the header sums up to 0x{computed:02X}"
+            ),
+            CartridgeError::UnknownCartridgeType(code) => {
+                write!(f, "Unknown cartridge type 0x{code:02X}")
+            }
+            CartridgeError::UnknownRomSize(code) => write!(f, "Unknown ROM size 0x{code:02X}"),
+            CartridgeError::UnknownRamSize(code) => write!(f, "Unknown RAM size 0x{code:02X}"),
+            CartridgeError::RamSizeMismatch {
+                cartridge_type,
+                ram_size,
+            } => write!(
+                f,
+                "Cartridge type 0x{cartridge_type:02X} has no RAM but the header declares \
+                 {ram_size} bytes"
+            ),
+            CartridgeError::RomSizeMismatch { expected, actual } => write!(
+                f,
+                "Header declares {expected} bytes of ROM but the file has {actual} bytes"
+            ),
+            CartridgeError::UnsupportedMbc(mbc) => {
+                write!(f, "Memory bank controller {mbc:?} is not supported")
+            }
+            CartridgeError::SaveSizeMismatch { expected, actual } => write!(
+                f,
+                "Save data has {actual} bytes but the cartridge stores {expected} bytes"
+            ),
+            CartridgeError::SaveIo { path, error } => {
+                write!(
+                    f,
+                    "Could not access the save file {}: {error}",
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CartridgeError::Io(error) | CartridgeError::SaveIo { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for CartridgeError {
+    fn from(error: std::io::Error) -> Self {
+        CartridgeError::Io(error)
+    }
+}
+
+/// Offset of `address` within `bank` of a memory that is `len` bytes long
+///
+/// Unconnected address lines make banks beyond the end of the memory wrap around.
+fn banked_offset(len: usize, bank: usize, bank_size: usize, address: u16) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    Some((bank * bank_size + address as usize % bank_size) % len)
+}
+
+/// Memory bank controller selected from the cartridge type
+enum Controller {
+    /// ROM and RAM are wired directly to the bus
+    RomOnly,
+    Mbc1(Mbc1),
+    Mbc2(Mbc2),
+    Mbc3(Mbc3),
+    Mbc5(Mbc5),
+}
+
+/// Game Pak holding the ROM (0x0000-0x7FFF) and the optional external RAM (0xA000-0xBFFF)
+pub(crate) struct Cartridge {
+    header: Header,
+    controller: Controller,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    /// `.sav` file the RAM of battery-backed cartridges is flushed to
+    save_path: Option<PathBuf>,
+    /// Whether the RAM or RTC was written since the last flush
+    save_dirty: bool,
+}
+
+impl Cartridge {
+    /// Reads a `.gb` file and validates it, battery-backed RAM is restored from the `.sav` file
+    /// next to it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or isn't a valid ROM, see `Cartridge::new`, or
+    /// if the save file can't be read or doesn't fit the cartridge
+    pub(crate) fn load(path: impl AsRef<Path>) -> Result<Self, CartridgeError> {
+        let path = path.as_ref();
+        let mut cartridge = Self::new(std::fs::read(path)?)?;
+        if cartridge.header.cartridge_type.battery {
+            let save_path = path.with_extension(SAVE_EXTENSION);
+            match std::fs::read(&save_path) {
+                Ok(data) => cartridge.load_save_data(&data)?,
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+                Err(error) => {
+                    return Err(CartridgeError::SaveIo {
+                        path: save_path,
+                        error,
+                    });
+                }
+            }
+            cartridge.save_path = Some(save_path);
+        }
+        Ok(cartridge)
+    }
+
+    /// Writes the battery-backed RAM to the `.sav` file if it changed since the last flush
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the save file can't be written
+    pub(crate) fn flush_save(&mut self) -> Result<(), CartridgeError> {
+        let Some(path) = self.save_path.as_ref().filter(|_| self.save_dirty) else {
+            return Ok(());
+        };
+        // A crash or a full disk while writing leaves the previous save intact
+        let temporary_path = path.with_extension(SAVE_TEMPORARY_EXTENSION);
+        std::fs::write(&temporary_path, self.save_data())
+            .and_then(|()| std::fs::rename(&temporary_path, path))
+            .map_err(|error| CartridgeError::SaveIo {
+                path: path.clone(),
+                error,
+            })?;
+        self.save_dirty = false;
+        Ok(())
+    }
+
+    /// Parses the header and sizes the external RAM after it, an RTC counts the host's time
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ROM is truncated, the logo or the header checksum doesn't match,
+    /// the ROM is shorter than its header declares, RAM is declared for a cartridge type without
+    /// RAM or the cartridge needs an unsupported memory bank controller. Like the hardware it accepts a wrong global checksum, see
+    /// `Header::global_checksum_valid`
+    pub(crate) fn new(rom: Vec<u8>) -> Result<Self, CartridgeError> {
+        Self::with_time_source(rom, Box::new(SystemTimeSource))
+    }
+
+    /// Like `Cartridge::new`, but an RTC counts the time of `time_source`
+    ///
+    /// # Errors
+    ///
+    /// See `Cartridge::new`
+    pub(crate) fn with_time_source(
+        rom: Vec<u8>,
+        time_source: Box<dyn TimeSource>,
+    ) -> Result<Self, CartridgeError> {
+        if rom.len() < HEADER_END {
+            return Err(CartridgeError::Truncated { length: rom.len() });
+        }
+        let header = Header::parse(&rom)?;
+        if rom.len() < header.rom_size {
+            return Err(CartridgeError::RomSizeMismatch {
+                expected: header.rom_size,
+                actual: rom.len(),
+            });
+        }
+        let controller = match header.cartridge_type.mbc {
+            Mbc::None => Controller::RomOnly,
+            Mbc::Mbc1 => Controller::Mbc1(Mbc1::new(&rom)),
+            Mbc::Mbc2 => Controller::Mbc2(Mbc2::new()),
+            Mbc::Mbc3 => {
+                let rtc = header.cartridge_type.timer.then(|| Rtc::new(time_source));
+                Controller::Mbc3(Mbc3::new(rtc))
+            }
+            Mbc::Mbc5 => Controller::Mbc5(Mbc5::new(header.cartridge_type.rumble)),
+            mbc => return Err(CartridgeError::UnsupportedMbc(mbc)),
+        };
+        let ram_size = match controller {
+            Controller::Mbc2(_) => mbc2::RAM_SIZE,
+            _ => header.ram_size,
+        };
+
+        Ok(Self {
+            controller,
+            ram: vec![0; ram_size],
+            header,
+            rom,
+            save_path: None,
+            save_dirty: false,
+        })
+    }
+
+    pub(crate) fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Whether the rumble motor of the cartridge is running
+    pub(crate) fn rumble(&self) -> bool {
+        match &self.controller {
+            Controller::Mbc5(mbc) => mbc.rumble(),
+            _ => false,
+        }
+    }
+
+    fn rtc(&self) -> Option<&Rtc> {
+        match &self.controller {
+            Controller::Mbc3(mbc) => mbc.rtc(),
+            _ => None,
+        }
+    }
+
+    /// External RAM followed by the RTC state if the cartridge has a clock
+    pub(crate) fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+        if let Some(rtc) = self.rtc() {
+            data.extend_from_slice(&rtc.save());
+        }
+        data
+    }
+
+    /// Restores data written by `Cartridge::save_data`, the RTC state is optional
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is neither the size of the external RAM nor of the external
+    /// RAM and the RTC state
+    pub(crate) fn load_save_data(&mut self, data: &[u8]) -> Result<(), CartridgeError> {
+        let mismatch = CartridgeError::SaveSizeMismatch {
+            expected: self.save_data_size(),
+            actual: data.len(),
+        };
+        if data.len() < self.ram.len() {
+            return Err(mismatch);
+        }
+        let (ram, footer) = data.split_at(self.ram.len());
+        let rtc = match &mut self.controller {
+            Controller::Mbc3(mbc) => mbc.rtc_mut(),
+            _ => None,
+        };
+        let loaded = match rtc {
+            _ if footer.is_empty() => true,
+            Some(rtc) => rtc.load(footer),
+            None => false,
+        };
+        if !loaded {
+            return Err(mismatch);
+        }
+        self.ram.copy_from_slice(ram);
+        Ok(())
+    }
+
+    fn save_data_size(&self) -> usize {
+        self.ram.len() + self.rtc().map_or(0, |_| RTC_SAVE_SIZE)
+    }
+
+    pub(crate) fn read_rom(&self, address: u16) -> u8 {
+        match &self.controller {
+            Controller::RomOnly => self.rom.get(address as usize).copied().unwrap_or(OPEN_BUS),
+            Controller::Mbc1(mbc) => mbc.read_rom(&self.rom, address),
+            Controller::Mbc2(mbc) => mbc.read_rom(&self.rom, address),
+            Controller::Mbc3(mbc) => mbc.read_rom(&self.rom, address),
+            Controller::Mbc5(mbc) => mbc.read_rom(&self.rom, address),
+        }
+    }
+
+    /// ROM is read only, writes only reach memory bank controllers
+    pub(crate) fn write_rom(&mut self, address: u16, byte: u8) {
+        match &mut self.controller {
+            Controller::RomOnly => {}
+            Controller::Mbc1(mbc) => mbc.write_rom(address, byte),
+            Controller::Mbc2(mbc) => mbc.write_rom(address, byte),
+            Controller::Mbc3(mbc) => mbc.write_rom(address, byte),
+            Controller::Mbc5(mbc) => mbc.write_rom(address, byte),
+        }
+    }
+
+    /// `address` is relative to the start of external RAM
+    pub(crate) fn read_ram(&self, address: u16) -> u8 {
+        match &self.controller {
+            Controller::RomOnly => self.ram.get(address as usize).copied().unwrap_or(OPEN_BUS),
+            Controller::Mbc1(mbc) => mbc.read_ram(&self.ram, address),
+            Controller::Mbc2(mbc) => mbc.read_ram(&self.ram, address),
+            Controller::Mbc3(mbc) => mbc.read_ram(&self.ram, address),
+            Controller::Mbc5(mbc) => mbc.read_ram(&self.ram, address),
+        }
+    }
+
+    /// `address` is relative to the start of external RAM
+    pub(crate) fn write_ram(&mut self, address: u16, byte: u8) {
+        let changed = match &mut self.controller {
+            Controller::RomOnly => self
+                .ram
+                .get_mut(address as usize)
+                .is_some_and(|ram| std::mem::replace(ram, byte) != byte),
+            Controller::Mbc1(mbc) => mbc.write_ram(&mut self.ram, address, byte),
+            Controller::Mbc2(mbc) => mbc.write_ram(&mut self.ram, address, byte),
+            Controller::Mbc3(mbc) => mbc.write_ram(&mut self.ram, address, byte),
+            Controller::Mbc5(mbc) => mbc.write_ram(&mut self.ram, address, byte),
+        };
+        self.save_dirty |= changed;
+    }
+}
+
+/// Builds a ROM filled with `fill` and a valid header for the given header codes
+#[cfg(test)]
+pub(crate) fn test_rom(cartridge_type: u8, rom_size: u8, ram_size: u8, fill: u8) -> Vec<u8> {
+    let mut rom = vec![fill; 0x8000 << rom_size];
+    rom[0x0100..HEADER_END].fill(0);
+    rom[0x0104..=0x0133].copy_from_slice(&header::NINTENDO_LOGO);
+    rom[0x0147] = cartridge_type;
+    rom[0x0148] = rom_size;
+    rom[0x0149] = ram_size;
+    rom[0x014D] = header::compute_header_checksum(&rom);
+    let global_checksum = header::compute_global_checksum(&rom);
+    rom[0x014E..=0x014F].copy_from_slice(&global_checksum.to_be_bytes());
+    rom
+}
+
+/// Builds a ROM where every byte holds the number of its bank, except that the byte at offset 1
+/// holds the upper byte of the number to tell banks beyond 255 apart
+#[cfg(test)]
+pub(crate) fn numbered_banks(banks: usize) -> Vec<u8> {
+    (0..banks)
+        .flat_map(|bank| {
+            let mut data = [bank as u8; ROM_BANK_SIZE];
+            data[1] = (bank >> 8) as u8;
+            data
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::header::{CgbSupport, Licensee};
+    use super::rtc::tests::ManualTimeSource;
+    use super::*;
+
+    const SIMPLE_ADD: &[u8] = include_bytes!("../test_roms/simple_add.gb");
+    const INTERRUPTS: &[u8] = include_bytes!("../test_roms/interrupts.gb");
+
+    #[test]
+    fn loads_test_roms() {
+        for rom in [SIMPLE_ADD, INTERRUPTS] {
+            let cartridge = Cartridge::new(rom.to_vec()).unwrap();
+            let header = cartridge.header();
+            assert_eq!(header.title, "");
+            assert_eq!(header.cgb_support, CgbSupport::None);
+            assert!(!header.sgb_support);
+            assert_eq!(header.cartridge_type.mbc, Mbc::None);
+            assert_eq!(header.rom_size, 0x8000);
+            assert_eq!(header.ram_size, 0);
+            assert_eq!(header.licensee, Licensee::Old(0x00));
+            assert_eq!(header.version, 0);
+            assert_eq!(cartridge.read_rom(0x0104), 0xCE);
+        }
+    }
+
+    #[test]
+    fn parses_header_fields() {
+        let mut rom = test_rom(0x08, 0x00, 0x02, 0x00);
+        rom[0x0134..0x0139].copy_from_slice(b"TETRA");
+        rom[0x0143] = 0x80;
+        rom[0x0144..=0x0145].copy_from_slice(b"01");
+        rom[0x0146] = 0x03;
+        rom[0x014B] = 0x33;
+        rom[0x014C] = 0x02;
+        rom[0x014D] = header::compute_header_checksum(&rom);
+        let global_checksum = header::compute_global_checksum(&rom);
+        rom[0x014E..=0x014F].copy_from_slice(&global_checksum.to_be_bytes());
+
+        let header = Cartridge::new(rom).unwrap().header;
+        assert_eq!(header.title, "TETRA");
+        assert_eq!(header.cgb_support, CgbSupport::Compatible);
+        assert!(header.sgb_support);
+        assert!(header.cartridge_type.ram);
+        assert_eq!(header.ram_size, 0x2000);
+        assert_eq!(header.licensee, Licensee::New(*b"01"));
+        assert_eq!(header.version, 0x02);
+    }
+
+    #[test]
+    fn external_ram_is_sized_from_header() {
+        let mut cartridge = Cartridge::new(test_rom(0x08, 0x00, 0x02, 0x00)).unwrap();
+        cartridge.write_ram(0x1FFF, 0x42);
+        assert_eq!(cartridge.read_ram(0x1FFF), 0x42);
+        cartridge.write_ram(0x2000, 0x42);
+        assert_eq!(cartridge.read_ram(0x2000), OPEN_BUS);
+    }
+
+    #[test]
+    fn rejects_invalid_roms() {
+        assert!(matches!(
+            Cartridge::new(SIMPLE_ADD[..0x014F].to_vec()),
+            Err(CartridgeError::Truncated { length: 0x014F })
+        ));
+        assert!(matches!(
+            Cartridge::new(SIMPLE_ADD[..0x4000].to_vec()),
+            Err(CartridgeError::RomSizeMismatch {
+                expected: 0x8000,
+                actual: 0x4000
+            })
+        ));
+
+        let mut rom = SIMPLE_ADD.to_vec();
+        rom[0x0110] ^= 0xFF;
+        assert!(matches!(
+            Cartridge::new(rom),
+            Err(CartridgeError::InvalidLogo)
+        ));
+
+        let mut rom = SIMPLE_ADD.to_vec();
+        rom[0x014C] = 0x01;
+        assert!(matches!(
+            Cartridge::new(rom),
+            Err(CartridgeError::HeaderChecksum {
+                expected: 0xE7,
+                computed: 0xE6
+            })
+        ));
+
+        assert!(matches!(
+            Cartridge::new(test_rom(0x04, 0x00, 0x00, 0x00)),
+            Err(CartridgeError::UnknownCartridgeType(0x04))
+        ));
+        for cartridge_type in [0x00, 0x01, 0x05, 0x0F, 0x11, 0x19] {
+            assert!(matches!(
+                Cartridge::new(test_rom(cartridge_type, 0x01, 0x02, 0x00)),
+                Err(CartridgeError::RamSizeMismatch {
+                    cartridge_type: found,
+                    ram_size: 0x2000
+                }) if found == cartridge_type
+            ));
+        }
+        assert!(matches!(
+            Cartridge::new(test_rom(0x0B, 0x01, 0x00, 0x00)),
+            Err(CartridgeError::UnsupportedMbc(Mbc::Mmm01))
+        ));
+    }
+
+    #[test]
+    fn accepts_what_the_hardware_accepts() {
+        assert!(
+            Cartridge::new(SIMPLE_ADD.to_vec())
+                .unwrap()
+                .header
+                .global_checksum_valid
+        );
+
+        let mut rom = SIMPLE_ADD.to_vec();
+        rom[0x7000] = rom[0x7000].wrapping_add(1);
+        let header = Cartridge::new(rom).unwrap().header;
+        assert!(!header.global_checksum_valid);
+
+        let mut overdump = SIMPLE_ADD.to_vec();
+        overdump.resize(0x10000, 0xFF);
+        let cartridge = Cartridge::new(overdump).unwrap();
+        assert_eq!(cartridge.header.rom_size, 0x8000);
+        assert_eq!(cartridge.read_rom(0x0104), 0xCE);
+    }
+
+    /// Writes the ROM to a fresh temporary directory and returns the path of the ROM
+    fn write_temporary_rom(name: &str, rom: &[u8]) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("gb-emulator-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("game.gb");
+        std::fs::write(&path, rom).unwrap();
+        path
+    }
+
+    #[test]
+    fn battery_backed_ram_is_saved() {
+        let path = write_temporary_rom("battery", &test_rom(0x03, 0x01, 0x02, 0x00));
+        let save_path = path.with_extension("sav");
+        let mut cartridge = Cartridge::load(&path).unwrap();
+        cartridge.write_ram(0x1234, 0x42);
+        cartridge.flush_save().unwrap();
+        assert!(
+            !save_path.exists(),
+            "Nothing was written to the disabled RAM"
+        );
+
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_ram(0x1234, 0x42);
+        cartridge.flush_save().unwrap();
+        let save = std::fs::read(&save_path).unwrap();
+        assert_eq!(save.len(), 0x2000);
+        assert!(!path.with_extension("sav.tmp").exists());
+        assert_eq!(save[0x1234], 0x42);
+
+        let mut cartridge = Cartridge::load(&path).unwrap();
+        cartridge.write_rom(0x0000, 0x0A);
+        assert_eq!(cartridge.read_ram(0x1234), 0x42);
+
+        std::fs::write(&save_path, &save[..0x1000]).unwrap();
+        assert!(matches!(
+            Cartridge::load(&path),
+            Err(CartridgeError::SaveSizeMismatch {
+                expected: 0x2000,
+                actual: 0x1000
+            })
+        ));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn ram_without_battery_is_not_saved() {
+        let path = write_temporary_rom("no-battery", &test_rom(0x02, 0x01, 0x02, 0x00));
+        let mut cartridge = Cartridge::load(&path).unwrap();
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_ram(0x0000, 0x42);
+        cartridge.flush_save().unwrap();
+        assert!(!path.with_extension("sav").exists());
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn load_reports_io_errors() {
+        assert!(matches!(
+            Cartridge::load("test_roms/does_not_exist.gb"),
+            Err(CartridgeError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn save_data_includes_rtc() {
+        let time = ManualTimeSource::default();
+        let rom = test_rom(0x10, 0x01, 0x02, 0x00);
+        let mut cartridge =
+            Cartridge::with_time_source(rom.clone(), Box::new(time.clone())).unwrap();
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_ram(0x0000, 0x42);
+        cartridge.write_rom(0x4000, 0x0A);
+        cartridge.write_ram(0x0000, 5);
+        let save = cartridge.save_data();
+        assert_eq!(save.len(), 0x2000 + RTC_SAVE_SIZE);
+        assert_eq!(save[0], 0x42);
+
+        time.advance(3600);
+        let mut loaded = Cartridge::with_time_source(rom, Box::new(time.clone())).unwrap();
+        loaded.load_save_data(&save).unwrap();
+        loaded.write_rom(0x0000, 0x0A);
+        loaded.write_rom(0x6000, 0x00);
+        loaded.write_rom(0x6000, 0x01);
+        loaded.write_rom(0x4000, 0x0A);
+        assert_eq!(loaded.read_ram(0x0000), 6, "Hours kept counting");
+        loaded.write_rom(0x4000, 0x00);
+        assert_eq!(loaded.read_ram(0x0000), 0x42);
+
+        // The RTC state is optional, other sizes are rejected
+        loaded.load_save_data(&save[..0x2000]).unwrap();
+        assert!(matches!(
+            loaded.load_save_data(&save[..0x2010]),
+            Err(CartridgeError::SaveSizeMismatch {
+                expected: 0x2030,
+                actual: 0x2010
+            })
+        ));
+    }
+}
@@ -0,0 +1,352 @@
+use super::{CartridgeError, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+pub(crate) const LOGO_START: usize = 0x0104;
+const LOGO_END: usize = 0x0133;
+const TITLE_START: usize = 0x0134;
+/// Last title byte, on CGB cartridges this byte is the CGB flag instead
+const TITLE_END: usize = 0x0143;
+const CGB_FLAG_ADDRESS: usize = 0x0143;
+const NEW_LICENSEE_CODE_START: usize = 0x0144;
+const SGB_FLAG_ADDRESS: usize = 0x0146;
+const CARTRIDGE_TYPE_ADDRESS: usize = 0x0147;
+const ROM_SIZE_ADDRESS: usize = 0x0148;
+const RAM_SIZE_ADDRESS: usize = 0x0149;
+const OLD_LICENSEE_CODE_ADDRESS: usize = 0x014B;
+const VERSION_ADDRESS: usize = 0x014C;
+const HEADER_CHECKSUM_ADDRESS: usize = 0x014D;
+const GLOBAL_CHECKSUM_START: usize = 0x014E;
+/// The header ends at 0x014F, every valid ROM is at least this long
+pub(crate) const HEADER_END: usize = 0x0150;
+
+/// Old licensee code telling that the new licensee code is used instead
+const USE_NEW_LICENSEE_CODE: u8 = 0x33;
+const SGB_SUPPORTED: u8 = 0x03;
+
+/// Bitmap the boot ROM compares before handing control to the cartridge
+pub(crate) const NINTENDO_LOGO: [u8; LOGO_END - LOGO_START + 1] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+/// Memory bank controller the cartridge uses to map ROM and RAM banks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mbc {
+    None,
+    Mbc1,
+    Mbc2,
+    Mmm01,
+    Mbc3,
+    Mbc5,
+    Mbc6,
+    Mbc7,
+    PocketCamera,
+    Tama5,
+    HuC3,
+    HuC1,
+}
+
+/// Hardware on the cartridge as declared by the cartridge type byte (0x0147)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CartridgeType {
+    pub(crate) mbc: Mbc,
+    pub(crate) ram: bool,
+    pub(crate) battery: bool,
+    pub(crate) timer: bool,
+    pub(crate) rumble: bool,
+}
+
+impl CartridgeType {
+    const fn new(mbc: Mbc) -> Self {
+        Self {
+            mbc,
+            ram: false,
+            battery: false,
+            timer: false,
+            rumble: false,
+        }
+    }
+
+    /// Whether the header may declare external RAM. MBC2 has built-in RAM and declares none, the
+    /// RAM of some controllers that aren't emulated has no type of its own
+    fn allows_ram(self) -> bool {
+        self.ram
+            || matches!(
+                self.mbc,
+                Mbc::Mbc6 | Mbc::PocketCamera | Mbc::Tama5 | Mbc::HuC3
+            )
+    }
+
+    const fn ram(mut self) -> Self {
+        self.ram = true;
+        self
+    }
+
+    const fn battery(mut self) -> Self {
+        self.battery = true;
+        self
+    }
+
+    const fn timer(mut self) -> Self {
+        self.timer = true;
+        self
+    }
+
+    const fn rumble(mut self) -> Self {
+        self.rumble = true;
+        self
+    }
+}
+
+impl TryFrom<u8> for CartridgeType {
+    type Error = CartridgeError;
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        use Mbc::*;
+        let cartridge_type = match code {
+            0x00 => Self::new(None),
+            0x01 => Self::new(Mbc1),
+            0x02 => Self::new(Mbc1).ram(),
+            0x03 => Self::new(Mbc1).ram().battery(),
+            0x05 => Self::new(Mbc2),
+            0x06 => Self::new(Mbc2).battery(),
+            0x08 => Self::new(None).ram(),
+            0x09 => Self::new(None).ram().battery(),
+            0x0B => Self::new(Mmm01),
+            0x0C => Self::new(Mmm01).ram(),
+            0x0D => Self::new(Mmm01).ram().battery(),
+            0x0F => Self::new(Mbc3).timer().battery(),
+            0x10 => Self::new(Mbc3).timer().ram().battery(),
+            0x11 => Self::new(Mbc3),
+            0x12 => Self::new(Mbc3).ram(),
+            0x13 => Self::new(Mbc3).ram().battery(),
+            0x19 => Self::new(Mbc5),
+            0x1A => Self::new(Mbc5).ram(),
+            0x1B => Self::new(Mbc5).ram().battery(),
+            0x1C => Self::new(Mbc5).rumble(),
+            0x1D => Self::new(Mbc5).rumble().ram(),
+            0x1E => Self::new(Mbc5).rumble().ram().battery(),
+            0x20 => Self::new(Mbc6),
+            0x22 => Self::new(Mbc7).rumble().ram().battery(),
+            0xFC => Self::new(PocketCamera),
+            0xFD => Self::new(Tama5),
+            0xFE => Self::new(HuC3),
+            0xFF => Self::new(HuC1).ram().battery(),
+            _ => return Err(CartridgeError::UnknownCartridgeType(code)),
+        };
+        Ok(cartridge_type)
+    }
+}
+
+/// Whether the cartridge uses the Game Boy Color features (0x0143)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CgbSupport {
+    None,
+    /// Runs on DMG and CGB
+    Compatible,
+    Only,
+}
+
+impl From<u8> for CgbSupport {
+    fn from(flag: u8) -> Self {
+        match flag {
+            0xC0 => Self::Only,
+            // Bit 6 is ignored by the hardware as well
+            flag if flag & 0x80 != 0 => Self::Compatible,
+            _ => Self::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Licensee {
+    /// Code at 0x014B
+    Old(u8),
+    /// Two ASCII characters at 0x0144-0x0145, used if the old code is 0x33
+    New([u8; 2]),
+}
+
+/// Cartridge header at 0x0100-0x014F
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Header {
+    pub(crate) title: String,
+    pub(crate) cgb_support: CgbSupport,
+    pub(crate) sgb_support: bool,
+    pub(crate) cartridge_type: CartridgeType,
+    /// ROM size in bytes
+    pub(crate) rom_size: usize,
+    /// External RAM size in bytes, MBC2 has built-in RAM and declares none
+    pub(crate) ram_size: usize,
+    pub(crate) licensee: Licensee,
+    pub(crate) version: u8,
+    pub(crate) header_checksum: u8,
+    pub(crate) global_checksum: u16,
+    /// Whether the global checksum matches the ROM, the hardware ignores it so a mismatch is
+    /// only worth a warning
+    pub(crate) global_checksum_valid: bool,
+}
+
+impl Header {
+    /// Parses and validates the header, the ROM must be at least `HEADER_END` bytes long
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the logo or the header checksum don't match, a code is unknown or the
+    /// cartridge type has no RAM but a RAM size is declared
+    pub(crate) fn parse(rom: &[u8]) -> Result<Self, CartridgeError> {
+        if rom[LOGO_START..=LOGO_END] != NINTENDO_LOGO {
+            return Err(CartridgeError::InvalidLogo);
+        }
+        let header_checksum = rom[HEADER_CHECKSUM_ADDRESS];
+        let computed = compute_header_checksum(rom);
+        if header_checksum != computed {
+            return Err(CartridgeError::HeaderChecksum {
+                expected: header_checksum,
+                computed,
+            });
+        }
+
+        let cgb_support = CgbSupport::from(rom[CGB_FLAG_ADDRESS]);
+        let title_end = match cgb_support {
+            CgbSupport::None => TITLE_END,
+            _ => TITLE_END - 1,
+        };
+        let title = rom[TITLE_START..=title_end]
+            .iter()
+            .take_while(|&&byte| byte != 0)
+            .map(|&byte| byte as char)
+            .collect::<String>()
+            .trim_end()
+            .to_owned();
+
+        let licensee = match rom[OLD_LICENSEE_CODE_ADDRESS] {
+            USE_NEW_LICENSEE_CODE => Licensee::New([
+                rom[NEW_LICENSEE_CODE_START],
+                rom[NEW_LICENSEE_CODE_START + 1],
+            ]),
+            code => Licensee::Old(code),
+        };
+        let cartridge_type_code = rom[CARTRIDGE_TYPE_ADDRESS];
+        let cartridge_type = CartridgeType::try_from(cartridge_type_code)?;
+        let ram_size = ram_size(rom[RAM_SIZE_ADDRESS])?;
+        if ram_size != 0 && !cartridge_type.allows_ram() {
+            return Err(CartridgeError::RamSizeMismatch {
+                cartridge_type: cartridge_type_code,
+                ram_size,
+            });
+        }
+        let global_checksum =
+            u16::from_be_bytes([rom[GLOBAL_CHECKSUM_START], rom[GLOBAL_CHECKSUM_START + 1]]);
+
+        Ok(Self {
+            title,
+            cgb_support,
+            sgb_support: rom[SGB_FLAG_ADDRESS] == SGB_SUPPORTED,
+            cartridge_type,
+            rom_size: rom_size(rom[ROM_SIZE_ADDRESS])?,
+            ram_size,
+            licensee,
+            version: rom[VERSION_ADDRESS],
+            header_checksum,
+            global_checksum,
+            global_checksum_valid: global_checksum == compute_global_checksum(rom),
+        })
+    }
+}
+
+/// Checksum over 0x0134-0x014C, the boot ROM refuses to start if it doesn't match
+pub(crate) fn compute_header_checksum(rom: &[u8]) -> u8 {
+    rom[TITLE_START..HEADER_CHECKSUM_ADDRESS]
+        .iter()
+        .fold(0u8, |checksum, &byte| {
+            checksum.wrapping_sub(byte).wrapping_sub(1)
+        })
+}
+
+/// Sum of all ROM bytes except the global checksum itself, not checked by the hardware
+pub(crate) fn compute_global_checksum(rom: &[u8]) -> u16 {
+    rom.iter()
+        .enumerate()
+        .filter(|(address, _)| {
+            !(GLOBAL_CHECKSUM_START..=GLOBAL_CHECKSUM_START + 1).contains(address)
+        })
+        .fold(0u16, |checksum, (_, &byte)| {
+            checksum.wrapping_add(byte as u16)
+        })
+}
+
+/// 32 KiB shifted left by the code, up to 8 MiB
+fn rom_size(code: u8) -> Result<usize, CartridgeError> {
+    match code {
+        0x00..=0x08 => Ok((2 * ROM_BANK_SIZE) << code),
+        _ => Err(CartridgeError::UnknownRomSize(code)),
+    }
+}
+
+fn ram_size(code: u8) -> Result<usize, CartridgeError> {
+    match code {
+        0x00 => Ok(0),
+        // Unofficial, used by some homebrew for a single 2 KiB chip
+        0x01 => Ok(RAM_BANK_SIZE / 4),
+        0x02 => Ok(RAM_BANK_SIZE),
+        0x03 => Ok(4 * RAM_BANK_SIZE),
+        0x04 => Ok(16 * RAM_BANK_SIZE),
+        0x05 => Ok(8 * RAM_BANK_SIZE),
+        _ => Err(CartridgeError::UnknownRamSize(code)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cartridge_types() {
+        assert_eq!(
+            CartridgeType::try_from(0x00).unwrap(),
+            CartridgeType::new(Mbc::None)
+        );
+        assert_eq!(
+            CartridgeType::try_from(0x10).unwrap(),
+            CartridgeType {
+                mbc: Mbc::Mbc3,
+                ram: true,
+                battery: true,
+                timer: true,
+                rumble: false,
+            }
+        );
+        assert!(CartridgeType::try_from(0x1E).unwrap().rumble);
+        assert!(matches!(
+            CartridgeType::try_from(0x04),
+            Err(CartridgeError::UnknownCartridgeType(0x04))
+        ));
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(rom_size(0x00).unwrap(), 0x8000);
+        assert_eq!(rom_size(0x05).unwrap(), 0x10_0000);
+        assert_eq!(rom_size(0x08).unwrap(), 0x80_0000);
+        assert!(matches!(
+            rom_size(0x52),
+            Err(CartridgeError::UnknownRomSize(0x52))
+        ));
+        assert_eq!(ram_size(0x00).unwrap(), 0);
+        assert_eq!(ram_size(0x03).unwrap(), 0x8000);
+        assert_eq!(ram_size(0x05).unwrap(), 0x1_0000);
+        assert!(matches!(
+            ram_size(0x06),
+            Err(CartridgeError::UnknownRamSize(0x06))
+        ));
+    }
+
+    #[test]
+    fn cgb_flag() {
+        assert_eq!(CgbSupport::from(0x00), CgbSupport::None);
+        assert_eq!(CgbSupport::from(0x80), CgbSupport::Compatible);
+        assert_eq!(CgbSupport::from(0xC0), CgbSupport::Only);
+        // Part of the title on cartridges predating the CGB
+        assert_eq!(CgbSupport::from(b'E'), CgbSupport::None);
+    }
+}
@@ -0,0 +1,178 @@
+use super::header::{LOGO_START, NINTENDO_LOGO};
+use super::{OPEN_BUS, RAM_BANK_SIZE, RAM_ENABLE_VALUE, ROM_BANK_SIZE, banked_offset};
+
+const RAM_ENABLE_END: u16 = 0x1FFF;
+const ROM_BANK_SELECT_END: u16 = 0x3FFF;
+const UPPER_BANK_END: u16 = 0x5FFF;
+const FIXED_ROM_END: u16 = 0x3FFF;
+
+const ROM_BANK_MASK: u8 = 0x1F;
+const UPPER_BANK_MASK: u8 = 0x03;
+
+/// MBC1M carts are 1 MiB and repeat the boot logo in the first bank of every game
+const MULTICART_SIZE: usize = 0x10_0000;
+const MULTICART_GAME_BANKS: usize = 0x10;
+
+/// Maps up to 2 MiB of ROM and 32 KiB of RAM
+pub(crate) struct Mbc1 {
+    ram_enabled: bool,
+    /// BANK1, lower bits of the ROM bank mapped to 0x4000-0x7FFF
+    rom_bank: u8,
+    /// BANK2, RAM bank or upper bits of the ROM bank
+    upper_bank: u8,
+    /// Mode 1 also applies BANK2 to 0x0000-0x3FFF and to the RAM
+    advanced_banking: bool,
+    /// MBC1M wires only 4 bits of BANK1 to the ROM, BANK2 then selects the game
+    multicart: bool,
+}
+
+impl Mbc1 {
+    pub(crate) fn new(rom: &[u8]) -> Self {
+        Self {
+            ram_enabled: false,
+            rom_bank: 1,
+            upper_bank: 0,
+            advanced_banking: false,
+            multicart: is_multicart(rom),
+        }
+    }
+
+    fn upper_bank_shift(&self) -> u8 {
+        if self.multicart { 4 } else { 5 }
+    }
+
+    pub(crate) fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=FIXED_ROM_END if self.advanced_banking => {
+                self.upper_bank << self.upper_bank_shift()
+            }
+            0x0000..=FIXED_ROM_END => 0,
+            _ => {
+                let lower_bank_mask = (1 << self.upper_bank_shift()) - 1;
+                (self.upper_bank << self.upper_bank_shift()) | (self.rom_bank & lower_bank_mask)
+            }
+        };
+        banked_offset(rom.len(), bank as usize, ROM_BANK_SIZE, address)
+            .map_or(OPEN_BUS, |offset| rom[offset])
+    }
+
+    pub(crate) fn write_rom(&mut self, address: u16, byte: u8) {
+        match address {
+            0x0000..=RAM_ENABLE_END => self.ram_enabled = byte & 0x0F == RAM_ENABLE_VALUE,
+            0x2000..=ROM_BANK_SELECT_END => {
+                // The zero check sees all 5 bits, so bank 0x20 maps bank 0x21 and so on
+                self.rom_bank = (byte & ROM_BANK_MASK).max(1);
+            }
+            0x4000..=UPPER_BANK_END => self.upper_bank = byte & UPPER_BANK_MASK,
+            _ => self.advanced_banking = byte & 1 != 0,
+        }
+    }
+
+    fn ram_offset(&self, ram: &[u8], address: u16) -> Option<usize> {
+        if !self.ram_enabled {
+            return None;
+        }
+        let bank = if self.advanced_banking {
+            self.upper_bank
+        } else {
+            0
+        };
+        banked_offset(ram.len(), bank as usize, RAM_BANK_SIZE, address)
+    }
+
+    pub(crate) fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        self.ram_offset(ram, address)
+            .map_or(OPEN_BUS, |offset| ram[offset])
+    }
+
+    /// Returns whether the RAM changed, writes are ignored while the RAM is disabled
+    pub(crate) fn write_ram(&self, ram: &mut [u8], address: u16, byte: u8) -> bool {
+        self.ram_offset(ram, address)
+            .is_some_and(|offset| std::mem::replace(&mut ram[offset], byte) != byte)
+    }
+}
+
+/// There is no header flag for MBC1M, the logo of the second game gives it away
+fn is_multicart(rom: &[u8]) -> bool {
+    let logo_start = MULTICART_GAME_BANKS * ROM_BANK_SIZE + LOGO_START;
+    rom.len() == MULTICART_SIZE
+        && rom[logo_start..logo_start + NINTENDO_LOGO.len()] == NINTENDO_LOGO
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::numbered_banks;
+    use super::*;
+
+    #[test]
+    fn upper_bank_extends_rom_bank() {
+        let rom = numbered_banks(128);
+        let mut mbc = Mbc1::new(&rom);
+        mbc.write_rom(0x4000, 0x02);
+        mbc.write_rom(0x2000, 0x05);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x45);
+        assert_eq!(
+            mbc.read_rom(&rom, 0x0000),
+            0x00,
+            "Mode 0 always maps bank 0"
+        );
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_rom(&rom, 0x3FFF), 0x40);
+        assert_eq!(mbc.read_rom(&rom, 0x7FFF), 0x45);
+
+        // Banks 0x20, 0x40 and 0x60 can't be mapped to 0x4000-0x7FFF
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x41);
+    }
+
+    #[test]
+    fn bank_number_wraps_around_rom_size() {
+        let rom = numbered_banks(16);
+        let mut mbc = Mbc1::new(&rom);
+        // Not zero as seen by the MBC, but masked to bank 0 by the ROM size
+        mbc.write_rom(0x2000, 0x10);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x00);
+        mbc.write_rom(0x2000, 0x13);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x03);
+    }
+
+    #[test]
+    fn multicart() {
+        let mut rom = numbered_banks(64);
+        let logo_start = MULTICART_GAME_BANKS * ROM_BANK_SIZE + LOGO_START;
+        rom[logo_start..logo_start + NINTENDO_LOGO.len()].copy_from_slice(&NINTENDO_LOGO);
+        let mut mbc = Mbc1::new(&rom);
+        assert!(mbc.multicart);
+
+        mbc.write_rom(0x4000, 0x01);
+        mbc.write_rom(0x2000, 0x02);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x12);
+        // BANK1 bit 4 is not wired, but still counts for the zero check
+        mbc.write_rom(0x2000, 0x10);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x10);
+        mbc.write_rom(0x6000, 0x01);
+        mbc.write_rom(0x4000, 0x03);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x30);
+
+        assert!(!Mbc1::new(&numbered_banks(64)).multicart);
+    }
+
+    #[test]
+    fn ram_banking() {
+        let rom = numbered_banks(4);
+        let mut ram = vec![0; 4 * RAM_BANK_SIZE];
+        let mut mbc = Mbc1::new(&rom);
+        assert!(!mbc.write_ram(&mut ram, 0x0000, 0x42));
+        assert_eq!(ram[0], 0x00, "RAM is disabled after reset");
+        assert_eq!(mbc.read_ram(&ram, 0x0000), OPEN_BUS);
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x03);
+        assert!(mbc.write_ram(&mut ram, 0x0001, 0x42));
+        assert_eq!(ram[0x0001], 0x42, "Mode 0 always maps RAM bank 0");
+        assert!(!mbc.write_ram(&mut ram, 0x0001, 0x42), "Same value");
+        mbc.write_rom(0x6000, 0x01);
+        mbc.write_ram(&mut ram, 0x0001, 0x24);
+        assert_eq!(ram[3 * RAM_BANK_SIZE + 1], 0x24);
+    }
+}
@@ -0,0 +1,101 @@
+use super::{OPEN_BUS, RAM_ENABLE_VALUE, ROM_BANK_SIZE, banked_offset};
+
+const REGISTERS_END: u16 = 0x3FFF;
+const FIXED_ROM_END: u16 = 0x3FFF;
+/// Address bit 8 selects between the RAM enable and the ROM bank register
+const ROM_BANK_SELECT_BIT: u16 = 0x0100;
+const ROM_BANK_MASK: u8 = 0x0F;
+
+/// 512 half-bytes of RAM are built into the MBC2
+pub(crate) const RAM_SIZE: usize = 0x200;
+/// Only the lower nibble of every RAM byte exists, the upper one reads as 1s
+const RAM_UNUSED_BITS: u8 = 0xF0;
+
+/// Maps up to 256 KiB of ROM and has its own 512×4 bit RAM
+pub(crate) struct Mbc2 {
+    ram_enabled: bool,
+    rom_bank: u8,
+}
+
+impl Mbc2 {
+    pub(crate) fn new() -> Self {
+        Self {
+            ram_enabled: false,
+            rom_bank: 1,
+        }
+    }
+
+    pub(crate) fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=FIXED_ROM_END => 0,
+            _ => self.rom_bank,
+        };
+        banked_offset(rom.len(), bank as usize, ROM_BANK_SIZE, address)
+            .map_or(OPEN_BUS, |offset| rom[offset])
+    }
+
+    pub(crate) fn write_rom(&mut self, address: u16, byte: u8) {
+        match address {
+            0x0000..=REGISTERS_END if address & ROM_BANK_SELECT_BIT != 0 => {
+                self.rom_bank = (byte & ROM_BANK_MASK).max(1);
+            }
+            0x0000..=REGISTERS_END => self.ram_enabled = byte & 0x0F == RAM_ENABLE_VALUE,
+            _ => {}
+        }
+    }
+
+    /// The RAM repeats throughout 0xA000-0xBFFF
+    fn ram_offset(&self, ram: &[u8], address: u16) -> Option<usize> {
+        if !self.ram_enabled {
+            return None;
+        }
+        banked_offset(ram.len(), 0, RAM_SIZE, address)
+    }
+
+    pub(crate) fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        self.ram_offset(ram, address)
+            .map_or(OPEN_BUS, |offset| ram[offset] | RAM_UNUSED_BITS)
+    }
+
+    /// Returns whether the RAM changed, writes are ignored while the RAM is disabled
+    pub(crate) fn write_ram(&self, ram: &mut [u8], address: u16, byte: u8) -> bool {
+        let byte = byte & !RAM_UNUSED_BITS;
+        self.ram_offset(ram, address)
+            .is_some_and(|offset| std::mem::replace(&mut ram[offset], byte) != byte)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::numbered_banks;
+    use super::*;
+
+    #[test]
+    fn address_bit_8_selects_register() {
+        let rom = numbered_banks(16);
+        let mut mbc = Mbc2::new();
+        mbc.write_rom(0x0100, 0x0A);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x0A);
+        assert!(!mbc.ram_enabled);
+        mbc.write_rom(0x3EFF, 0x0A);
+        assert!(mbc.ram_enabled);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x0A);
+        mbc.write_rom(0x3FFF, 0x10);
+        assert_eq!(mbc.read_rom(&rom, 0x7FFF), 0x01);
+    }
+
+    #[test]
+    fn half_byte_ram() {
+        let mut ram = vec![0; RAM_SIZE];
+        let mut mbc = Mbc2::new();
+        mbc.write_ram(&mut ram, 0x0000, 0x5A);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), OPEN_BUS);
+        assert_eq!(ram[0], 0x00, "RAM is disabled after reset");
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_ram(&mut ram, 0x0000, 0x5A);
+        assert_eq!(ram[0], 0x0A);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 0xFA);
+        assert_eq!(mbc.read_ram(&ram, 0x1E00), 0xFA);
+    }
+}
@@ -0,0 +1,179 @@
+use super::rtc::{Rtc, RtcRegister};
+use super::{OPEN_BUS, RAM_BANK_SIZE, RAM_ENABLE_VALUE, ROM_BANK_SIZE, banked_offset};
+
+const RAM_ENABLE_END: u16 = 0x1FFF;
+const ROM_BANK_SELECT_END: u16 = 0x3FFF;
+const RAM_BANK_SELECT_END: u16 = 0x5FFF;
+const FIXED_ROM_END: u16 = 0x3FFF;
+
+const ROM_BANK_MASK: u8 = 0x7F;
+const RAM_BANKS: u8 = 4;
+
+/// Writing 0x00 and then 0x01 to 0x6000-0x7FFF latches the RTC
+const LATCH_PREPARE_VALUE: u8 = 0x00;
+const LATCH_VALUE: u8 = 0x01;
+
+/// What 0xA000-0xBFFF maps to, selected by writing to 0x4000-0x5FFF
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RamMapping {
+    Bank(u8),
+    Rtc(RtcRegister),
+    /// No RAM bank or RTC register has this select value
+    None,
+}
+
+/// Maps up to 2 MiB of ROM and 32 KiB of RAM, optionally with a real time clock
+pub(crate) struct Mbc3 {
+    /// Enables both the RAM and the RTC registers
+    ram_enabled: bool,
+    rom_bank: u8,
+    ram_mapping: RamMapping,
+    /// Last value written to the latch register
+    latch_prepared: bool,
+    rtc: Option<Rtc>,
+}
+
+impl Mbc3 {
+    pub(crate) fn new(rtc: Option<Rtc>) -> Self {
+        Self {
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_mapping: RamMapping::Bank(0),
+            latch_prepared: false,
+            rtc,
+        }
+    }
+
+    pub(crate) fn rtc(&self) -> Option<&Rtc> {
+        self.rtc.as_ref()
+    }
+
+    pub(crate) fn rtc_mut(&mut self) -> Option<&mut Rtc> {
+        self.rtc.as_mut()
+    }
+
+    pub(crate) fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=FIXED_ROM_END => 0,
+            _ => self.rom_bank,
+        };
+        banked_offset(rom.len(), bank as usize, ROM_BANK_SIZE, address)
+            .map_or(OPEN_BUS, |offset| rom[offset])
+    }
+
+    pub(crate) fn write_rom(&mut self, address: u16, byte: u8) {
+        match address {
+            0x0000..=RAM_ENABLE_END => self.ram_enabled = byte & 0x0F == RAM_ENABLE_VALUE,
+            // Unlike MBC1 all 7 bits are checked for bank 0
+            0x2000..=ROM_BANK_SELECT_END => self.rom_bank = (byte & ROM_BANK_MASK).max(1),
+            0x4000..=RAM_BANK_SELECT_END => {
+                self.ram_mapping = match RtcRegister::from_select(byte) {
+                    Some(register) if self.rtc.is_some() => RamMapping::Rtc(register),
+                    _ if byte < RAM_BANKS => RamMapping::Bank(byte),
+                    _ => RamMapping::None,
+                }
+            }
+            _ => {
+                if self.latch_prepared
+                    && byte == LATCH_VALUE
+                    && let Some(rtc) = self.rtc.as_mut()
+                {
+                    rtc.latch();
+                }
+                self.latch_prepared = byte == LATCH_PREPARE_VALUE;
+            }
+        }
+    }
+
+    pub(crate) fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        if !self.ram_enabled {
+            return OPEN_BUS;
+        }
+        match (self.ram_mapping, &self.rtc) {
+            (RamMapping::Bank(bank), _) => {
+                banked_offset(ram.len(), bank as usize, RAM_BANK_SIZE, address)
+                    .map_or(OPEN_BUS, |offset| ram[offset])
+            }
+            (RamMapping::Rtc(register), Some(rtc)) => rtc.read(register),
+            _ => OPEN_BUS,
+        }
+    }
+
+    /// Returns whether the RAM or the RTC changed, writes are ignored while they are disabled
+    pub(crate) fn write_ram(&mut self, ram: &mut [u8], address: u16, byte: u8) -> bool {
+        if !self.ram_enabled {
+            return false;
+        }
+        match (self.ram_mapping, &mut self.rtc) {
+            (RamMapping::Bank(bank), _) => {
+                banked_offset(ram.len(), bank as usize, RAM_BANK_SIZE, address)
+                    .is_some_and(|offset| std::mem::replace(&mut ram[offset], byte) != byte)
+            }
+            (RamMapping::Rtc(register), Some(rtc)) => {
+                rtc.write(register, byte);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::numbered_banks;
+    use super::super::rtc::tests::ManualTimeSource;
+    use super::*;
+
+    #[test]
+    fn rom_banking() {
+        let rom = numbered_banks(128);
+        let mut mbc = Mbc3::new(None);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x01);
+        mbc.write_rom(0x2000, 0x7F);
+        assert_eq!(mbc.read_rom(&rom, 0x7FFF), 0x7F);
+        mbc.write_rom(0x2000, 0x20);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x20);
+        mbc.write_rom(0x2000, 0x80);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x01);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x00);
+    }
+
+    #[test]
+    fn ram_banking() {
+        let mut ram = vec![0; 4 * RAM_BANK_SIZE];
+        let mut mbc = Mbc3::new(None);
+        assert!(!mbc.write_ram(&mut ram, 0x0010, 0x42), "RAM is disabled");
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x02);
+        assert!(mbc.write_ram(&mut ram, 0x0010, 0x42));
+        assert_eq!(ram[2 * RAM_BANK_SIZE + 0x10], 0x42);
+        assert_eq!(mbc.read_ram(&ram, 0x0010), 0x42);
+
+        // Without a clock the RTC select values don't map anything
+        mbc.write_rom(0x4000, 0x08);
+        assert_eq!(mbc.read_ram(&ram, 0x0010), OPEN_BUS);
+        assert!(!mbc.write_ram(&mut ram, 0x0010, 0x24));
+    }
+
+    #[test]
+    fn rtc_registers() {
+        let time = ManualTimeSource::default();
+        let mut ram = vec![0; RAM_BANK_SIZE];
+        let mut mbc = Mbc3::new(Some(Rtc::new(Box::new(time.clone()))));
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x09);
+        mbc.write_ram(&mut ram, 0x0000, 30);
+        time.advance(90);
+
+        // Only writing 0x00 and then 0x01 latches
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 0);
+        mbc.write_rom(0x6000, 0x00);
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_ram(&ram, 0x0000), 31);
+        mbc.write_rom(0x4000, 0x08);
+        assert_eq!(mbc.read_ram(&ram, 0x1FFF), 30);
+
+        assert_eq!(ram, vec![0; RAM_BANK_SIZE], "RTC writes don't reach RAM");
+    }
+}
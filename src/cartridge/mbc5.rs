@@ -0,0 +1,130 @@
+use super::{OPEN_BUS, RAM_BANK_SIZE, RAM_ENABLE_VALUE, ROM_BANK_SIZE, banked_offset};
+
+const RAM_ENABLE_END: u16 = 0x1FFF;
+const ROM_BANK_LOW_END: u16 = 0x2FFF;
+const ROM_BANK_HIGH_END: u16 = 0x3FFF;
+const RAM_BANK_SELECT_END: u16 = 0x5FFF;
+const FIXED_ROM_END: u16 = 0x3FFF;
+
+const RAM_BANK_MASK: u8 = 0x0F;
+/// Rumble carts drive the motor with bit 3 of the RAM bank register instead
+const RUMBLE_BIT: u8 = 0b0000_1000;
+
+/// Maps up to 8 MiB of ROM and 128 KiB of RAM, optionally with a rumble motor
+pub(crate) struct Mbc5 {
+    ram_enabled: bool,
+    /// 9 bit ROM bank, unlike older MBCs bank 0 can be mapped to 0x4000-0x7FFF
+    rom_bank: u16,
+    ram_bank: u8,
+    has_rumble: bool,
+    rumble: bool,
+}
+
+impl Mbc5 {
+    pub(crate) fn new(has_rumble: bool) -> Self {
+        Self {
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            has_rumble,
+            rumble: false,
+        }
+    }
+
+    /// Whether the rumble motor is running
+    pub(crate) fn rumble(&self) -> bool {
+        self.rumble
+    }
+
+    pub(crate) fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=FIXED_ROM_END => 0,
+            _ => self.rom_bank,
+        };
+        banked_offset(rom.len(), bank as usize, ROM_BANK_SIZE, address)
+            .map_or(OPEN_BUS, |offset| rom[offset])
+    }
+
+    pub(crate) fn write_rom(&mut self, address: u16, byte: u8) {
+        match address {
+            0x0000..=RAM_ENABLE_END => self.ram_enabled = byte & 0x0F == RAM_ENABLE_VALUE,
+            0x2000..=ROM_BANK_LOW_END => self.rom_bank = (self.rom_bank & 0x100) | byte as u16,
+            0x3000..=ROM_BANK_HIGH_END => {
+                self.rom_bank = (self.rom_bank & 0xFF) | ((byte & 1) as u16) << 8;
+            }
+            0x4000..=RAM_BANK_SELECT_END if self.has_rumble => {
+                self.rumble = byte & RUMBLE_BIT != 0;
+                self.ram_bank = byte & RAM_BANK_MASK & !RUMBLE_BIT;
+            }
+            0x4000..=RAM_BANK_SELECT_END => self.ram_bank = byte & RAM_BANK_MASK,
+            _ => {}
+        }
+    }
+
+    fn ram_offset(&self, ram: &[u8], address: u16) -> Option<usize> {
+        if !self.ram_enabled {
+            return None;
+        }
+        banked_offset(ram.len(), self.ram_bank as usize, RAM_BANK_SIZE, address)
+    }
+
+    pub(crate) fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        self.ram_offset(ram, address)
+            .map_or(OPEN_BUS, |offset| ram[offset])
+    }
+
+    /// Returns whether the RAM changed, writes are ignored while the RAM is disabled
+    pub(crate) fn write_ram(&self, ram: &mut [u8], address: u16, byte: u8) -> bool {
+        self.ram_offset(ram, address)
+            .is_some_and(|offset| std::mem::replace(&mut ram[offset], byte) != byte)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::numbered_banks;
+    use super::*;
+
+    fn read_bank_number(mbc: &Mbc5, rom: &[u8]) -> u16 {
+        u16::from_le_bytes([mbc.read_rom(rom, 0x4000), mbc.read_rom(rom, 0x4001)])
+    }
+
+    #[test]
+    fn nine_bit_rom_bank() {
+        let rom = numbered_banks(512);
+        let mut mbc = Mbc5::new(false);
+        assert_eq!(read_bank_number(&mbc, &rom), 0x001);
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(read_bank_number(&mbc, &rom), 0x000, "Bank 0 can be mapped");
+        mbc.write_rom(0x3000, 0x01);
+        assert_eq!(read_bank_number(&mbc, &rom), 0x100);
+        mbc.write_rom(0x2FFF, 0xFF);
+        assert_eq!(read_bank_number(&mbc, &rom), 0x1FF);
+        mbc.write_rom(0x3FFF, 0xFE);
+        assert_eq!(read_bank_number(&mbc, &rom), 0x0FF);
+    }
+
+    #[test]
+    fn ram_banking() {
+        let mut ram = vec![0; 16 * RAM_BANK_SIZE];
+        let mut mbc = Mbc5::new(false);
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x0F);
+        mbc.write_ram(&mut ram, 0x0000, 0x42);
+        assert_eq!(ram[15 * RAM_BANK_SIZE], 0x42);
+        assert!(!mbc.rumble());
+    }
+
+    #[test]
+    fn rumble() {
+        let mut ram = vec![0; 8 * RAM_BANK_SIZE];
+        let mut mbc = Mbc5::new(true);
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x09);
+        assert!(mbc.rumble());
+        mbc.write_ram(&mut ram, 0x0000, 0x42);
+        assert_eq!(ram[RAM_BANK_SIZE], 0x42);
+        mbc.write_rom(0x4000, 0x01);
+        assert!(!mbc.rumble());
+    }
+}
@@ -0,0 +1,382 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_MINUTE: u64 = 60;
+const SECONDS_PER_HOUR: u64 = 60 * SECONDS_PER_MINUTE;
+const SECONDS_PER_DAY: u64 = 24 * SECONDS_PER_HOUR;
+/// The day counter is 9 bits wide
+const DAYS: u64 = 512;
+
+const SECONDS_MASK: u8 = 0x3F;
+const MINUTES_MASK: u8 = 0x3F;
+const HOURS_MASK: u8 = 0x1F;
+const DAY_HIGH_BIT: u8 = 0b0000_0001;
+const HALT_BIT: u8 = 0b0100_0000;
+const DAY_CARRY_BIT: u8 = 0b1000_0000;
+
+/// Live and latched registers as 32 bit values followed by a 64 bit Unix timestamp, the format
+/// other emulators append to the save RAM
+pub(crate) const RTC_SAVE_SIZE: usize = 48;
+/// Older variant of the save format with a 32 bit timestamp
+const RTC_SAVE_SIZE_32BIT_TIMESTAMP: usize = 44;
+const REGISTERS_SAVE_SIZE: usize = 20;
+const TIMESTAMP_START: usize = 2 * REGISTERS_SAVE_SIZE;
+
+/// Provides the time the RTC counts, tests use one they can advance by hand
+pub(crate) trait TimeSource {
+    /// Seconds since the Unix epoch
+    fn now(&self) -> u64;
+}
+
+/// Wall clock of the host
+pub(crate) struct SystemTimeSource;
+
+impl TimeSource for SystemTimeSource {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs())
+    }
+}
+
+/// RTC register mapped to 0xA000-0xBFFF by writing 0x08-0x0C to 0x4000-0x5FFF
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RtcRegister {
+    Seconds,
+    Minutes,
+    Hours,
+    DayLow,
+    /// Bit 8 of the day counter, the halt flag and the day carry flag
+    DayHigh,
+}
+
+impl RtcRegister {
+    /// Ordered by their select value
+    const ALL: [RtcRegister; 5] = [
+        Self::Seconds,
+        Self::Minutes,
+        Self::Hours,
+        Self::DayLow,
+        Self::DayHigh,
+    ];
+
+    pub(crate) fn from_select(select: u8) -> Option<Self> {
+        match select {
+            0x08 => Some(Self::Seconds),
+            0x09 => Some(Self::Minutes),
+            0x0A => Some(Self::Hours),
+            0x0B => Some(Self::DayLow),
+            0x0C => Some(Self::DayHigh),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct RtcRegisters {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    days: u16,
+    halted: bool,
+    day_carry: bool,
+}
+
+impl RtcRegisters {
+    fn read(&self, register: RtcRegister) -> u8 {
+        match register {
+            RtcRegister::Seconds => self.seconds,
+            RtcRegister::Minutes => self.minutes,
+            RtcRegister::Hours => self.hours,
+            RtcRegister::DayLow => self.days as u8,
+            RtcRegister::DayHigh => {
+                let mut byte = (self.days >> 8) as u8 & DAY_HIGH_BIT;
+                if self.halted {
+                    byte |= HALT_BIT;
+                }
+                if self.day_carry {
+                    byte |= DAY_CARRY_BIT;
+                }
+                byte
+            }
+        }
+    }
+
+    fn write(&mut self, register: RtcRegister, byte: u8) {
+        match register {
+            RtcRegister::Seconds => self.seconds = byte & SECONDS_MASK,
+            RtcRegister::Minutes => self.minutes = byte & MINUTES_MASK,
+            RtcRegister::Hours => self.hours = byte & HOURS_MASK,
+            RtcRegister::DayLow => self.days = (self.days & 0x100) | byte as u16,
+            RtcRegister::DayHigh => {
+                self.days = (self.days & 0xFF) | ((byte & DAY_HIGH_BIT) as u16) << 8;
+                self.halted = byte & HALT_BIT != 0;
+                self.day_carry = byte & DAY_CARRY_BIT != 0;
+            }
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        self.seconds < 60 && self.minutes < 60 && self.hours < 24
+    }
+
+    /// Counts one second, counters set out of range count up to their bit width and wrap around
+    /// to 0 without a carry
+    fn tick(&mut self) {
+        self.seconds = (self.seconds + 1) & SECONDS_MASK;
+        if self.seconds != 60 {
+            return;
+        }
+        self.seconds = 0;
+        self.minutes = (self.minutes + 1) & MINUTES_MASK;
+        if self.minutes != 60 {
+            return;
+        }
+        self.minutes = 0;
+        self.hours = (self.hours + 1) & HOURS_MASK;
+        if self.hours != 24 {
+            return;
+        }
+        self.hours = 0;
+        self.add_days(1);
+    }
+
+    fn add_days(&mut self, days: u64) {
+        let days = self.days as u64 + days;
+        if days >= DAYS {
+            self.day_carry = true;
+        }
+        self.days = (days % DAYS) as u16;
+    }
+
+    fn advance(&mut self, mut seconds: u64) {
+        if self.halted {
+            return;
+        }
+        // Out of range counters need to wrap around before whole days can be added at once
+        while !self.is_valid() {
+            if seconds == 0 {
+                return;
+            }
+            self.tick();
+            seconds -= 1;
+        }
+        let time_of_day = self.seconds as u64
+            + self.minutes as u64 * SECONDS_PER_MINUTE
+            + self.hours as u64 * SECONDS_PER_HOUR
+            + seconds;
+        self.seconds = (time_of_day % SECONDS_PER_MINUTE) as u8;
+        self.minutes = (time_of_day / SECONDS_PER_MINUTE % 60) as u8;
+        self.hours = (time_of_day / SECONDS_PER_HOUR % 24) as u8;
+        self.add_days(time_of_day / SECONDS_PER_DAY);
+    }
+
+    fn save(&self, data: &mut [u8]) {
+        for (chunk, register) in data.chunks_exact_mut(4).zip(RtcRegister::ALL) {
+            chunk.copy_from_slice(&(self.read(register) as u32).to_le_bytes());
+        }
+    }
+
+    fn load(data: &[u8]) -> Self {
+        let mut registers = Self::default();
+        // Only the lowest byte of every 32 bit value is used
+        for (chunk, register) in data.chunks_exact(4).zip(RtcRegister::ALL) {
+            registers.write(register, chunk[0]);
+        }
+        registers
+    }
+}
+
+/// Real time clock of MBC3 cartridges
+///
+/// The counters are only brought up to date when the game latches or writes them, the time in
+/// between is taken from the time source.
+pub(crate) struct Rtc {
+    live: RtcRegisters,
+    /// Copy of the live registers the game reads
+    latched: RtcRegisters,
+    /// Time the live registers were last brought up to date
+    last_update: u64,
+    time_source: Box<dyn TimeSource>,
+}
+
+impl Rtc {
+    pub(crate) fn new(time_source: Box<dyn TimeSource>) -> Self {
+        Self {
+            live: RtcRegisters::default(),
+            latched: RtcRegisters::default(),
+            last_update: time_source.now(),
+            time_source,
+        }
+    }
+
+    /// Live registers with the time passed since the last update added
+    fn current(&self) -> RtcRegisters {
+        let mut registers = self.live;
+        let now = self.time_source.now();
+        registers.advance(now.saturating_sub(self.last_update));
+        registers
+    }
+
+    fn update(&mut self) {
+        self.live = self.current();
+        self.last_update = self.time_source.now();
+    }
+
+    pub(crate) fn latch(&mut self) {
+        self.update();
+        self.latched = self.live;
+    }
+
+    pub(crate) fn read(&self, register: RtcRegister) -> u8 {
+        self.latched.read(register)
+    }
+
+    pub(crate) fn write(&mut self, register: RtcRegister, byte: u8) {
+        self.update();
+        self.live.write(register, byte);
+    }
+
+    pub(crate) fn save(&self) -> [u8; RTC_SAVE_SIZE] {
+        let mut data = [0; RTC_SAVE_SIZE];
+        self.current().save(&mut data[..REGISTERS_SAVE_SIZE]);
+        self.latched
+            .save(&mut data[REGISTERS_SAVE_SIZE..TIMESTAMP_START]);
+        data[TIMESTAMP_START..].copy_from_slice(&self.time_source.now().to_le_bytes());
+        data
+    }
+
+    /// Restores a saved RTC, the time passed since it was saved is counted on the next update
+    ///
+    /// Returns false and leaves the RTC as is if `data` has neither of the supported sizes
+    pub(crate) fn load(&mut self, data: &[u8]) -> bool {
+        let timestamp = &data[TIMESTAMP_START.min(data.len())..];
+        self.last_update = match data.len() {
+            RTC_SAVE_SIZE => u64::from_le_bytes(timestamp.try_into().unwrap()),
+            RTC_SAVE_SIZE_32BIT_TIMESTAMP => {
+                u32::from_le_bytes(timestamp.try_into().unwrap()) as u64
+            }
+            _ => return false,
+        };
+        self.live = RtcRegisters::load(&data[..REGISTERS_SAVE_SIZE]);
+        self.latched = RtcRegisters::load(&data[REGISTERS_SAVE_SIZE..TIMESTAMP_START]);
+        true
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Time source the test advances by hand
+    #[derive(Clone, Default)]
+    pub(crate) struct ManualTimeSource(Rc<Cell<u64>>);
+
+    impl ManualTimeSource {
+        pub(crate) fn advance(&self, seconds: u64) {
+            self.0.set(self.0.get() + seconds);
+        }
+    }
+
+    impl TimeSource for ManualTimeSource {
+        fn now(&self) -> u64 {
+            self.0.get()
+        }
+    }
+
+    fn read_all(rtc: &Rtc) -> [u8; 5] {
+        RtcRegister::ALL.map(|register| rtc.read(register))
+    }
+
+    #[test]
+    fn counts_time_of_time_source() {
+        let time = ManualTimeSource::default();
+        let mut rtc = Rtc::new(Box::new(time.clone()));
+        time.advance(61);
+        assert_eq!(read_all(&rtc), [0; 5], "Reads return the latched registers");
+        rtc.latch();
+        assert_eq!(read_all(&rtc), [1, 1, 0, 0, 0]);
+
+        time.advance(SECONDS_PER_DAY * 256 + SECONDS_PER_HOUR * 23 - 61);
+        rtc.latch();
+        assert_eq!(read_all(&rtc), [0, 0, 23, 0, DAY_HIGH_BIT]);
+    }
+
+    #[test]
+    fn day_counter_overflow_sets_carry() {
+        let time = ManualTimeSource::default();
+        let mut rtc = Rtc::new(Box::new(time.clone()));
+        rtc.write(RtcRegister::DayLow, 0xFF);
+        rtc.write(RtcRegister::DayHigh, DAY_HIGH_BIT);
+        time.advance(SECONDS_PER_DAY + 5);
+        rtc.latch();
+        assert_eq!(read_all(&rtc), [5, 0, 0, 0, DAY_CARRY_BIT]);
+
+        // The carry stays set until it is cleared
+        time.advance(SECONDS_PER_DAY);
+        rtc.latch();
+        assert_eq!(read_all(&rtc), [5, 0, 0, 1, DAY_CARRY_BIT]);
+        rtc.write(RtcRegister::DayHigh, 0);
+        rtc.latch();
+        assert_eq!(rtc.read(RtcRegister::DayHigh), 0);
+    }
+
+    #[test]
+    fn halt_stops_the_clock() {
+        let time = ManualTimeSource::default();
+        let mut rtc = Rtc::new(Box::new(time.clone()));
+        rtc.write(RtcRegister::DayHigh, HALT_BIT);
+        rtc.write(RtcRegister::Seconds, 30);
+        time.advance(100);
+        rtc.latch();
+        assert_eq!(read_all(&rtc), [30, 0, 0, 0, HALT_BIT]);
+
+        rtc.write(RtcRegister::DayHigh, 0);
+        time.advance(10);
+        rtc.latch();
+        assert_eq!(read_all(&rtc), [40, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn out_of_range_counters_wrap_without_carry() {
+        let time = ManualTimeSource::default();
+        let mut rtc = Rtc::new(Box::new(time.clone()));
+        rtc.write(RtcRegister::Seconds, 62);
+        rtc.write(RtcRegister::Hours, 31);
+        time.advance(2);
+        rtc.latch();
+        assert_eq!(read_all(&rtc), [0, 0, 31, 0, 0]);
+
+        rtc.write(RtcRegister::Minutes, 59);
+        time.advance(60);
+        rtc.latch();
+        assert_eq!(read_all(&rtc), [0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let time = ManualTimeSource::default();
+        time.advance(1_000_000);
+        let mut rtc = Rtc::new(Box::new(time.clone()));
+        rtc.write(RtcRegister::Minutes, 10);
+        rtc.latch();
+        time.advance(5);
+        let save = rtc.save();
+        assert_eq!(save[0..8], [5, 0, 0, 0, 10, 0, 0, 0]);
+        assert_eq!(save[20..28], [0, 0, 0, 0, 10, 0, 0, 0]);
+        assert_eq!(save[40..48], 1_000_005u64.to_le_bytes());
+
+        // The time passed while the emulator wasn't running is caught up
+        time.advance(SECONDS_PER_HOUR);
+        let mut loaded = Rtc::new(Box::new(time.clone()));
+        assert!(loaded.load(&save));
+        assert_eq!(read_all(&loaded), [0, 10, 0, 0, 0]);
+        loaded.latch();
+        assert_eq!(read_all(&loaded), [5, 10, 1, 0, 0]);
+
+        assert!(loaded.load(&save[..RTC_SAVE_SIZE_32BIT_TIMESTAMP]));
+        assert!(!loaded.load(&save[..40]));
+    }
+}
@@ -1,36 +1,333 @@
-#![allow(dead_code)]
+mod boot;
+mod disassembler;
+mod dma;
 mod instruction;
+mod interrupt;
+mod memory_bus;
 use std::fmt::Display;
 
-use instruction::{Instruction, JumpCondition, R8};
+pub(crate) use boot::{Boot, BootRom, Model};
+use disassembler::Disassembly;
+use instruction::{IndirectR16, Instruction, JumpCondition, R8, R16, R16_2};
+use interrupt::Interrupt;
+use memory_bus::MemoryBus;
 
-const INSTRUCTION_PREFIX: u8 = 0xcb;
+use crate::cartridge::Cartridge;
+use crate::joypad::Buttons;
+use crate::ppu::{Framebuffer, Ppu, Renderer, T_CYCLES_PER_FRAME};
+use crate::serial::{Link, SerialSink};
+use crate::timer::DIVIDER_ADDRESS;
+
+/// Where the boot ROM jumps to after it finished
+const CARTRIDGE_ENTRY_POINT: u16 = 0x0100;
+const INITIAL_STACK_POINTER: u16 = 0xFFFE;
+
+const T_CYCLES_PER_M_CYCLE: u8 = 4;
+/// Pushing PC and jumping to the interrupt vector takes as long as a CALL
+const INTERRUPT_DISPATCH_M_CYCLES: u8 = 5;
 
 #[derive(Default)]
-struct Cpu {
+pub(crate) struct Cpu {
     registers: Registers,
     bus: MemoryBus,
+    /// T-cycles executed since power on
+    cycles: u64,
+    /// Interrupt master enable
+    ime: bool,
+    /// Set by EI, IME is only enabled after the next instruction
+    ime_scheduled: bool,
+    /// Set by HALT, the CPU idles until an interrupt is pending
+    halted: bool,
+    /// Set by HALT when it is skipped, the next opcode fetch does not increment PC
+    halt_bug: bool,
+    /// Set by STOP, the clock is stopped until a button is pressed
+    stopped: bool,
+    /// Lock up on illegal opcodes like the hardware does, instead of returning an error
+    lock_on_illegal_opcode: bool,
+    /// Set when an illegal opcode locked up the CPU, only a reset recovers from it
+    locked: bool,
+}
+
+/// An executed instruction together with the time it took
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Executed {
+    /// `None` while the CPU idles because it is halted, stopped or locked up
+    instruction: Option<Instruction>,
+    /// Machine cycles, each one is 4 T-cycles. Includes the dispatch of `interrupt`
+    m_cycles: u8,
+    /// Interrupt serviced right before the instruction
+    interrupt: Option<Interrupt>,
 }
 
+impl Executed {
+    fn idle(m_cycles: u8) -> Self {
+        Self {
+            instruction: None,
+            m_cycles,
+            interrupt: None,
+        }
+    }
+
+    fn t_cycles(&self) -> u8 {
+        self.m_cycles * T_CYCLES_PER_M_CYCLE
+    }
+}
+
+/// Errors that abort the execution, `address` is the one of the failing instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub(crate) enum ExecutionError {
+    /// The opcode is not part of the instruction set
+    IllegalOpcode { opcode: u8, address: u16 },
+    /// The instruction does something in the current state that isn't emulated, like STOP with a
+    /// button held down, which turns into HALT or a one byte NOP
+    UnimplementedInstruction {
+        instruction: Instruction,
+        address: u16,
+    },
+    /// The instruction accessed `target` in the prohibited area 0xFEA0-0xFEFF, only reported
+    /// with strict bus access checks
+    InvalidBusAccess { target: u16, address: u16 },
+}
+
+impl ExecutionError {
+    /// Address of the failing instruction
+    pub(crate) fn address(&self) -> u16 {
+        match *self {
+            ExecutionError::IllegalOpcode { address, .. }
+            | ExecutionError::UnimplementedInstruction { address, .. }
+            | ExecutionError::InvalidBusAccess { address, .. } => address,
+        }
+    }
+}
+
+impl Display for ExecutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecutionError::IllegalOpcode { opcode, address } => {
+                write!(f, "Illegal opcode 0x{opcode:02X} at 0x{address:04X}")
+            }
+            ExecutionError::UnimplementedInstruction {
+                instruction,
+                address,
+            } => write!(
+                f,
+                "Unimplemented instruction {instruction} at 0x{address:04X}"
+            ),
+            ExecutionError::InvalidBusAccess { target, address } => write!(
+                f,
+                "Invalid bus access to 0x{target:04X} by the instruction at 0x{address:04X}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExecutionError {}
+
 impl Cpu {
-    /// Steps to next instructions
+    /// Creates a CPU that starts at the cartridge entry point, where the boot ROM hands over
+    #[cfg(test)]
+    pub(crate) fn new(cartridge: Cartridge) -> Self {
+        Self::with_options(cartridge, Renderer::default(), Boot::default())
+    }
+
+    pub(crate) fn with_options(cartridge: Cartridge, renderer: Renderer, boot: Boot) -> Self {
+        let mut cpu = Self::default();
+        cpu.bus.ppu = Ppu::new(renderer);
+        match boot {
+            // Everything starts out zeroed at power on, including PC
+            Boot::Rom(boot_rom) => cpu.bus.map_boot_rom(boot_rom),
+            Boot::Skip(model) => {
+                cpu.registers = model.registers(cartridge.header().header_checksum);
+                cpu.bus.skip_boot_rom(model);
+            }
+        }
+        cpu.bus.insert_cartridge(cartridge);
+        cpu
+    }
+
+    /// Lock up on illegal opcodes like the hardware does, instead of returning an error from
+    /// `Cpu::run_frame`
+    pub(crate) fn set_lock_on_illegal_opcode(&mut self, lock: bool) {
+        self.lock_on_illegal_opcode = lock;
+    }
+
+    /// Return an error when an instruction accesses the prohibited area 0xFEA0-0xFEFF, which the
+    /// hardware tolerates, to catch bugs in homebrew
+    pub(crate) fn set_strict_bus_access(&mut self, strict: bool) {
+        self.bus.set_strict_access(strict);
+    }
+
+    pub(crate) fn cartridge(&self) -> Option<&Cartridge> {
+        self.bus.cartridge()
+    }
+
+    pub(crate) fn cartridge_mut(&mut self) -> Option<&mut Cartridge> {
+        self.bus.cartridge_mut()
+    }
+
+    /// Decodes the instruction at `address` as the CPU currently sees the memory
+    pub(crate) fn disassemble(&self, address: u16) -> Disassembly {
+        disassembler::disassemble(address, |address| self.bus.read_byte(address))
+    }
+
+    /// Updates the buttons held down, the input is read from the joypad register
+    pub(crate) fn set_buttons(&mut self, buttons: Buttons) {
+        self.bus.set_buttons(buttons);
+    }
+
+    /// Hands the bytes transmitted over the serial port to `sink`
+    pub(crate) fn set_serial_sink(&mut self, sink: Box<dyn SerialSink>) {
+        self.bus.serial.set_sink(sink);
+    }
+
+    /// Connects the serial port to another emulator, both must connect before running
+    pub(crate) fn set_link(&mut self, link: Box<dyn Link>) {
+        self.bus.serial.set_link(link);
+    }
+
+    /// False once the link partner disconnected
+    pub(crate) fn is_linked(&self) -> bool {
+        self.bus.serial.is_linked()
+    }
+
+    pub(crate) fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.bus.apu.set_sample_rate(sample_rate);
+    }
+
+    /// Enables or mutes one of the 4 sound channels in the audio output
+    pub(crate) fn set_audio_channel_enabled(&mut self, channel: usize, enabled: bool) {
+        self.bus.apu.set_channel_enabled(channel, enabled);
+    }
+
+    /// Returns the audio samples produced since the last call, interleaved left and right
+    pub(crate) fn take_samples(&mut self) -> Vec<f32> {
+        self.bus.apu.take_samples()
+    }
+
+    pub(crate) fn framebuffer(&self) -> &Framebuffer {
+        self.bus.ppu.framebuffer()
+    }
+
+    /// Runs until the PPU completed a frame. With the LCD off no frame is completed, so it stops
+    /// after the time of one frame instead
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Panics if the instruction is unknown
-    fn step(&mut self) -> Instruction {
-        let mut next_byte = self.read_next_byte();
-        let is_prefixed = if next_byte == INSTRUCTION_PREFIX {
-            next_byte = self.read_next_byte();
-            true
-        } else {
-            false
-        };
-        let Some(instruction) = Instruction::from_byte(next_byte, is_prefixed) else {
-            panic!("Unknown opcode 0x{next_byte:x}")
+    /// Returns an error if an instruction could not be executed
+    pub(crate) fn run_frame(&mut self) -> Result<(), ExecutionError> {
+        let end = self.cycles + T_CYCLES_PER_FRAME;
+        while self.cycles < end && !self.bus.ppu.take_frame() {
+            if self.step()?.m_cycles == 0 {
+                // Stopped, no time passes until a button is pressed
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Services a pending interrupt, then steps to next instructions and returns it with the
+    /// cycles it took.
+    ///
+    /// While halted or locked up every step idles for one machine cycle. While stopped no time
+    /// passes. The rest of the system is advanced by the cycles of the step afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the opcode is illegal, unless `lock_on_illegal_opcode` is set, if the
+    /// instruction isn't emulated in the current state or if it made an invalid bus access
+    fn step(&mut self) -> Result<Executed, ExecutionError> {
+        let executed = self.execute_next()?;
+        self.cycles += executed.t_cycles() as u64;
+        self.bus.tick(executed.m_cycles);
+        Ok(executed)
+    }
+
+    /// `Cpu::step` without advancing time
+    fn execute_next(&mut self) -> Result<Executed, ExecutionError> {
+        if self.locked {
+            return Ok(Executed::idle(1));
+        }
+        if self.stopped {
+            if !self.bus.take_joypad_press() {
+                return Ok(Executed::idle(0));
+            }
+            self.stopped = false;
+        }
+        if self.halted {
+            // Any pending interrupt ends HALT, even if IME is disabled
+            if self.bus.interrupts.pending().is_none() {
+                return Ok(Executed::idle(1));
+            }
+            self.halted = false;
+        }
+
+        let interrupt = self.dispatch_interrupt();
+        if self.ime_scheduled {
+            self.ime_scheduled = false;
+            self.ime = true;
+        }
+
+        let address = self.registers.pc;
+        let opcode = self.read_next_byte();
+        if self.halt_bug {
+            self.halt_bug = false;
+            self.registers.pc = self.registers.pc.wrapping_sub(1);
+        }
+        let Some(instruction) = Instruction::decode(opcode, || self.read_next_byte()) else {
+            if self.lock_on_illegal_opcode {
+                self.locked = true;
+                return Ok(Executed::idle(1));
+            }
+            return Err(ExecutionError::IllegalOpcode { opcode, address });
         };
-        self.exec(&instruction);
-        return instruction;
+        if instruction == Instruction::Stop && self.bus.joypad_line_low() {
+            return Err(ExecutionError::UnimplementedInstruction {
+                instruction,
+                address,
+            });
+        }
+        let mut m_cycles = self.exec(&instruction);
+        if let Some(target) = self.bus.take_invalid_access() {
+            return Err(ExecutionError::InvalidBusAccess { target, address });
+        }
+        if interrupt.is_some() {
+            m_cycles += INTERRUPT_DISPATCH_M_CYCLES;
+        }
+        Ok(Executed {
+            instruction: Some(instruction),
+            m_cycles,
+            interrupt,
+        })
+    }
+
+    #[cfg(test)]
+    fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    #[cfg(test)]
+    fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    #[cfg(test)]
+    fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Jumps to the vector of the highest priority pending interrupt if IME is set
+    fn dispatch_interrupt(&mut self) -> Option<Interrupt> {
+        if !self.ime {
+            return None;
+        }
+        let interrupt = self.bus.interrupts.pending()?;
+        self.ime = false;
+        self.ime_scheduled = false;
+        self.bus.interrupts.acknowledge(interrupt);
+        self.push(self.registers.pc);
+        self.registers.pc = interrupt.vector();
+        Some(interrupt)
     }
 
     /// Reads the next byte, incements PC
@@ -40,97 +337,366 @@ impl Cpu {
         byte
     }
 
-    /// Reads the next two bytes interpreted as u16. Respects endiannes and increments PC
-    fn read_next_2_bytes_le(&mut self) -> u16 {
-        let mut bytes = self.bus.read_byte(self.registers.pc) as u16;
-        self.registers.pc = self.registers.pc.wrapping_add(1);
-        bytes |= (self.bus.read_byte(self.registers.pc) as u16) << 8;
-        self.registers.pc = self.registers.pc.wrapping_add(1);
-        bytes
+    /// Reads the 8-bit register, or the byte pointed to by HL for `R8::Hl`
+    fn read_r8(&self, register: R8) -> u8 {
+        match register {
+            R8::A => self.registers.a,
+            R8::B => self.registers.b,
+            R8::C => self.registers.c,
+            R8::D => self.registers.d,
+            R8::E => self.registers.e,
+            R8::H => self.registers.h,
+            R8::L => self.registers.l,
+            R8::Hl => self
+                .bus
+                .read_byte(self.registers.get_16b_register(Registers16b::HL)),
+        }
+    }
+
+    /// Writes the 8-bit register, or the byte pointed to by HL for `R8::Hl`
+    fn write_r8(&mut self, register: R8, value: u8) {
+        match register {
+            R8::A => self.registers.a = value,
+            R8::B => self.registers.b = value,
+            R8::C => self.registers.c = value,
+            R8::D => self.registers.d = value,
+            R8::E => self.registers.e = value,
+            R8::H => self.registers.h = value,
+            R8::L => self.registers.l = value,
+            R8::Hl => self
+                .bus
+                .write_byte(self.registers.get_16b_register(Registers16b::HL), value),
+        }
+    }
+
+    /// Resolves the address of an indirect load and applies the HL increment/decrement
+    fn indirect_address(&mut self, register: IndirectR16) -> u16 {
+        match register {
+            IndirectR16::Bc => self.registers.get_16b_register(Registers16b::BC),
+            IndirectR16::De => self.registers.get_16b_register(Registers16b::DE),
+            IndirectR16::Hli => {
+                let address = self.registers.get_16b_register(Registers16b::HL);
+                self.registers
+                    .set_16b_register(Registers16b::HL, address.wrapping_add(1));
+                address
+            }
+            IndirectR16::Hld => {
+                let address = self.registers.get_16b_register(Registers16b::HL);
+                self.registers
+                    .set_16b_register(Registers16b::HL, address.wrapping_sub(1));
+                address
+            }
+        }
+    }
+
+    fn condition_met(&self, condition: JumpCondition) -> bool {
+        match condition {
+            JumpCondition::Always => true,
+            JumpCondition::Zero => self.registers.f.zero,
+            JumpCondition::NotZero => !self.registers.f.zero,
+            JumpCondition::Carry => self.registers.f.carry,
+            JumpCondition::NotCarry => !self.registers.f.carry,
+        }
+    }
+
+    /// Pushes a 16-bit value onto the stack, high byte first
+    fn push(&mut self, value: u16) {
+        self.registers.sp = self.registers.sp.wrapping_sub(1);
+        self.bus.write_byte(self.registers.sp, (value >> 8) as u8);
+        self.registers.sp = self.registers.sp.wrapping_sub(1);
+        self.bus.write_byte(self.registers.sp, value as u8);
     }
 
-    fn exec(&mut self, instruction: &Instruction) {
-        match instruction {
+    /// Pops a 16-bit value from the stack, low byte first
+    fn pop(&mut self) -> u16 {
+        let mut value = self.bus.read_byte(self.registers.sp) as u16;
+        self.registers.sp = self.registers.sp.wrapping_add(1);
+        value |= (self.bus.read_byte(self.registers.sp) as u16) << 8;
+        self.registers.sp = self.registers.sp.wrapping_add(1);
+        value
+    }
+
+    /// Executes the instruction and returns the machine cycles it took
+    fn exec(&mut self, instruction: &Instruction) -> u8 {
+        let mut branch_taken = false;
+        match *instruction {
             Instruction::Nop => {}
-            Instruction::LdR16Imm(dest) => match dest {
-                instruction::R16::Bc => todo!(),
-                // instruction::R16::De => todo!(),
-                instruction::R16::Hl => {
-                    let value = self.read_next_2_bytes_le();
-                    self.registers.set_16b_register(Registers16b::HL, value);
-                }
-                // instruction::R16::Sp => todo!(),
-                _ => panic!("Destination {dest:?} for load immidiate r16"),
-            },
-            Instruction::Jp(jump_condition) => {
-                if match jump_condition {
-                    JumpCondition::Always => true,
-                    JumpCondition::Zero => self.registers.f.zero,
-                    JumpCondition::NotZero => !self.registers.f.zero,
-                    JumpCondition::Carry => self.registers.f.carry,
-                    JumpCondition::NotCarry => !self.registers.f.carry,
-                } {
-                    self.registers.pc = self.read_next_2_bytes_le();
-                } else {
-                    self.registers.pc = self.registers.pc.wrapping_add(2);
-                }
+            Instruction::Ld(dest, src) => {
+                let value = self.read_r8(src);
+                self.write_r8(dest, value);
             }
-            Instruction::LdImm(dest) => match dest {
-                R8::A => self.registers.a = self.read_next_byte(),
-                R8::B => self.registers.b = self.read_next_byte(),
-                R8::C => self.registers.c = self.read_next_byte(),
-                R8::D => self.registers.d = self.read_next_byte(),
-                R8::E => self.registers.e = self.read_next_byte(),
-                R8::H => self.registers.h = self.read_next_byte(),
-                R8::L => self.registers.l = self.read_next_byte(),
-                R8::Hl => {
-                    let byte = self.read_next_byte();
-                    self.bus
-                        .write_byte(self.registers.get_16b_register(Registers16b::HL), byte)
-                }
-            },
-            Instruction::AddAImm => {
-                let value = self.read_next_byte();
+            Instruction::LdIndirectFromA(dest) => {
+                let address = self.indirect_address(dest);
+                self.bus.write_byte(address, self.registers.a);
+            }
+            Instruction::LdIndirectToA(src) => {
+                let address = self.indirect_address(src);
+                self.registers.a = self.bus.read_byte(address);
+            }
+            Instruction::LdImm(dest, value) => self.write_r8(dest, value),
+            Instruction::LdMemImmFromA(address) => self.bus.write_byte(address, self.registers.a),
+            Instruction::LdMemImmToA(address) => self.registers.a = self.bus.read_byte(address),
+            Instruction::LdMemOffsetImmFromA(offset) => {
+                let address = 0xFF00 | offset as u16;
+                self.bus.write_byte(address, self.registers.a);
+            }
+            Instruction::LdMemOffsetImmToA(offset) => {
+                let address = 0xFF00 | offset as u16;
+                self.registers.a = self.bus.read_byte(address);
+            }
+            Instruction::LdMemOffsetCFromA => {
+                let address = 0xFF00 | self.registers.c as u16;
+                self.bus.write_byte(address, self.registers.a);
+            }
+            Instruction::LdMemOffsetCToA => {
+                let address = 0xFF00 | self.registers.c as u16;
+                self.registers.a = self.bus.read_byte(address);
+            }
+            Instruction::LdImmFromSp(address) => {
+                self.bus.write_byte(address, self.registers.sp as u8);
+                self.bus
+                    .write_byte(address.wrapping_add(1), (self.registers.sp >> 8) as u8);
+            }
+            Instruction::LdR16Imm(dest, value) => {
+                self.registers.set_16b_register(dest.into(), value);
+            }
+            Instruction::LdHlAdjSpImm(offset) => {
+                let value = self.add_sp(offset);
+                self.registers.set_16b_register(Registers16b::HL, value);
+            }
+            Instruction::LdSpHl => {
+                self.registers.sp = self.registers.get_16b_register(Registers16b::HL);
+            }
+            Instruction::AddA(src) => {
+                let value = self.read_r8(src);
                 self.registers.a = self.add(value);
             }
-            Instruction::AddA(dest) => match dest {
-                R8::B => {
-                    let value = self.registers.b;
-                    self.registers.a = self.add(value);
-                }
-                R8::C => {
-                    let value = self.registers.c;
-                    self.registers.a = self.add(value);
-                }
-                R8::D => {
-                    let value = self.registers.d;
-                    self.registers.a = self.add(value);
-                }
-                R8::E => {
-                    let value = self.registers.e;
-                    self.registers.a = self.add(value);
+            Instruction::AdcA(src) => {
+                let value = self.read_r8(src);
+                self.registers.a = self.adc(value);
+            }
+            Instruction::AddHl(src) => {
+                let value = self.registers.get_16b_register(src.into());
+                let result = self.add_hl(value);
+                self.registers.set_16b_register(Registers16b::HL, result);
+            }
+            Instruction::AddAImm(value) => self.registers.a = self.add(value),
+            Instruction::AdcAImm(value) => self.registers.a = self.adc(value),
+            Instruction::AddSpImm(offset) => self.registers.sp = self.add_sp(offset),
+            Instruction::SubA(src) => {
+                let value = self.read_r8(src);
+                self.registers.a = self.sub(value);
+            }
+            Instruction::SubAImm(value) => self.registers.a = self.sub(value),
+            Instruction::SbcA(src) => {
+                let value = self.read_r8(src);
+                self.registers.a = self.sbc(value);
+            }
+            Instruction::SbcAImm(value) => self.registers.a = self.sbc(value),
+            Instruction::AndA(src) => {
+                let value = self.read_r8(src);
+                self.registers.a = self.and(value);
+            }
+            Instruction::AndAImm(value) => self.registers.a = self.and(value),
+            Instruction::XorA(src) => {
+                let value = self.read_r8(src);
+                self.registers.a = self.xor(value);
+            }
+            Instruction::XorAImm(value) => self.registers.a = self.xor(value),
+            Instruction::OrA(src) => {
+                let value = self.read_r8(src);
+                self.registers.a = self.or(value);
+            }
+            Instruction::OrAImm(value) => self.registers.a = self.or(value),
+            Instruction::CpA(src) => {
+                let value = self.read_r8(src);
+                self.sub(value);
+            }
+            Instruction::CpAImm(value) => {
+                self.sub(value);
+            }
+            Instruction::IncR16(dest) => {
+                let value = self.registers.get_16b_register(dest.into());
+                self.registers
+                    .set_16b_register(dest.into(), value.wrapping_add(1));
+            }
+            Instruction::DecR16(dest) => {
+                let value = self.registers.get_16b_register(dest.into());
+                self.registers
+                    .set_16b_register(dest.into(), value.wrapping_sub(1));
+            }
+            Instruction::Inc(dest) => {
+                let value = self.read_r8(dest);
+                let result = value.wrapping_add(1);
+                self.registers.f.zero = result == 0;
+                self.registers.f.substraction = false;
+                self.registers.f.half_carry = value & 0xF == 0xF;
+                self.write_r8(dest, result);
+            }
+            Instruction::Dec(dest) => {
+                let value = self.read_r8(dest);
+                let result = value.wrapping_sub(1);
+                self.registers.f.zero = result == 0;
+                self.registers.f.substraction = true;
+                self.registers.f.half_carry = value & 0xF == 0;
+                self.write_r8(dest, result);
+            }
+            Instruction::Jr(jump_condition, offset) => {
+                branch_taken = self.condition_met(jump_condition);
+                if branch_taken {
+                    self.registers.pc = self.registers.pc.wrapping_add_signed(offset as i16);
                 }
-                R8::H => {
-                    let value = self.registers.h;
-                    self.registers.a = self.add(value);
+            }
+            Instruction::Jp(jump_condition, address) => {
+                branch_taken = self.condition_met(jump_condition);
+                if branch_taken {
+                    self.registers.pc = address;
                 }
-                R8::L => {
-                    let value = self.registers.l;
-                    self.registers.a = self.add(value);
+            }
+            Instruction::JpHl => {
+                self.registers.pc = self.registers.get_16b_register(Registers16b::HL);
+            }
+            Instruction::Rlca => {
+                self.registers.a = self.rlc(self.registers.a);
+                self.registers.f.zero = false;
+            }
+            Instruction::Rrca => {
+                self.registers.a = self.rrc(self.registers.a);
+                self.registers.f.zero = false;
+            }
+            Instruction::Rla => {
+                self.registers.a = self.rl(self.registers.a);
+                self.registers.f.zero = false;
+            }
+            Instruction::Rra => {
+                self.registers.a = self.rr(self.registers.a);
+                self.registers.f.zero = false;
+            }
+            Instruction::Rlc(dest) => {
+                let value = self.read_r8(dest);
+                let result = self.rlc(value);
+                self.write_r8(dest, result);
+            }
+            Instruction::Rrc(dest) => {
+                let value = self.read_r8(dest);
+                let result = self.rrc(value);
+                self.write_r8(dest, result);
+            }
+            Instruction::Rl(dest) => {
+                let value = self.read_r8(dest);
+                let result = self.rl(value);
+                self.write_r8(dest, result);
+            }
+            Instruction::Rr(dest) => {
+                let value = self.read_r8(dest);
+                let result = self.rr(value);
+                self.write_r8(dest, result);
+            }
+            Instruction::Sla(dest) => {
+                let value = self.read_r8(dest);
+                let result = value << 1;
+                self.set_shift_flags(result, value & 0x80 != 0);
+                self.write_r8(dest, result);
+            }
+            Instruction::Sra(dest) => {
+                let value = self.read_r8(dest);
+                let result = (value >> 1) | (value & 0x80);
+                self.set_shift_flags(result, value & 1 != 0);
+                self.write_r8(dest, result);
+            }
+            Instruction::Swap(dest) => {
+                let value = self.read_r8(dest);
+                let result = value.rotate_left(4);
+                self.set_shift_flags(result, false);
+                self.write_r8(dest, result);
+            }
+            Instruction::Srl(dest) => {
+                let value = self.read_r8(dest);
+                let result = value >> 1;
+                self.set_shift_flags(result, value & 1 != 0);
+                self.write_r8(dest, result);
+            }
+            Instruction::Bit(bit, src) => {
+                let value = self.read_r8(src);
+                self.registers.f.zero = value >> bit & 1 == 0;
+                self.registers.f.substraction = false;
+                self.registers.f.half_carry = true;
+            }
+            Instruction::Res(bit, dest) => {
+                let value = self.read_r8(dest);
+                self.write_r8(dest, value & !(1 << bit));
+            }
+            Instruction::Set(bit, dest) => {
+                let value = self.read_r8(dest);
+                self.write_r8(dest, value | 1 << bit);
+            }
+            Instruction::Daa => self.daa(),
+            Instruction::Cpl => {
+                self.registers.a = !self.registers.a;
+                self.registers.f.substraction = true;
+                self.registers.f.half_carry = true;
+            }
+            Instruction::Scf => {
+                self.registers.f.substraction = false;
+                self.registers.f.half_carry = false;
+                self.registers.f.carry = true;
+            }
+            Instruction::Ccf => {
+                self.registers.f.substraction = false;
+                self.registers.f.half_carry = false;
+                self.registers.f.carry = !self.registers.f.carry;
+            }
+            Instruction::Ret(jump_condition) => {
+                branch_taken = self.condition_met(jump_condition);
+                if branch_taken {
+                    self.registers.pc = self.pop();
                 }
-                R8::A => {
-                    let value = self.registers.a;
-                    self.registers.a = self.add(value);
+            }
+            Instruction::RetI => {
+                self.registers.pc = self.pop();
+                self.ime = true;
+            }
+            Instruction::Di => {
+                self.ime = false;
+                self.ime_scheduled = false;
+            }
+            Instruction::Ei => self.ime_scheduled = true,
+            Instruction::Halt => {
+                // With IME disabled and an interrupt already pending HALT is skipped, and the
+                // following byte is read twice
+                if !self.ime && self.bus.interrupts.pending().is_some() {
+                    self.halt_bug = true;
+                } else {
+                    self.halted = true;
                 }
-                R8::Hl => {
-                    let value = self
-                        .bus
-                        .read_byte(self.registers.get_16b_register(Registers16b::HL));
-                    self.registers.a = self.add(value);
+            }
+            Instruction::Stop => {
+                // Only a press from now on ends STOP, not an earlier one or its leftover IF bit
+                self.bus.take_joypad_press();
+                self.stopped = true;
+                self.bus.write_byte(DIVIDER_ADDRESS, 0);
+            }
+            Instruction::Pop(dest) => {
+                let value = self.pop();
+                self.registers.set_16b_register(dest.into(), value);
+            }
+            Instruction::Push(src) => {
+                let value = self.registers.get_16b_register(src.into());
+                self.push(value);
+            }
+            Instruction::Call(jump_condition, address) => {
+                branch_taken = self.condition_met(jump_condition);
+                if branch_taken {
+                    self.push(self.registers.pc);
+                    self.registers.pc = address;
                 }
-            },
-            _ => todo!("Instruction {:?} not implemented", instruction),
+            }
+            Instruction::Rst(vector) => {
+                self.push(self.registers.pc);
+                self.registers.pc = vector.address();
+            }
         }
+        instruction.m_cycles(branch_taken)
     }
 
     fn add(&mut self, value: u8) -> u8 {
@@ -141,40 +707,157 @@ impl Cpu {
         self.registers.f.carry = did_overflow;
         self.registers.f.half_carry = (self.registers.a & 0xF) + (value & 0xF) > 0xF;
 
-        return result;
+        result
     }
-}
 
-const MEMORY_SIZE: usize = 0xFFFF;
+    fn adc(&mut self, value: u8) -> u8 {
+        let carry = self.registers.f.carry as u8;
+        let result = self.registers.a.wrapping_add(value).wrapping_add(carry);
+        self.registers.f.zero = result == 0;
+        self.registers.f.substraction = false;
+        self.registers.f.carry = self.registers.a as u16 + value as u16 + carry as u16 > 0xFF;
+        self.registers.f.half_carry = (self.registers.a & 0xF) + (value & 0xF) + carry > 0xF;
 
-struct MemoryBus {
-    memory: [u8; MEMORY_SIZE],
-}
+        result
+    }
 
-impl Default for MemoryBus {
-    fn default() -> Self {
-        Self {
-            memory: [0; 0xFFFF],
-        }
+    /// Subtracts from A and sets the flags. Also used by CP, which discards the result
+    fn sub(&mut self, value: u8) -> u8 {
+        let (result, did_overflow) = self.registers.a.overflowing_sub(value);
+        self.registers.f.zero = result == 0;
+        self.registers.f.substraction = true;
+        self.registers.f.carry = did_overflow;
+        self.registers.f.half_carry = (self.registers.a & 0xF) < (value & 0xF);
+
+        result
     }
-}
 
-impl MemoryBus {
-    fn read_byte(&self, address: u16) -> u8 {
-        self.memory[address as usize]
+    fn sbc(&mut self, value: u8) -> u8 {
+        let carry = self.registers.f.carry as u8;
+        let result = self.registers.a.wrapping_sub(value).wrapping_sub(carry);
+        self.registers.f.zero = result == 0;
+        self.registers.f.substraction = true;
+        self.registers.f.carry = (self.registers.a as u16) < value as u16 + carry as u16;
+        self.registers.f.half_carry = (self.registers.a & 0xF) < (value & 0xF) + carry;
+
+        result
     }
-    fn write_byte(&mut self, address: u16, byte: u8) {
-        self.memory[address as usize] = byte;
+
+    fn and(&mut self, value: u8) -> u8 {
+        let result = self.registers.a & value;
+        self.registers.f = FlagRegister {
+            zero: result == 0,
+            substraction: false,
+            half_carry: true,
+            carry: false,
+        };
+
+        result
     }
 
-    // TODO: maybe check bounds
-    fn copy_bytes(&mut self, start_address: u16, bytes: &[u8]) {
-        bytes
-            .iter()
-            .zip(self.memory[start_address as usize..].iter_mut())
-            .for_each(|(input, memory)| {
-                *memory = *input;
-            });
+    fn xor(&mut self, value: u8) -> u8 {
+        let result = self.registers.a ^ value;
+        self.registers.f = FlagRegister {
+            zero: result == 0,
+            ..Default::default()
+        };
+
+        result
+    }
+
+    fn or(&mut self, value: u8) -> u8 {
+        let result = self.registers.a | value;
+        self.registers.f = FlagRegister {
+            zero: result == 0,
+            ..Default::default()
+        };
+
+        result
+    }
+
+    /// Adds a 16-bit value to HL. The zero flag is left untouched
+    fn add_hl(&mut self, value: u16) -> u16 {
+        let hl = self.registers.get_16b_register(Registers16b::HL);
+        let (result, did_overflow) = hl.overflowing_add(value);
+        self.registers.f.substraction = false;
+        self.registers.f.carry = did_overflow;
+        self.registers.f.half_carry = (hl & 0xFFF) + (value & 0xFFF) > 0xFFF;
+
+        result
+    }
+
+    /// Adds a signed offset to SP. Carry and half carry are computed on the lower byte
+    fn add_sp(&mut self, offset: i8) -> u16 {
+        let sp = self.registers.sp;
+        let value = offset as u8;
+        self.registers.f = FlagRegister {
+            zero: false,
+            substraction: false,
+            half_carry: (sp & 0xF) + (value & 0xF) as u16 > 0xF,
+            carry: (sp & 0xFF) + value as u16 > 0xFF,
+        };
+
+        sp.wrapping_add_signed(offset as i16)
+    }
+
+    fn rlc(&mut self, value: u8) -> u8 {
+        let result = value.rotate_left(1);
+        self.set_shift_flags(result, value & 0x80 != 0);
+        result
+    }
+
+    fn rrc(&mut self, value: u8) -> u8 {
+        let result = value.rotate_right(1);
+        self.set_shift_flags(result, value & 1 != 0);
+        result
+    }
+
+    fn rl(&mut self, value: u8) -> u8 {
+        let result = value << 1 | self.registers.f.carry as u8;
+        self.set_shift_flags(result, value & 0x80 != 0);
+        result
+    }
+
+    fn rr(&mut self, value: u8) -> u8 {
+        let result = value >> 1 | (self.registers.f.carry as u8) << 7;
+        self.set_shift_flags(result, value & 1 != 0);
+        result
+    }
+
+    /// Flags shared by all rotate, shift and swap instructions
+    fn set_shift_flags(&mut self, result: u8, carry: bool) {
+        self.registers.f = FlagRegister {
+            zero: result == 0,
+            substraction: false,
+            half_carry: false,
+            carry,
+        };
+    }
+
+    /// Adjusts A to a valid BCD number after an addition or subtraction
+    fn daa(&mut self) {
+        let mut a = self.registers.a;
+        let mut carry = self.registers.f.carry;
+        if !self.registers.f.substraction {
+            if carry || a > 0x99 {
+                a = a.wrapping_add(0x60);
+                carry = true;
+            }
+            if self.registers.f.half_carry || a & 0xF > 0x9 {
+                a = a.wrapping_add(0x6);
+            }
+        } else {
+            if carry {
+                a = a.wrapping_sub(0x60);
+            }
+            if self.registers.f.half_carry {
+                a = a.wrapping_sub(0x6);
+            }
+        }
+        self.registers.a = a;
+        self.registers.f.zero = a == 0;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = carry;
     }
 }
 
@@ -199,11 +882,11 @@ struct Registers {
 
 impl Display for Registers {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "A:\t{:b} F:\t{:b}\n", self.a, Into::<u8>::into(self.f))?;
-        write!(f, "B:\t{:b} C:\t{:b}\n", self.b, self.c)?;
-        write!(f, "D:\t{:b} E:\t{:b}\n", self.d, self.e)?;
-        write!(f, "H:\t{:b} L:\t{:b}\n", self.h, self.l)?;
-        write!(f, "SP:\t{:b} PC:\t{:b}\n", self.h, self.l)?;
+        writeln!(f, "A:\t{:b} F:\t{:b}", self.a, Into::<u8>::into(self.f))?;
+        writeln!(f, "B:\t{:b} C:\t{:b}", self.b, self.c)?;
+        writeln!(f, "D:\t{:b} E:\t{:b}", self.d, self.e)?;
+        writeln!(f, "H:\t{:b} L:\t{:b}", self.h, self.l)?;
+        writeln!(f, "SP:\t{:b} PC:\t{:b}", self.sp, self.pc)?;
         Ok(())
     }
 }
@@ -214,9 +897,32 @@ enum Registers16b {
     DE,
     HL,
     SP,
+    #[cfg(test)]
     PC,
 }
 
+impl From<R16> for Registers16b {
+    fn from(register: R16) -> Self {
+        match register {
+            R16::Bc => Self::BC,
+            R16::De => Self::DE,
+            R16::Hl => Self::HL,
+            R16::Sp => Self::SP,
+        }
+    }
+}
+
+impl From<R16_2> for Registers16b {
+    fn from(register: R16_2) -> Self {
+        match register {
+            R16_2::Bc => Self::BC,
+            R16_2::De => Self::DE,
+            R16_2::Hl => Self::HL,
+            R16_2::Af => Self::AF,
+        }
+    }
+}
+
 impl Registers {
     fn get_16b_register(&self, register: Registers16b) -> u16 {
         match register {
@@ -228,12 +934,13 @@ impl Registers {
             Registers16b::DE => (self.d as u16) << 8 | (self.e as u16),
             Registers16b::HL => (self.h as u16) << 8 | (self.l as u16),
             Registers16b::SP => self.sp,
+            #[cfg(test)]
             Registers16b::PC => self.pc,
         }
     }
     fn set_16b_register(&mut self, register: Registers16b, value: u16) {
         let higher = (value >> 8) as u8;
-        let lower = value as u8 & 0xff;
+        let lower = value as u8;
         match register {
             Registers16b::AF => {
                 self.a = higher;
@@ -254,6 +961,7 @@ impl Registers {
             }
 
             Registers16b::SP => self.sp = value,
+            #[cfg(test)]
             Registers16b::PC => self.pc = value,
         };
     }
@@ -300,9 +1008,58 @@ impl From<FlagRegister> for u8 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cartridge::test_rom;
+    use crate::ppu::SCREEN_WIDTH;
+    use crate::serial::tests::CaptureSink;
 
     const SIMPLE_ADD: &[u8] = include_bytes!("../test_roms/simple_add.gb");
     const ALL_ADDS_AND_LOADS: &[u8] = include_bytes!("../test_roms/all_adds_and_loads.gb");
+    const LOADS: &[u8] = include_bytes!("../test_roms/loads.gb");
+    const ALU: &[u8] = include_bytes!("../test_roms/alu.gb");
+    const ARITHMETIC_16B: &[u8] = include_bytes!("../test_roms/arithmetic_16b.gb");
+    const ROTATES_AND_SHIFTS: &[u8] = include_bytes!("../test_roms/rotates_and_shifts.gb");
+    const BIT_OPERATIONS: &[u8] = include_bytes!("../test_roms/bit_operations.gb");
+    const STACK_AND_CALLS: &[u8] = include_bytes!("../test_roms/stack_and_calls.gb");
+    const MISC: &[u8] = include_bytes!("../test_roms/misc.gb");
+    const BRANCH_TIMING: &[u8] = include_bytes!("../test_roms/branch_timing.gb");
+    const INTERRUPTS: &[u8] = include_bytes!("../test_roms/interrupts.gb");
+    const HALT_AND_STOP: &[u8] = include_bytes!("../test_roms/halt_and_stop.gb");
+    const ILLEGAL_OPCODE: &[u8] = include_bytes!("../test_roms/illegal_opcode.gb");
+    const MBC1: &[u8] = include_bytes!("../test_roms/mbc1.gb");
+    const MBC2: &[u8] = include_bytes!("../test_roms/mbc2.gb");
+    const MBC3: &[u8] = include_bytes!("../test_roms/mbc3.gb");
+    const MBC5: &[u8] = include_bytes!("../test_roms/mbc5.gb");
+    const TIMER: &[u8] = include_bytes!("../test_roms/timer.gb");
+    const PPU: &[u8] = include_bytes!("../test_roms/ppu.gb");
+    const BACKGROUND: &[u8] = include_bytes!("../test_roms/background.gb");
+    const OBJECTS: &[u8] = include_bytes!("../test_roms/objects.gb");
+    const OAM_DMA: &[u8] = include_bytes!("../test_roms/oam_dma.gb");
+    const JOYPAD: &[u8] = include_bytes!("../test_roms/joypad.gb");
+    const APU: &[u8] = include_bytes!("../test_roms/apu.gb");
+    const SERIAL: &[u8] = include_bytes!("../test_roms/serial.gb");
+    const LINK: &[u8] = include_bytes!("../test_roms/link.gb");
+
+    /// Runs a ROM until it jumps to the end of the address space
+    fn run_rom(rom: &[u8]) -> Cpu {
+        run_rom_with(rom, |_| {})
+    }
+
+    /// Runs a ROM after preparing the CPU, e.g. holding buttons down the whole time
+    fn run_rom_with(rom: &[u8], setup: impl FnOnce(&mut Cpu)) -> Cpu {
+        let mut cpu = Cpu::default();
+        cpu.bus
+            .insert_cartridge(Cartridge::new(rom.to_vec()).unwrap());
+        setup(&mut cpu);
+        while cpu.registers.pc < 0xFFFF {
+            cpu.step().unwrap();
+        }
+        cpu
+    }
+
+    /// Reads the results a test ROM stored at the start of WRAM
+    fn wram_results(cpu: &Cpu, len: u16) -> Vec<u8> {
+        (0..len).map(|i| cpu.bus.read_byte(0xC000 + i)).collect()
+    }
 
     #[test]
     fn flag_register_from_u8() {
@@ -407,7 +1164,7 @@ mod tests {
 
     #[test]
     fn load_and_copy_bytes() {
-        let start_address = 0x1;
+        let start_address = 0xC001;
         let bytes = [0x0, 0x1, 0x3, 0x4];
         let mut cpu = Cpu::default();
         cpu.bus.copy_bytes(start_address, &bytes);
@@ -418,10 +1175,11 @@ mod tests {
     #[test]
     fn simple_add() {
         let mut cpu = Cpu::default();
-        cpu.bus.copy_bytes(0, SIMPLE_ADD);
+        cpu.bus
+            .insert_cartridge(Cartridge::new(SIMPLE_ADD.to_vec()).unwrap());
 
         while cpu.registers.pc < 0xFFFF {
-            cpu.step();
+            cpu.step().unwrap();
         }
         println!("{}", cpu.registers);
 
@@ -430,12 +1188,14 @@ mod tests {
     #[test]
     fn all_add_and_loads() {
         let mut cpu = Cpu::default();
-        cpu.bus.copy_bytes(0, ALL_ADDS_AND_LOADS);
+        cpu.bus
+            .insert_cartridge(Cartridge::new(ALL_ADDS_AND_LOADS.to_vec()).unwrap());
 
         while cpu.registers.pc < 0xFFFF {
-            let instruction = cpu.step();
-            if !matches!(instruction, Instruction::Nop) {
-                println!("{:?}", instruction)
+            let disassembly = cpu.disassemble(cpu.registers.pc);
+            let executed = cpu.step().unwrap();
+            if !matches!(executed.instruction, Some(Instruction::Nop)) {
+                println!("{disassembly}")
             }
         }
         println!("{}", cpu.registers);
@@ -444,4 +1204,684 @@ mod tests {
         assert_eq!(cpu.registers.h, 15);
         assert_eq!(cpu.registers.l, 15);
     }
+
+    #[test]
+    fn loads() {
+        let cpu = run_rom(LOADS);
+
+        assert_eq!(
+            wram_results(&cpu, 17),
+            [
+                0x12, 0x34, 0x34, 0x56, 0x78, 0x9A, 0xF0, 0x56, 0x78, 0x9A, 0xF0, 0x9A, 0xC3, 0x24,
+                0x42, 0xCD, 0xAB
+            ]
+        );
+    }
+
+    #[test]
+    fn alu() {
+        let cpu = run_rom(ALU);
+
+        let expected: [(u8, u8); 23] = [
+            // ADD
+            (0x00, 0xB0),
+            (0x18, 0x00),
+            // ADC
+            (0xF1, 0x20),
+            (0x00, 0xB0),
+            // SUB
+            (0x00, 0xC0),
+            (0x2F, 0x60),
+            // SBC
+            (0x10, 0x40),
+            (0xEB, 0x70),
+            // AND
+            (0x1A, 0x20),
+            (0x00, 0xA0),
+            // XOR
+            (0x00, 0x80),
+            (0xF0, 0x00),
+            // OR
+            (0x5A, 0x00),
+            (0x00, 0x80),
+            // CP
+            (0x3C, 0x60),
+            (0x3C, 0xC0),
+            (0x3C, 0x50),
+            // INC
+            (0x00, 0xB0),
+            (0x51, 0x00),
+            // DEC
+            (0x00, 0xC0),
+            (0xFF, 0x60),
+            // INC (HL) / DEC (HL)
+            (0x10, 0x20),
+            (0x0F, 0x60),
+        ];
+        let results = wram_results(&cpu, 46);
+        for (i, (a, f)) in expected.iter().enumerate() {
+            assert_eq!(results[i * 2], *a, "Wrong result for operation {i}");
+            assert_eq!(results[i * 2 + 1], *f, "Wrong flags for operation {i}");
+        }
+    }
+
+    #[test]
+    fn arithmetic_16b() {
+        let cpu = run_rom(ARITHMETIC_16B);
+        let results = wram_results(&cpu, 32);
+
+        // ADD HL, rr
+        assert_eq!(
+            results[0..12],
+            [
+                0x28, 0x90, 0xA0, 0x46, 0x14, 0xB0, 0x00, 0x10, 0x20, 0x01, 0xE0, 0x00
+            ]
+        );
+        // LD HL, SP+e
+        assert_eq!(results[12..18], [0xFA, 0xFF, 0x00, 0xF0, 0xFF, 0x30]);
+        // INC rr / DEC rr
+        assert_eq!(
+            results[18..26],
+            [0x00, 0x00, 0xFF, 0xFF, 0x00, 0x01, 0x01, 0xE0]
+        );
+        // ADD SP, e
+        assert_eq!(results[26..32], [0x00, 0xD1, 0x30, 0xFF, 0xCF, 0x00]);
+    }
+
+    #[test]
+    fn rotates_and_shifts() {
+        let cpu = run_rom(ROTATES_AND_SHIFTS);
+
+        let expected: [(u8, u8); 20] = [
+            // RLCA / RRCA / RLA / RRA
+            (0x0B, 0x10),
+            (0x9D, 0x10),
+            (0x2A, 0x10),
+            (0xC0, 0x10),
+            (0x00, 0x00),
+            // RLC r / RRC r
+            (0x0B, 0x10),
+            (0x00, 0x80),
+            (0x80, 0x10),
+            // RL r / RR r
+            (0x00, 0x90),
+            (0x80, 0x10),
+            // SLA r / SRA r / SRL r
+            (0x00, 0x90),
+            (0xC5, 0x00),
+            (0x00, 0x90),
+            (0x7F, 0x10),
+            // SWAP r
+            (0x0F, 0x00),
+            (0x00, 0x80),
+            // (HL) variants
+            (0x0B, 0x10),
+            (0x16, 0x00),
+            (0x61, 0x00),
+            (0x30, 0x10),
+        ];
+        let results = wram_results(&cpu, 40);
+        for (i, (a, f)) in expected.iter().enumerate() {
+            assert_eq!(results[i * 2], *a, "Wrong result for operation {i}");
+            assert_eq!(results[i * 2 + 1], *f, "Wrong flags for operation {i}");
+        }
+    }
+
+    #[test]
+    fn bit_operations() {
+        let cpu = run_rom(BIT_OPERATIONS);
+        let results = wram_results(&cpu, 14);
+
+        // BIT b, r
+        assert_eq!(results[0..6], [0x80, 0x30, 0x80, 0xB0, 0xEF, 0xA0]);
+        // RES b, r / SET b, r
+        assert_eq!(results[6..10], [0x7E, 0xA0, 0x48, 0x80]);
+        // (HL) variants
+        assert_eq!(results[10..14], [0x71, 0x20, 0x71, 0xA0]);
+    }
+
+    #[test]
+    fn stack_and_calls() {
+        let cpu = run_rom(STACK_AND_CALLS);
+
+        assert_eq!(wram_results(&cpu, 4), [0x34, 0x12, 0xF0, 0x12]);
+        assert_eq!(cpu.bus.read_byte(0xC004), 13, "Not all jumps were taken");
+        assert_eq!(cpu.bus.read_byte(0xC005), 0, "A jump was wrongly taken");
+        assert_eq!(cpu.registers.sp, 0xE000, "Unbalanced stack");
+    }
+
+    #[test]
+    fn misc() {
+        let cpu = run_rom(MISC);
+        let results = wram_results(&cpu, 16);
+
+        // DAA
+        assert_eq!(
+            results[0..8],
+            [0x83, 0x00, 0x00, 0x90, 0x45, 0x40, 0x99, 0x50]
+        );
+        // CPL
+        assert_eq!(results[8..10], [0xCA, 0xE0]);
+        // SCF / CCF
+        assert_eq!(results[10..16], [0x00, 0x90, 0x00, 0x80, 0x80, 0x90]);
+    }
+
+    #[test]
+    fn branch_timing() {
+        let mut cpu = Cpu::default();
+        cpu.bus
+            .insert_cartridge(Cartridge::new(BRANCH_TIMING.to_vec()).unwrap());
+
+        let mut m_cycles = Vec::new();
+        while cpu.registers.pc < 0xFFFF {
+            let executed = cpu.step().unwrap();
+            if !matches!(executed.instruction, Some(Instruction::Nop)) {
+                m_cycles.push(executed.m_cycles);
+            }
+        }
+
+        // JP, LD SP, XOR, JR not taken/taken, JP not taken/taken, CALL not taken/taken,
+        // RET not taken/taken, JP
+        assert_eq!(m_cycles, [4, 3, 1, 2, 3, 3, 4, 3, 6, 2, 5, 4]);
+        // 0x100 NOPs before the header jump
+        let total: u64 = 0x100 + m_cycles.iter().map(|&cycles| cycles as u64).sum::<u64>();
+        assert_eq!(cpu.cycles, total * 4);
+    }
+
+    #[test]
+    fn interrupts() {
+        let mut cpu = Cpu::default();
+        cpu.bus
+            .insert_cartridge(Cartridge::new(INTERRUPTS.to_vec()).unwrap());
+
+        let mut dispatched = Vec::new();
+        while cpu.registers.pc < 0xFFFF {
+            let executed = cpu.step().unwrap();
+            if let Some(interrupt) = executed.interrupt {
+                // Dispatch followed by the PUSH AF of the handler
+                assert_eq!(executed.m_cycles, 5 + 4);
+                dispatched.push(interrupt);
+            }
+        }
+
+        assert_eq!(
+            dispatched,
+            [
+                Interrupt::Timer,
+                Interrupt::VBlank,
+                Interrupt::LcdStat,
+                Interrupt::Timer,
+                Interrupt::Serial,
+                Interrupt::Joypad
+            ]
+        );
+        assert_eq!(
+            wram_results(&cpu, 10),
+            [0x01, 0x02, 0x50, 0x40, 0x48, 0x50, 0x58, 0x60, 0xE0, 0xF1]
+        );
+        assert_eq!(cpu.registers.sp, 0xE000, "Unbalanced stack");
+    }
+
+    #[test]
+    fn halt_and_stop() {
+        let mut cpu = Cpu::default();
+        cpu.bus
+            .insert_cartridge(Cartridge::new(HALT_AND_STOP.to_vec()).unwrap());
+
+        // HALT idles until an interrupt is pending, even with IME disabled
+        while !cpu.is_halted() {
+            cpu.step().unwrap();
+        }
+        let pc = cpu.registers.pc;
+        for _ in 0..10 {
+            let executed = cpu.step().unwrap();
+            assert_eq!(executed.instruction, None);
+            assert_eq!(executed.m_cycles, 1);
+        }
+        assert_eq!(cpu.registers.pc, pc);
+        cpu.bus.interrupts.request(Interrupt::Timer);
+        cpu.step().unwrap();
+        assert!(!cpu.is_halted());
+
+        // The HALT bug does not halt, the next one waits with IME enabled
+        while !cpu.is_halted() {
+            cpu.step().unwrap();
+        }
+        cpu.bus.interrupts.request(Interrupt::Timer);
+
+        // STOP idles without any time passing until a button is pressed, the Joypad interrupt
+        // requested before doesn't count
+        while !cpu.is_stopped() {
+            cpu.step().unwrap();
+        }
+        assert!(cpu.bus.interrupts.requested(Interrupt::Joypad));
+        let pc = cpu.registers.pc;
+        let cycles = cpu.cycles;
+        for _ in 0..10 {
+            assert_eq!(cpu.step().unwrap().m_cycles, 0);
+        }
+        assert_eq!(cpu.registers.pc, pc);
+        assert_eq!(cpu.cycles, cycles);
+        cpu.set_buttons(Buttons {
+            start: true,
+            ..Default::default()
+        });
+
+        while cpu.registers.pc < 0xFFFF {
+            cpu.step().unwrap();
+        }
+        assert!(!cpu.is_halted());
+        assert!(!cpu.is_stopped());
+        assert_eq!(wram_results(&cpu, 5), [0x01, 0x02, 0x50, 0x03, 0x04]);
+    }
+
+    #[test]
+    fn illegal_opcode() {
+        let mut cpu = Cpu::default();
+        cpu.bus
+            .insert_cartridge(Cartridge::new(ILLEGAL_OPCODE.to_vec()).unwrap());
+
+        let error = loop {
+            if let Err(error) = cpu.step() {
+                break error;
+            }
+        };
+
+        assert_eq!(
+            error,
+            ExecutionError::IllegalOpcode {
+                opcode: 0xD3,
+                address: 0x0152
+            }
+        );
+        assert_eq!(error.to_string(), "Illegal opcode 0xD3 at 0x0152");
+        assert_eq!(cpu.registers.a, 0x01);
+    }
+
+    #[test]
+    fn stop_with_button_held() {
+        let mut rom = test_rom(0x00, 0x00, 0x00, 0x00);
+        rom[0x0100..0x0102].copy_from_slice(&[0x10, 0x00]);
+        let mut cpu = Cpu::new(Cartridge::new(rom).unwrap());
+        cpu.set_buttons(Buttons {
+            a: true,
+            ..Default::default()
+        });
+
+        let error = cpu.step().unwrap_err();
+        assert_eq!(
+            error,
+            ExecutionError::UnimplementedInstruction {
+                instruction: Instruction::Stop,
+                address: 0x0100
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Unimplemented instruction stop at 0x0100"
+        );
+        assert!(!cpu.is_stopped());
+    }
+
+    #[test]
+    fn invalid_bus_access() {
+        let mut rom = test_rom(0x00, 0x00, 0x00, 0x00);
+        // ld a, [$FEA0]
+        rom[0x0100..0x0103].copy_from_slice(&[0xFA, 0xA0, 0xFE]);
+        let mut cpu = Cpu::new(Cartridge::new(rom.clone()).unwrap());
+        assert!(cpu.step().is_ok(), "The hardware tolerates the access");
+
+        let mut cpu = Cpu::new(Cartridge::new(rom).unwrap());
+        cpu.set_strict_bus_access(true);
+        let error = cpu.step().unwrap_err();
+        assert_eq!(
+            error,
+            ExecutionError::InvalidBusAccess {
+                target: 0xFEA0,
+                address: 0x0100
+            }
+        );
+        assert_eq!(error.address(), 0x0100);
+    }
+
+    #[test]
+    fn illegal_opcode_locks_up() {
+        let mut cpu = Cpu::default();
+        cpu.set_lock_on_illegal_opcode(true);
+        cpu.bus
+            .insert_cartridge(Cartridge::new(ILLEGAL_OPCODE.to_vec()).unwrap());
+
+        while !cpu.is_locked() {
+            cpu.step().unwrap();
+        }
+        let pc = cpu.registers.pc;
+        // Not even interrupts recover from the lock up
+        cpu.ime = true;
+        cpu.bus.write_byte(0xFFFF, 0x1F);
+        cpu.bus.interrupts.request(Interrupt::VBlank);
+        for _ in 0..10 {
+            assert_eq!(cpu.step(), Ok(Executed::idle(1)));
+        }
+
+        assert_eq!(cpu.registers.pc, pc);
+        assert_eq!(cpu.registers.a, 0x01);
+    }
+
+    #[test]
+    fn mbc1() {
+        let cpu = run_rom(MBC1);
+        let results = wram_results(&cpu, 11);
+
+        // ROM banking
+        assert_eq!(results[0..6], [0x01, 0x02, 0x03, 0x01, 0x01, 0x02]);
+        // RAM enable and banking
+        assert_eq!(results[6..11], [0xFF, 0x22, 0x11, 0x11, 0xFF]);
+    }
+
+    #[test]
+    fn mbc2() {
+        let cpu = run_rom(MBC2);
+        let results = wram_results(&cpu, 5);
+
+        // Register select with address bit 8
+        assert_eq!(results[0..2], [0x03, 0x03]);
+        // Half-byte RAM and its mirror
+        assert_eq!(results[2..4], [0xFA, 0xFA]);
+        assert_eq!(results[4], 0x01);
+    }
+
+    #[test]
+    fn mbc3() {
+        let cpu = run_rom(MBC3);
+        let results = wram_results(&cpu, 7);
+
+        // ROM banking
+        assert_eq!(results[0], 0x03);
+        // Latched RTC registers
+        assert_eq!(results[1..5], [42, 7, 0x40, 42]);
+        // RAM banking
+        assert_eq!(results[5..7], [0x00, 0x55]);
+    }
+
+    #[test]
+    fn mbc5() {
+        let cpu = run_rom(MBC5);
+        let results = wram_results(&cpu, 5);
+
+        // ROM banking, bank 0 maps the jp opcode at 0x0000
+        assert_eq!(results[0..3], [0x03, 0xC3, 0x02]);
+        // RAM banking
+        assert_eq!(results[3..5], [0xF0, 0x11]);
+    }
+
+    #[test]
+    fn timer() {
+        let cpu = run_rom(TIMER);
+        let results = wram_results(&cpu, 5);
+
+        // DIV and TIMA counting
+        assert_eq!(results[0..2], [3, 21]);
+        assert_eq!(results[2], 0xFD);
+        // Overflow
+        assert_eq!(results[3..5], [0xAB, 0xE4]);
+    }
+
+    #[test]
+    fn ppu() {
+        let cpu = run_rom(PPU);
+        let results = wram_results(&cpu, 8);
+
+        // VBlank: LY and STAT
+        assert_eq!(results[0..2], [144, 0x81]);
+        // LYC interrupt: LY and STAT
+        assert_eq!(results[2..4], [10, 0xC6]);
+        // HBlank interrupt: STAT and VRAM
+        assert_eq!(results[4..6], [0x8C, 0x42]);
+        // OAM scan interrupt: STAT and VRAM while drawing
+        assert_eq!(results[6..8], [0xA2, 0xFF]);
+    }
+
+    #[test]
+    fn background() {
+        let cpu = run_rom(BACKGROUND);
+        let pixel = |x: usize, y: usize| cpu.framebuffer()[y * SCREEN_WIDTH + x];
+
+        // Scrolled background tile
+        assert_eq!(
+            [pixel(3, 4), pixel(4, 4), pixel(11, 11), pixel(12, 11)],
+            [0, 3, 3, 0]
+        );
+        assert_eq!([pixel(4, 3), pixel(4, 12)], [0, 0]);
+        // Window tile
+        assert_eq!(
+            [
+                pixel(79, 100),
+                pixel(80, 100),
+                pixel(87, 107),
+                pixel(88, 107)
+            ],
+            [0, 1, 1, 0]
+        );
+        assert_eq!([pixel(80, 99), pixel(80, 108)], [0, 0]);
+    }
+
+    #[test]
+    fn objects() {
+        let cpu = run_rom(OBJECTS);
+        let pixel = |x: usize, y: usize| cpu.framebuffer()[y * SCREEN_WIDTH + x];
+
+        // OBP0 and OBP1
+        assert_eq!(
+            [pixel(9, 20), pixel(10, 20), pixel(17, 27), pixel(18, 27)],
+            [0, 3, 3, 0]
+        );
+        assert_eq!(pixel(30, 20), 1);
+        // Background over the object with the smaller X
+        assert_eq!(
+            [pixel(40, 40), pixel(47, 40), pixel(48, 40), pixel(51, 47)],
+            [1, 1, 3, 3]
+        );
+        assert_eq!(pixel(52, 40), 0);
+    }
+
+    #[test]
+    fn oam_dma() {
+        let cpu = run_rom(OAM_DMA);
+        let results = wram_results(&cpu, 5);
+
+        // Reads during the transfer: OAM and ROM
+        assert_eq!(results[0..2], [0xFF, 0x09]);
+        // OAM after the transfer
+        assert_eq!(results[2..4], [0x00, 0x9F]);
+        assert_eq!(results[4], 0xC1);
+    }
+
+    #[test]
+    fn joypad() {
+        let buttons = Buttons {
+            a: true,
+            down: true,
+            ..Default::default()
+        };
+        let cpu = run_rom_with(JOYPAD, |cpu| cpu.set_buttons(buttons));
+        let results = wram_results(&cpu, 6);
+
+        // Nothing, directions, actions and both selected
+        assert_eq!(results[0..4], [0xFF, 0xE7, 0xDE, 0xC6]);
+        // Joypad interrupt only when a line goes low
+        assert_eq!(results[4..6], [0x10, 0x00]);
+    }
+
+    #[test]
+    fn apu() {
+        let cpu = run_rom(APU);
+        let results = wram_results(&cpu, 5);
+
+        assert_eq!(results[0], 0x77);
+        // Channel 1 active until its length expired
+        assert_eq!(results[1..3], [0xF1, 0xF0]);
+        // NR50 and NR52 after turning the APU off
+        assert_eq!(results[3..5], [0x00, 0x70]);
+    }
+
+    #[test]
+    fn boot_rom_hands_over() {
+        // LD SP, $FFFE and LD A, $01, then unmapping at the end like the DMG boot ROM
+        let mut bytes = vec![0x00; 0x100];
+        bytes[..5].copy_from_slice(&[0x31, 0xFE, 0xFF, 0x3E, 0x01]);
+        bytes[0xFC..0xFE].copy_from_slice(&[0xE0, 0x50]);
+        let cartridge = Cartridge::new(SIMPLE_ADD.to_vec()).unwrap();
+        let boot = Boot::Rom(BootRom::new(bytes).unwrap());
+        let mut cpu = Cpu::with_options(cartridge, Renderer::default(), boot);
+        assert_eq!(cpu.registers.pc, 0x0000);
+        assert_eq!(cpu.bus.read_byte(0x0000), 0x31);
+        assert_eq!(
+            cpu.bus.read_byte(0x0100),
+            SIMPLE_ADD[0x0100],
+            "Only 256 bytes are mapped"
+        );
+
+        while cpu.registers.pc < CARTRIDGE_ENTRY_POINT {
+            cpu.step().unwrap();
+        }
+        assert_eq!(cpu.registers.pc, CARTRIDGE_ENTRY_POINT);
+        assert_eq!(cpu.registers.sp, INITIAL_STACK_POINTER);
+        assert_eq!(cpu.bus.read_byte(0x0000), SIMPLE_ADD[0x0000]);
+        assert_eq!(cpu.bus.read_byte(0xFF50), 0xFF);
+    }
+
+    #[test]
+    fn post_boot_state() {
+        assert_ne!(SIMPLE_ADD[0x014D], 0x00, "Header checksum sets H and C");
+        let cpu = Cpu::new(Cartridge::new(SIMPLE_ADD.to_vec()).unwrap());
+        let registers = &cpu.registers;
+        assert_eq!([registers.a, u8::from(registers.f)], [0x01, 0xB0]);
+        assert_eq!([registers.b, registers.c], [0x00, 0x13]);
+        assert_eq!([registers.d, registers.e], [0x00, 0xD8]);
+        assert_eq!([registers.h, registers.l], [0x01, 0x4D]);
+        assert_eq!(registers.sp, INITIAL_STACK_POINTER);
+        assert_eq!(registers.pc, CARTRIDGE_ENTRY_POINT);
+
+        for (address, byte) in [
+            (0xFF00, 0xCF),
+            (0xFF02, 0x7E),
+            (0xFF04, 0xAB),
+            (0xFF07, 0xF8),
+            (0xFF0F, 0xE1),
+            (0xFF24, 0x77),
+            (0xFF25, 0xF3),
+            (0xFF26, 0xF1),
+            (0xFF40, 0x91),
+            (0xFF47, 0xFC),
+        ] {
+            assert_eq!(cpu.bus.read_byte(address), byte, "0x{address:04X}");
+        }
+    }
+
+    #[test]
+    fn serial() {
+        let capture = CaptureSink::default();
+        let cpu = run_rom_with(SERIAL, |cpu| cpu.set_serial_sink(Box::new(capture.clone())));
+        let results = wram_results(&cpu, 2);
+
+        assert_eq!(capture.text(), "Hi");
+        // SB after the transfer and the Serial interrupt
+        assert_eq!(results, [0xFF, 0x08]);
+    }
+
+    /// Runs the link ROM on two CPUs connected over TCP, returns the bytes each received and the
+    /// cycles it ran
+    fn run_linked_roms() -> [(Vec<u8>, u64); 2] {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let run = |link: Box<dyn Link>, master: bool| {
+            let cpu = run_rom_with(LINK, |cpu| {
+                cpu.bus.write_byte(0xC0FF, master as u8);
+                cpu.set_link(link);
+            });
+            (wram_results(&cpu, 3), cpu.cycles)
+        };
+        let slave =
+            std::thread::spawn(move || run(crate::serial::connect(&address).unwrap(), false));
+        let (stream, _) = listener.accept().unwrap();
+        let master = run(Box::new(crate::serial::StreamLink::new(stream)), true);
+        [master, slave.join().unwrap()]
+    }
+
+    #[test]
+    fn link_cable() {
+        let [master, slave] = run_linked_roms();
+        assert_eq!(master.0, [0xAA, 0xBB, 0xCC]);
+        assert_eq!(slave.0, [0x11, 0x22, 0x33]);
+        assert_eq!(run_linked_roms(), [master, slave], "Not deterministic");
+    }
+
+    /// Runs the ROMs of Mooneye's test suite in `directory`, which are not part of the repository
+    ///
+    /// The ROMs end with LD B, B and signal success with the Fibonacci numbers in B-L.
+    fn run_mooneye_roms(directory: &str) {
+        const TIMEOUT_T_CYCLES: u64 = 100_000_000;
+        let mut failed = Vec::new();
+        for entry in std::fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            let mut cpu = Cpu::new(Cartridge::load(&path).unwrap());
+            while cpu.cycles < TIMEOUT_T_CYCLES {
+                let executed = cpu.step().unwrap();
+                if executed.instruction == Some(Instruction::Ld(R8::B, R8::B)) {
+                    break;
+                }
+            }
+            let registers = &cpu.registers;
+            let result = [
+                registers.b,
+                registers.c,
+                registers.d,
+                registers.e,
+                registers.h,
+                registers.l,
+            ];
+            if result != [3, 5, 8, 13, 21, 34] {
+                failed.push(path);
+            }
+        }
+        assert!(failed.is_empty(), "Failed: {failed:?}");
+    }
+
+    #[test]
+    #[ignore = "needs Mooneye's acceptance/timer ROMs in test_roms/mooneye/timer"]
+    fn mooneye_timer() {
+        run_mooneye_roms("test_roms/mooneye/timer");
+    }
+
+    /// Runs Blargg's test ROMs in `directory`, which are not part of the repository
+    ///
+    /// The ROMs print their name and then "Passed" or "Failed" over the serial port.
+    fn run_blargg_roms(directory: &str) {
+        const TIMEOUT_T_CYCLES: u64 = 1_000_000_000;
+        let mut failed = Vec::new();
+        for entry in std::fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            let capture = CaptureSink::default();
+            let mut cpu = Cpu::new(Cartridge::load(&path).unwrap());
+            cpu.set_serial_sink(Box::new(capture.clone()));
+            while cpu.cycles < TIMEOUT_T_CYCLES {
+                cpu.run_frame().unwrap();
+                let output = capture.text();
+                if output.contains("Passed") || output.contains("Failed") {
+                    break;
+                }
+            }
+            let output = capture.text();
+            if !output.contains("Passed") {
+                failed.push((path, output));
+            }
+        }
+        assert!(failed.is_empty(), "Failed: {failed:?}");
+    }
+
+    #[test]
+    #[ignore = "needs the ROMs of Blargg's cpu_instrs/individual in test_roms/blargg/cpu_instrs"]
+    fn blargg_cpu_instrs() {
+        run_blargg_roms("test_roms/blargg/cpu_instrs");
+    }
 }
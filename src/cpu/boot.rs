@@ -0,0 +1,176 @@
+use std::fmt::Display;
+use std::path::Path;
+
+use super::{CARTRIDGE_ENTRY_POINT, FlagRegister, INITIAL_STACK_POINTER, Registers};
+
+/// Writing a non-zero value unmaps the boot ROM until the next power cycle
+pub(crate) const BOOT_ROM_DISABLE_ADDRESS: u16 = 0xFF50;
+pub(crate) const BOOT_ROM_END: u16 = 0x00FF;
+const BOOT_ROM_SIZE: usize = BOOT_ROM_END as usize + 1;
+
+#[derive(Debug)]
+pub(crate) enum BootRomError {
+    Io(std::io::Error),
+    /// The DMG boot ROM has exactly 256 bytes
+    Size(usize),
+}
+
+impl Display for BootRomError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BootRomError::Io(error) => write!(f, "Could not read the boot ROM: {error}"),
+            BootRomError::Size(size) => write!(
+                f,
+                "Boot ROM has {size} bytes instead of {BOOT_ROM_SIZE} bytes"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BootRomError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BootRomError::Io(error) => Some(error),
+            BootRomError::Size(_) => None,
+        }
+    }
+}
+
+/// The program mapped over 0x0000-0x00FF at power on, it shows the logo and hands over to the
+/// cartridge at 0x0100
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BootRom(Box<[u8; BOOT_ROM_SIZE]>);
+
+impl BootRom {
+    /// # Errors
+    ///
+    /// Returns an error if the boot ROM doesn't have 256 bytes
+    pub(crate) fn new(bytes: Vec<u8>) -> Result<Self, BootRomError> {
+        let size = bytes.len();
+        bytes
+            .into_boxed_slice()
+            .try_into()
+            .map(Self)
+            .map_err(|_| BootRomError::Size(size))
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or doesn't have 256 bytes
+    pub(crate) fn load(path: impl AsRef<Path>) -> Result<Self, BootRomError> {
+        Self::new(std::fs::read(path).map_err(BootRomError::Io)?)
+    }
+
+    pub(crate) fn read(&self, address: u16) -> u8 {
+        self.0[address as usize]
+    }
+}
+
+/// Game Boy models, which differ in the state their boot ROMs leave behind
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Model {
+    /// Early DMG with a boot ROM that doesn't check the header checksum
+    Dmg0,
+    #[default]
+    Dmg,
+    /// Game Boy Pocket
+    Mgb,
+    /// Super Game Boy
+    Sgb,
+    Sgb2,
+}
+
+impl Model {
+    /// Parses the names used on the command line, e.g. `dmg` or `sgb2`
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dmg0" => Some(Model::Dmg0),
+            "dmg" => Some(Model::Dmg),
+            "mgb" => Some(Model::Mgb),
+            "sgb" => Some(Model::Sgb),
+            "sgb2" => Some(Model::Sgb2),
+            _ => None,
+        }
+    }
+
+    /// CPU registers when the boot ROM jumps to the cartridge. The DMG and MGB boot ROMs leave
+    /// the half carry and carry flags set unless the header checksum is 0
+    pub(super) fn registers(self, header_checksum: u8) -> Registers {
+        let checksum_flags = header_checksum != 0;
+        let (a, f, b, c, d, e, h, l) = match self {
+            Model::Dmg0 => (0x01, 0x00, 0xFF, 0x13, 0x00, 0xC1, 0x84, 0x03),
+            Model::Dmg | Model::Mgb => {
+                let f = FlagRegister {
+                    zero: true,
+                    substraction: false,
+                    half_carry: checksum_flags,
+                    carry: checksum_flags,
+                };
+                let a = if self == Model::Mgb { 0xFF } else { 0x01 };
+                (a, f.into(), 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D)
+            }
+            Model::Sgb => (0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0xC0, 0x60),
+            Model::Sgb2 => (0xFF, 0x00, 0x00, 0x14, 0x00, 0x00, 0xC0, 0x60),
+        };
+        Registers {
+            a,
+            b,
+            c,
+            d,
+            e,
+            f: FlagRegister::from(f),
+            h,
+            l,
+            sp: INITIAL_STACK_POINTER,
+            pc: CARTRIDGE_ENTRY_POINT,
+        }
+    }
+
+    /// Internal counter of the timer, DIV is its upper byte. The SGB boot ROM talks to the SNES
+    /// for a varying time, so it has no fixed value there
+    pub(super) fn divider_counter(self) -> u16 {
+        match self {
+            Model::Dmg0 => 0x1800,
+            Model::Dmg | Model::Mgb => 0xABCC,
+            Model::Sgb | Model::Sgb2 => 0x0000,
+        }
+    }
+}
+
+/// How the CPU gets to the cartridge entry point
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Boot {
+    /// Runs the boot ROM from power on
+    Rom(BootRom),
+    /// Starts at the entry point in the state the boot ROM of the model leaves behind
+    Skip(Model),
+}
+
+impl Default for Boot {
+    fn default() -> Self {
+        Boot::Skip(Model::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boot_rom_size() {
+        assert!(BootRom::new(vec![0; BOOT_ROM_SIZE]).is_ok());
+        assert!(matches!(
+            BootRom::new(vec![0; 0x800]),
+            Err(BootRomError::Size(0x800))
+        ));
+    }
+
+    #[test]
+    fn checksum_flags() {
+        let flags = |model: Model, checksum| u8::from(model.registers(checksum).f);
+        assert_eq!(flags(Model::Dmg, 0x42), 0xB0);
+        assert_eq!(flags(Model::Dmg, 0x00), 0x80);
+        assert_eq!(flags(Model::Mgb, 0x42), 0xB0);
+        assert_eq!(flags(Model::Dmg0, 0x42), 0x00);
+    }
+}
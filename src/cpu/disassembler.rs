@@ -0,0 +1,143 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use super::instruction::Instruction;
+
+/// An instruction decoded from memory together with its address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Disassembly {
+    pub(crate) address: u16,
+    /// `None` for illegal opcodes and instructions cut off by the end of the bytes, which are
+    /// listed as a `db` of the opcode
+    pub(crate) instruction: Option<Instruction>,
+    opcode: u8,
+}
+
+impl Disassembly {
+    /// Length in bytes, including the prefix and the immediate operands
+    pub(crate) fn length(&self) -> u8 {
+        self.instruction
+            .map_or(1, |instruction| instruction.length())
+    }
+}
+
+impl Display for Disassembly {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.instruction {
+            Some(instruction) => instruction.write_with(f, Some(self.address)),
+            None => write!(f, "db ${:02X}", self.opcode),
+        }
+    }
+}
+
+/// Decodes the instruction at `address`, reading the memory through `read`
+pub(crate) fn disassemble(address: u16, read: impl Fn(u16) -> u8) -> Disassembly {
+    let mut next_address = address;
+    let mut next_byte = || {
+        let byte = read(next_address);
+        next_address = next_address.wrapping_add(1);
+        byte
+    };
+    let opcode = next_byte();
+    Disassembly {
+        address,
+        instruction: Instruction::decode(opcode, next_byte),
+        opcode,
+    }
+}
+
+/// Decodes `bytes` loaded at `origin` into consecutive instructions
+#[cfg(test)]
+pub(crate) fn disassemble_bytes(bytes: &[u8], origin: u16) -> Vec<Disassembly> {
+    let read = |address: u16| {
+        bytes
+            .get(address.wrapping_sub(origin) as usize)
+            .copied()
+            .unwrap_or_default()
+    };
+    let mut listing = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let mut disassembly = disassemble(origin.wrapping_add(offset as u16), read);
+        if offset + disassembly.length() as usize > bytes.len() {
+            disassembly.instruction = None;
+        }
+        offset += disassembly.length() as usize;
+        listing.push(disassembly);
+    }
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISASSEMBLY: &[u8] = include_bytes!("../../test_roms/disassembly.gb");
+    const DISASSEMBLY_SOURCE: &str = include_str!("../../test_roms/disassembly.asm");
+    const ENTRY_POINT: u16 = 0x0150;
+
+    fn listing(bytes: &[u8], origin: u16) -> Vec<String> {
+        disassemble_bytes(bytes, origin)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let source: Vec<&str> = DISASSEMBLY_SOURCE
+            .lines()
+            .skip_while(|line| *line != "EntryPoint:")
+            .skip(1)
+            .map(str::trim)
+            .take_while(|line| !line.is_empty())
+            .collect();
+        assert!(source.len() > 500, "Covers all opcodes");
+        let code = &DISASSEMBLY[ENTRY_POINT as usize..];
+        let mut disassembly = listing(code, ENTRY_POINT);
+        // The ROM is padded with zeros, which disassemble to NOPs
+        while disassembly.last().is_some_and(|line| line == "nop") {
+            disassembly.pop();
+        }
+        assert_eq!(disassembly, source);
+    }
+
+    #[test]
+    fn operands() {
+        let bytes = [
+            0x3E, 0x42, // ld a, $42
+            0x01, 0x34, 0x12, // ld bc, $1234
+            0xE0, 0x44, // ldh [$FF44], a
+            0xF8, 0xFE, // ld hl, sp - 2
+            0xE8, 0x05, // add sp, 5
+            0x20, 0xFE, // jr nz, to itself
+            0xCB, 0x7E, // bit 7, [hl]
+            0xD3, // Illegal
+            0xC3, 0x50, // Cut off
+        ];
+        assert_eq!(
+            listing(&bytes, 0x0150),
+            [
+                "ld a, $42",
+                "ld bc, $1234",
+                "ldh [$FF44], a",
+                "ld hl, sp - 2",
+                "add sp, 5",
+                "jr nz, $015B",
+                "bit 7, [hl]",
+                "db $D3",
+                "db $C3",
+                "ld d, b",
+            ]
+        );
+    }
+
+    #[test]
+    fn addresses_and_lengths() {
+        let listing = disassemble_bytes(&[0x00, 0xCD, 0x00, 0x02, 0xCB, 0x37, 0x18, 0x80], 0x0100);
+        let addresses: Vec<u16> = listing.iter().map(|line| line.address).collect();
+        let lengths: Vec<u8> = listing.iter().map(Disassembly::length).collect();
+        assert_eq!(addresses, [0x0100, 0x0101, 0x0104, 0x0106]);
+        assert_eq!(lengths, [1, 3, 2, 2]);
+        assert_eq!(listing[3].to_string(), "jr $0088", "Jumps 128 bytes back");
+    }
+}
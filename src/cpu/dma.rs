@@ -0,0 +1,125 @@
+pub(crate) const DMA_ADDRESS: u16 = 0xFF46;
+
+/// One byte is copied per machine cycle
+pub(crate) const TRANSFER_LENGTH: u8 = 0xA0;
+/// Sources from 0xE000 read the WRAM mirrored there
+const ECHO_RAM_START: u16 = 0xE000;
+const ECHO_RAM_OFFSET: u16 = 0x2000;
+
+/// A running transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Transfer {
+    source: u16,
+    /// Next byte to copy
+    index: u8,
+}
+
+impl Transfer {
+    fn address(&self) -> u16 {
+        self.source + self.index as u16
+    }
+}
+
+/// OAM DMA, which copies 160 bytes from `XX00` to OAM after `XX` is written to 0xFF46
+#[derive(Debug, Default)]
+pub(crate) struct OamDma {
+    /// Last value written to 0xFF46
+    register: u8,
+    /// Transfer requested by a write, it starts after a setup cycle
+    starting: Option<u16>,
+    active: Option<Transfer>,
+}
+
+impl OamDma {
+    /// Whether the transfer occupies the bus, which locks the CPU out of everything but HRAM and
+    /// the I/O registers
+    #[cfg(test)]
+    pub(crate) fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Address the transfer reads next, the CPU sees this byte when it accesses the same bus
+    pub(crate) fn source_address(&self) -> Option<u16> {
+        self.active.as_ref().map(Transfer::address)
+    }
+
+    /// Advances by one machine cycle, returns the source address and OAM offset of the byte to
+    /// copy during it
+    pub(crate) fn tick(&mut self) -> Option<(u16, u8)> {
+        let copy = self.active.as_mut().map(|transfer| {
+            let copy = (transfer.address(), transfer.index);
+            transfer.index += 1;
+            copy
+        });
+        if self
+            .active
+            .is_some_and(|transfer| transfer.index == TRANSFER_LENGTH)
+        {
+            self.active = None;
+        }
+        // A restarted transfer replaces the running one only after its setup cycle
+        if let Some(source) = self.starting.take() {
+            self.active = Some(Transfer { source, index: 0 });
+        }
+        copy
+    }
+
+    pub(crate) fn read(&self) -> u8 {
+        self.register
+    }
+
+    pub(crate) fn write(&mut self, byte: u8) {
+        self.register = byte;
+        let source = u16::from_be_bytes([byte, 0x00]);
+        self.starting = Some(if source >= ECHO_RAM_START {
+            source - ECHO_RAM_OFFSET
+        } else {
+            source
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_timing() {
+        let mut dma = OamDma::default();
+        dma.write(0xC1);
+        assert_eq!(dma.read(), 0xC1);
+        assert!(!dma.is_active());
+
+        assert_eq!(dma.tick(), None, "Setup cycle");
+        assert!(dma.is_active());
+        assert_eq!(dma.source_address(), Some(0xC100));
+        for index in 0..TRANSFER_LENGTH {
+            assert_eq!(dma.tick(), Some((0xC100 + index as u16, index)));
+        }
+        assert!(!dma.is_active());
+        assert_eq!(dma.tick(), None);
+    }
+
+    #[test]
+    fn restart() {
+        let mut dma = OamDma::default();
+        dma.write(0x80);
+        dma.tick();
+        dma.tick();
+        dma.write(0x90);
+        assert_eq!(
+            dma.tick(),
+            Some((0x8001, 1)),
+            "Old transfer continues during the setup"
+        );
+        assert_eq!(dma.tick(), Some((0x9000, 0)));
+    }
+
+    #[test]
+    fn echo_ram_source() {
+        let mut dma = OamDma::default();
+        dma.write(0xFE);
+        dma.tick();
+        assert_eq!(dma.tick(), Some((0xDE00, 0)));
+    }
+}
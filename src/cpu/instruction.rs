@@ -1,10 +1,16 @@
 /**
 Decoding is done by applying https://archive.gbdev.io/salvage/decoding_gbz80_opcodes/Decoding%20Gamboy%20Z80%20Opcodes.html
 */
+use std::fmt::{Display, Formatter, Result as FmtResult};
 
+/// Selects the second opcode table
+const INSTRUCTION_PREFIX: u8 = 0xcb;
+// Operands are decoded from the masked bits only, so converting a `u8` never fails
 const R8_MASK: u8 = 0b111;
 const R16_MASK: u8 = 0b11;
-#[derive(Debug)]
+const JUMP_CONDITION_MASK: u8 = 0b11;
+const RST_VECTOR_MASK: u8 = 0b111;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum Instruction {
     /// No operation
     Nop,
@@ -25,24 +31,25 @@ pub(crate) enum Instruction {
     or
     special case: when r is (HL)
     */
-    LdImm(R8),
-    LdMemImmFromA,
-    LdMemImmToA,
-    LdMemOffsetImmFromA,
-    LdMemOffsetImmToA,
+    LdImm(R8, u8),
+    LdMemImmFromA(u16),
+    LdMemImmToA(u16),
+    /// LDH to 0xFF00 plus the operand
+    LdMemOffsetImmFromA(u8),
+    LdMemOffsetImmToA(u8),
     LdMemOffsetCFromA,
     LdMemOffsetCToA,
     /**
     LD (nn), SP: Load from stack pointer (direct)
     Load to the absolute address specified by the 16-bit operand nn, data from the 16-bit SP register.
     */
-    LdImmFromSp,
+    LdImmFromSp(u16),
     /**
     LD rr, nn: Load 16-bit register / register pair
     Load to the 16-bit register rr, the immediate 16-bit data nn.
     */
-    LdR16Imm(R16),
-    LdHlAdjSpImm,
+    LdR16Imm(R16, u16),
+    LdHlAdjSpImm(i8),
     LdSpHl,
     AddA(R8),
     AdcA(R8),
@@ -52,22 +59,21 @@ pub(crate) enum Instruction {
     register pair.
     */
     AddHl(R16),
-    AddAImm,
-    AdcAImm,
-    /// Signed immediate
-    AddSpImm,
+    AddAImm(u8),
+    AdcAImm(u8),
+    AddSpImm(i8),
     SubA(R8),
-    SubAImm,
+    SubAImm(u8),
     SbcA(R8),
-    SbcAImm,
+    SbcAImm(u8),
     AndA(R8),
-    AndAImm,
+    AndAImm(u8),
     XorA(R8),
-    XorAImm,
+    XorAImm(u8),
     OrA(R8),
-    OrAImm,
+    OrAImm(u8),
     CpA(R8),
-    CpAImm,
+    CpAImm(u8),
     IncR16(R16),
     DecR16(R16),
     Inc(R8),
@@ -76,9 +82,10 @@ pub(crate) enum Instruction {
      JR e: Relative jump
      or
      JR cc, e: Relative jump
+     The offset is relative to the address after the instruction
     */
-    Jr(JumpCondition),
-    Jp(JumpCondition),
+    Jr(JumpCondition, i8),
+    Jp(JumpCondition, u16),
     JpHl,
     /**
     RLCA: Rotate left circular (accumulator)
@@ -208,66 +215,206 @@ pub(crate) enum Instruction {
     Di,
     /// EI: Enable interrupts
     Ei,
-    Call(JumpCondition),
-    Rst(u8),
+    Call(JumpCondition, u16),
+    Rst(RstVector),
 }
 
 impl Instruction {
-    pub(crate) fn from_byte(byte: u8, prefixed: bool) -> Option<Instruction> {
-        if prefixed {
-            Instruction::from_byte_prefixed(byte)
+    /// Decodes the instruction starting with `opcode`. `next_byte` returns the bytes following
+    /// it one after another, the opcode after the prefix and the immediate operands
+    pub(crate) fn decode(opcode: u8, mut next_byte: impl FnMut() -> u8) -> Option<Instruction> {
+        if opcode == INSTRUCTION_PREFIX {
+            Some(Instruction::decode_prefixed(next_byte()))
         } else {
-            Instruction::from_byte_not_prefixed(byte)
+            Instruction::decode_not_prefixed(opcode, next_byte)
         }
     }
 
-    fn from_byte_prefixed(byte: u8) -> Option<Instruction> {
+    /// Length of the instruction in bytes, including the prefix and any immediate operands
+    pub(crate) fn length(&self) -> u8 {
+        match self {
+            Self::LdR16Imm(..)
+            | Self::LdMemImmFromA(_)
+            | Self::LdMemImmToA(_)
+            | Self::LdImmFromSp(_)
+            | Self::Jp(..)
+            | Self::Call(..) => 3,
+            Self::Stop
+            | Self::LdImm(..)
+            | Self::LdMemOffsetImmFromA(_)
+            | Self::LdMemOffsetImmToA(_)
+            | Self::LdHlAdjSpImm(_)
+            | Self::AddAImm(_)
+            | Self::AdcAImm(_)
+            | Self::AddSpImm(_)
+            | Self::SubAImm(_)
+            | Self::SbcAImm(_)
+            | Self::AndAImm(_)
+            | Self::XorAImm(_)
+            | Self::OrAImm(_)
+            | Self::CpAImm(_)
+            | Self::Jr(..) => 2,
+            Self::Rlc(_)
+            | Self::Rrc(_)
+            | Self::Rl(_)
+            | Self::Rr(_)
+            | Self::Sla(_)
+            | Self::Sra(_)
+            | Self::Swap(_)
+            | Self::Srl(_)
+            | Self::Bit(_, _)
+            | Self::Res(_, _)
+            | Self::Set(_, _) => 2,
+            _ => 1,
+        }
+    }
+
+    /// Machine cycles the instruction takes, one machine cycle being 4 T-cycles.
+    /// `branch_taken` selects the timing of conditional jumps, calls and returns
+    pub(crate) fn m_cycles(&self, branch_taken: bool) -> u8 {
+        // Unconditional jumps and calls always take the branch
+        let branch_taken = branch_taken
+            || matches!(
+                self,
+                Self::Jr(JumpCondition::Always, _)
+                    | Self::Jp(JumpCondition::Always, _)
+                    | Self::Call(JumpCondition::Always, _)
+            );
+        match self {
+            Self::Nop | Self::Stop | Self::Halt | Self::Di | Self::Ei => 1,
+            Self::Ld(R8::Hl, _) | Self::Ld(_, R8::Hl) => 2,
+            Self::Ld(_, _) => 1,
+            Self::LdIndirectFromA(_) | Self::LdIndirectToA(_) => 2,
+            Self::LdImm(R8::Hl, _) => 3,
+            Self::LdImm(..) => 2,
+            Self::LdMemImmFromA(_) | Self::LdMemImmToA(_) => 4,
+            Self::LdMemOffsetImmFromA(_) | Self::LdMemOffsetImmToA(_) => 3,
+            Self::LdMemOffsetCFromA | Self::LdMemOffsetCToA => 2,
+            Self::LdImmFromSp(_) => 5,
+            Self::LdR16Imm(..) => 3,
+            Self::LdHlAdjSpImm(_) => 3,
+            Self::LdSpHl => 2,
+            Self::AddA(src)
+            | Self::AdcA(src)
+            | Self::SubA(src)
+            | Self::SbcA(src)
+            | Self::AndA(src)
+            | Self::XorA(src)
+            | Self::OrA(src)
+            | Self::CpA(src) => match src {
+                R8::Hl => 2,
+                _ => 1,
+            },
+            Self::AddAImm(_)
+            | Self::AdcAImm(_)
+            | Self::SubAImm(_)
+            | Self::SbcAImm(_)
+            | Self::AndAImm(_)
+            | Self::XorAImm(_)
+            | Self::OrAImm(_)
+            | Self::CpAImm(_) => 2,
+            Self::AddHl(_) => 2,
+            Self::AddSpImm(_) => 4,
+            Self::IncR16(_) | Self::DecR16(_) => 2,
+            Self::Inc(R8::Hl) | Self::Dec(R8::Hl) => 3,
+            Self::Inc(_) | Self::Dec(_) => 1,
+            Self::Jr(..) => {
+                if branch_taken {
+                    3
+                } else {
+                    2
+                }
+            }
+            Self::Jp(..) => {
+                if branch_taken {
+                    4
+                } else {
+                    3
+                }
+            }
+            Self::JpHl => 1,
+            Self::Rlca | Self::Rrca | Self::Rla | Self::Rra => 1,
+            Self::Rlc(dest)
+            | Self::Rrc(dest)
+            | Self::Rl(dest)
+            | Self::Rr(dest)
+            | Self::Sla(dest)
+            | Self::Sra(dest)
+            | Self::Swap(dest)
+            | Self::Srl(dest)
+            | Self::Res(_, dest)
+            | Self::Set(_, dest) => match dest {
+                R8::Hl => 4,
+                _ => 2,
+            },
+            Self::Bit(_, R8::Hl) => 3,
+            Self::Bit(_, _) => 2,
+            Self::Daa | Self::Cpl | Self::Scf | Self::Ccf => 1,
+            Self::Ret(JumpCondition::Always) | Self::RetI => 4,
+            Self::Ret(_) => {
+                if branch_taken {
+                    5
+                } else {
+                    2
+                }
+            }
+            Self::Pop(_) => 3,
+            Self::Push(_) => 4,
+            Self::Call(..) => {
+                if branch_taken {
+                    6
+                } else {
+                    3
+                }
+            }
+            Self::Rst(_) => 4,
+        }
+    }
+
+    /// Every opcode after the prefix is valid
+    fn decode_prefixed(byte: u8) -> Instruction {
         let x = byte >> 6;
         let y = (byte >> 3) & 0b111;
         let z = byte & 0b111;
-        return match x {
+        match x {
             0 => match y {
-                0 => Some(Self::Rlc(R8::from(z))),
-                1 => Some(Self::Rrc(R8::from(z))),
-                2 => Some(Self::Rl(R8::from(z))),
-                3 => Some(Self::Rr(R8::from(z))),
-                4 => Some(Self::Sla(R8::from(z))),
-                5 => Some(Self::Sra(R8::from(z))),
-                6 => Some(Self::Swap(R8::from(z))),
-                7 => Some(Self::Srl(R8::from(z))),
-                _ => None,
+                0 => Self::Rlc(R8::from(z)),
+                1 => Self::Rrc(R8::from(z)),
+                2 => Self::Rl(R8::from(z)),
+                3 => Self::Rr(R8::from(z)),
+                4 => Self::Sla(R8::from(z)),
+                5 => Self::Sra(R8::from(z)),
+                6 => Self::Swap(R8::from(z)),
+                _ => Self::Srl(R8::from(z)),
             },
-            1 => Some(Self::Bit(y, R8::from(z))),
-            2 => Some(Self::Res(y, R8::from(z))),
-            3 => Some(Self::Set(y, R8::from(z))),
-            _ => None,
-        };
+            1 => Self::Bit(y, R8::from(z)),
+            2 => Self::Res(y, R8::from(z)),
+            _ => Self::Set(y, R8::from(z)),
+        }
     }
 
-    fn from_byte_not_prefixed(byte: u8) -> Option<Instruction> {
+    fn decode_not_prefixed(byte: u8, mut next_byte: impl FnMut() -> u8) -> Option<Instruction> {
         let x = byte >> 6;
         let y = (byte >> 3) & 0b111;
         let z = byte & 0b111;
         let p = y >> 1;
         let q = y % 2;
-        return match x {
+        match x {
             0 => match z {
                 0 => match y {
                     0 => Some(Self::Nop),
-                    1 => Some(Self::LdImmFromSp),
-                    2 => Some(Self::Stop),
-                    3 => Some(Self::Jr(JumpCondition::Always)),
-                    4..=7 => Some(Self::Jr(JumpCondition::from(y - 4))),
+                    1 => Some(Self::LdImmFromSp(next_word(&mut next_byte))),
+                    2 => {
+                        // STOP is followed by a padding byte
+                        next_byte();
+                        Some(Self::Stop)
+                    }
+                    3 => Some(Self::Jr(JumpCondition::Always, next_byte() as i8)),
+                    4..=7 => Some(Self::Jr(JumpCondition::from(y - 4), next_byte() as i8)),
                     _ => None,
                 },
                 1 => match q {
-                    0 => match p {
-                        0 => Some(Self::LdR16Imm(R16::Bc)),
-                        1 => Some(Self::LdR16Imm(R16::De)),
-                        2 => Some(Self::LdR16Imm(R16::Hl)),
-                        3 => Some(Self::LdR16Imm(R16::Sp)),
-                        _ => None,
-                    },
+                    0 => Some(Self::LdR16Imm(R16::from(p), next_word(&mut next_byte))),
                     1 => match p {
                         0..=3 => Some(Self::AddHl(R16::from(p))),
                         _ => None,
@@ -296,18 +443,9 @@ impl Instruction {
                     1 => Some(Self::DecR16(R16::from(p))),
                     _ => None,
                 },
-                4 => match q {
-                    0 => Some(Self::Inc(R8::from(y))),
-                    _ => None,
-                },
-                5 => match q {
-                    1 => Some(Self::Dec(R8::from(y))),
-                    _ => None,
-                },
-                6 => match y {
-                    0..=7 => Some(Self::LdImm(R8::from(y))),
-                    _ => None,
-                },
+                4 => Some(Self::Inc(R8::from(y))),
+                5 => Some(Self::Dec(R8::from(y))),
+                6 => Some(Self::LdImm(R8::from(y), next_byte())),
                 7 => match y {
                     0 => Some(Self::Rlca),
                     1 => Some(Self::Rrca),
@@ -342,10 +480,10 @@ impl Instruction {
             3 => match z {
                 0 => match y {
                     0..=3 => Some(Self::Ret(JumpCondition::from(y))),
-                    4 => Some(Self::LdMemOffsetImmFromA),
-                    5 => Some(Self::AddSpImm),
-                    6 => Some(Self::LdMemOffsetImmToA),
-                    7 => Some(Self::LdHlAdjSpImm),
+                    4 => Some(Self::LdMemOffsetImmFromA(next_byte())),
+                    5 => Some(Self::AddSpImm(next_byte() as i8)),
+                    6 => Some(Self::LdMemOffsetImmToA(next_byte())),
+                    7 => Some(Self::LdHlAdjSpImm(next_byte() as i8)),
                     _ => None,
                 },
                 1 => match q {
@@ -360,51 +498,174 @@ impl Instruction {
                     _ => None,
                 },
                 2 => match y {
-                    0..=3 => Some(Self::Jp(JumpCondition::from(y))),
+                    0..=3 => Some(Self::Jp(JumpCondition::from(y), next_word(&mut next_byte))),
                     4 => Some(Self::LdMemOffsetCFromA),
-                    5 => Some(Self::LdMemImmFromA),
+                    5 => Some(Self::LdMemImmFromA(next_word(&mut next_byte))),
                     6 => Some(Self::LdMemOffsetCToA),
-                    7 => Some(Self::LdMemImmToA),
+                    7 => Some(Self::LdMemImmToA(next_word(&mut next_byte))),
                     _ => None,
                 },
                 3 => match y {
-                    0 => Some(Self::Jp(JumpCondition::Always)),
+                    0 => Some(Self::Jp(JumpCondition::Always, next_word(&mut next_byte))),
                     6 => Some(Self::Di),
                     7 => Some(Self::Ei),
                     _ => None,
                 },
                 4 => match y {
-                    0..=3 => Some(Self::Call(JumpCondition::from(y))),
+                    0..=3 => Some(Self::Call(
+                        JumpCondition::from(y),
+                        next_word(&mut next_byte),
+                    )),
                     _ => None,
                 },
                 5 => match q {
                     0 => Some(Self::Push(R16_2::from(p))),
                     1 => match p {
-                        0 => Some(Self::Call(JumpCondition::Always)),
+                        0 => Some(Self::Call(JumpCondition::Always, next_word(&mut next_byte))),
                         _ => None,
                     },
                     _ => None,
                 },
-                6 => match y {
-                    0 => Some(Self::AddAImm),
-                    1 => Some(Self::AdcAImm),
-                    2 => Some(Self::SubAImm),
-                    3 => Some(Self::SbcAImm),
-                    4 => Some(Self::AndAImm),
-                    5 => Some(Self::XorAImm),
-                    6 => Some(Self::OrAImm),
-                    7 => Some(Self::CpAImm),
-                    _ => None,
-                },
-                7 => Some(Self::Rst(y * 8)),
+                6 => {
+                    let value = next_byte();
+                    match y {
+                        0 => Some(Self::AddAImm(value)),
+                        1 => Some(Self::AdcAImm(value)),
+                        2 => Some(Self::SubAImm(value)),
+                        3 => Some(Self::SbcAImm(value)),
+                        4 => Some(Self::AndAImm(value)),
+                        5 => Some(Self::XorAImm(value)),
+                        6 => Some(Self::OrAImm(value)),
+                        _ => Some(Self::CpAImm(value)),
+                    }
+                }
+                7 => Some(Self::Rst(RstVector::from(y))),
                 _ => None,
             },
             _ => None,
-        };
+        }
+    }
+
+    /// Writes the instruction in RGBDS syntax. Relative jumps are written with their target if
+    /// the `address` of the instruction is known, otherwise relative to it as `@`
+    pub(crate) fn write_with(&self, f: &mut Formatter<'_>, address: Option<u16>) -> FmtResult {
+        match self {
+            Self::Nop => write!(f, "nop"),
+            Self::Stop => write!(f, "stop"),
+            Self::Ld(dest, src) => write!(f, "ld {dest}, {src}"),
+            Self::LdIndirectFromA(dest) => write!(f, "ld {dest}, a"),
+            Self::LdIndirectToA(src) => write!(f, "ld a, {src}"),
+            Self::LdImm(dest, value) => write!(f, "ld {dest}, ${value:02X}"),
+            Self::LdMemImmFromA(address) => write!(f, "ld [${address:04X}], a"),
+            Self::LdMemImmToA(address) => write!(f, "ld a, [${address:04X}]"),
+            // RGBDS takes the full address in the last page
+            Self::LdMemOffsetImmFromA(offset) => write!(f, "ldh [$FF{offset:02X}], a"),
+            Self::LdMemOffsetImmToA(offset) => write!(f, "ldh a, [$FF{offset:02X}]"),
+            Self::LdMemOffsetCFromA => write!(f, "ldh [c], a"),
+            Self::LdMemOffsetCToA => write!(f, "ldh a, [c]"),
+            Self::LdImmFromSp(address) => write!(f, "ld [${address:04X}], sp"),
+            Self::LdR16Imm(dest, value) => write!(f, "ld {dest}, ${value:04X}"),
+            Self::LdHlAdjSpImm(offset) if *offset < 0 => {
+                write!(f, "ld hl, sp - {}", offset.unsigned_abs())
+            }
+            Self::LdHlAdjSpImm(offset) => write!(f, "ld hl, sp + {offset}"),
+            Self::LdSpHl => write!(f, "ld sp, hl"),
+            Self::AddA(src) => write!(f, "add a, {src}"),
+            Self::AdcA(src) => write!(f, "adc a, {src}"),
+            Self::AddHl(src) => write!(f, "add hl, {src}"),
+            Self::AddAImm(value) => write!(f, "add a, ${value:02X}"),
+            Self::AdcAImm(value) => write!(f, "adc a, ${value:02X}"),
+            Self::AddSpImm(offset) => write!(f, "add sp, {offset}"),
+            Self::SubA(src) => write!(f, "sub a, {src}"),
+            Self::SubAImm(value) => write!(f, "sub a, ${value:02X}"),
+            Self::SbcA(src) => write!(f, "sbc a, {src}"),
+            Self::SbcAImm(value) => write!(f, "sbc a, ${value:02X}"),
+            Self::AndA(src) => write!(f, "and a, {src}"),
+            Self::AndAImm(value) => write!(f, "and a, ${value:02X}"),
+            Self::XorA(src) => write!(f, "xor a, {src}"),
+            Self::XorAImm(value) => write!(f, "xor a, ${value:02X}"),
+            Self::OrA(src) => write!(f, "or a, {src}"),
+            Self::OrAImm(value) => write!(f, "or a, ${value:02X}"),
+            Self::CpA(src) => write!(f, "cp a, {src}"),
+            Self::CpAImm(value) => write!(f, "cp a, ${value:02X}"),
+            Self::IncR16(dest) => write!(f, "inc {dest}"),
+            Self::DecR16(dest) => write!(f, "dec {dest}"),
+            Self::Inc(dest) => write!(f, "inc {dest}"),
+            Self::Dec(dest) => write!(f, "dec {dest}"),
+            Self::Jr(condition, offset) => {
+                // The offset is relative to the next instruction
+                let distance = self.length() as i16 + *offset as i16;
+                let target = match address {
+                    Some(address) => format!("${:04X}", address.wrapping_add_signed(distance)),
+                    None if distance < 0 => format!("@ - {}", distance.unsigned_abs()),
+                    None => format!("@ + {distance}"),
+                };
+                write_branch(f, "jr", *condition, &target)
+            }
+            Self::Jp(condition, address) => {
+                write_branch(f, "jp", *condition, &format!("${address:04X}"))
+            }
+            Self::JpHl => write!(f, "jp hl"),
+            Self::Rlca => write!(f, "rlca"),
+            Self::Rrca => write!(f, "rrca"),
+            Self::Rla => write!(f, "rla"),
+            Self::Rra => write!(f, "rra"),
+            Self::Rlc(dest) => write!(f, "rlc {dest}"),
+            Self::Rrc(dest) => write!(f, "rrc {dest}"),
+            Self::Rl(dest) => write!(f, "rl {dest}"),
+            Self::Rr(dest) => write!(f, "rr {dest}"),
+            Self::Sla(dest) => write!(f, "sla {dest}"),
+            Self::Sra(dest) => write!(f, "sra {dest}"),
+            Self::Swap(dest) => write!(f, "swap {dest}"),
+            Self::Srl(dest) => write!(f, "srl {dest}"),
+            Self::Bit(bit, src) => write!(f, "bit {bit}, {src}"),
+            Self::Res(bit, dest) => write!(f, "res {bit}, {dest}"),
+            Self::Set(bit, dest) => write!(f, "set {bit}, {dest}"),
+            Self::Daa => write!(f, "daa"),
+            Self::Cpl => write!(f, "cpl"),
+            Self::Scf => write!(f, "scf"),
+            Self::Ccf => write!(f, "ccf"),
+            Self::Halt => write!(f, "halt"),
+            Self::Ret(JumpCondition::Always) => write!(f, "ret"),
+            Self::Ret(condition) => write!(f, "ret {condition}"),
+            Self::RetI => write!(f, "reti"),
+            Self::Pop(dest) => write!(f, "pop {dest}"),
+            Self::Push(src) => write!(f, "push {src}"),
+            Self::Di => write!(f, "di"),
+            Self::Ei => write!(f, "ei"),
+            Self::Call(condition, address) => {
+                write_branch(f, "call", *condition, &format!("${address:04X}"))
+            }
+            Self::Rst(vector) => write!(f, "rst ${:02X}", vector.address()),
+        }
+    }
+}
+
+/// Reads an immediate 16-bit operand, which is little endian
+fn next_word(next_byte: &mut impl FnMut() -> u8) -> u16 {
+    u16::from_le_bytes([next_byte(), next_byte()])
+}
+
+/// Writes e.g. `jp nz, $1234` or `jp $1234`
+fn write_branch(
+    f: &mut Formatter<'_>,
+    mnemonic: &str,
+    condition: JumpCondition,
+    target: &str,
+) -> FmtResult {
+    match condition {
+        JumpCondition::Always => write!(f, "{mnemonic} {target}"),
+        condition => write!(f, "{mnemonic} {condition}, {target}"),
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.write_with(f, None)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum R8 {
     B,
     C,
@@ -417,7 +678,7 @@ pub(crate) enum R8 {
 }
 impl From<u8> for R8 {
     fn from(value: u8) -> Self {
-        match value {
+        match value & R8_MASK {
             0 => Self::B,
             1 => Self::C,
             2 => Self::D,
@@ -425,13 +686,28 @@ impl From<u8> for R8 {
             4 => Self::H,
             5 => Self::L,
             6 => Self::Hl,
-            7 => Self::A,
-            _ => panic!("invalid value: {value}"),
+            _ => Self::A,
         }
     }
 }
 
-#[derive(Debug)]
+impl Display for R8 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = match self {
+            Self::B => "b",
+            Self::C => "c",
+            Self::D => "d",
+            Self::E => "e",
+            Self::H => "h",
+            Self::L => "l",
+            Self::Hl => "[hl]",
+            Self::A => "a",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum R16 {
     Bc,
     De,
@@ -441,17 +717,28 @@ pub(crate) enum R16 {
 
 impl From<u8> for R16 {
     fn from(value: u8) -> Self {
-        match value {
+        match value & R16_MASK {
             0 => Self::Bc,
             1 => Self::De,
             2 => Self::Hl,
-            3 => Self::Sp,
-            _ => panic!("invalid value: {value}"),
+            _ => Self::Sp,
         }
     }
 }
 
-#[derive(Debug)]
+impl Display for R16 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = match self {
+            Self::Bc => "bc",
+            Self::De => "de",
+            Self::Hl => "hl",
+            Self::Sp => "sp",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum R16_2 {
     Bc,
     De,
@@ -461,17 +748,28 @@ pub(crate) enum R16_2 {
 
 impl From<u8> for R16_2 {
     fn from(value: u8) -> Self {
-        match value {
+        match value & R16_MASK {
             0 => Self::Bc,
             1 => Self::De,
             2 => Self::Hl,
-            3 => Self::Af,
-            _ => panic!("invalid value: {value}"),
+            _ => Self::Af,
         }
     }
 }
 
-#[derive(Debug)]
+impl Display for R16_2 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = match self {
+            Self::Bc => "bc",
+            Self::De => "de",
+            Self::Hl => "hl",
+            Self::Af => "af",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum IndirectR16 {
     Bc,
     De,
@@ -479,7 +777,19 @@ pub(crate) enum IndirectR16 {
     Hld,
 }
 
-#[derive(Debug)]
+impl Display for IndirectR16 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = match self {
+            Self::Bc => "[bc]",
+            Self::De => "[de]",
+            Self::Hli => "[hl+]",
+            Self::Hld => "[hl-]",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum JumpCondition {
     NotZero,
     Zero,
@@ -489,12 +799,251 @@ pub(crate) enum JumpCondition {
 }
 impl From<u8> for JumpCondition {
     fn from(value: u8) -> Self {
-        match value {
+        match value & JUMP_CONDITION_MASK {
             0 => Self::NotZero,
             1 => Self::Zero,
             2 => Self::NotCarry,
-            3 => Self::Carry,
-            _ => panic!("invalid value: {value}"),
+            _ => Self::Carry,
         }
     }
 }
+
+impl Display for JumpCondition {
+    /// `Always` has no name, branches leave it out
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = match self {
+            Self::NotZero => "nz",
+            Self::Zero => "z",
+            Self::NotCarry => "nc",
+            Self::Carry => "c",
+            Self::Always => "",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Targets of RST, the calls to the first 64 bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RstVector {
+    V00,
+    V08,
+    V10,
+    V18,
+    V20,
+    V28,
+    V30,
+    V38,
+}
+
+impl RstVector {
+    pub(crate) fn address(self) -> u16 {
+        self as u16 * 8
+    }
+}
+
+impl From<u8> for RstVector {
+    fn from(value: u8) -> Self {
+        match value & RST_VECTOR_MASK {
+            0 => Self::V00,
+            1 => Self::V08,
+            2 => Self::V10,
+            3 => Self::V18,
+            4 => Self::V20,
+            5 => Self::V28,
+            6 => Self::V30,
+            _ => Self::V38,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes the opcode with zeroed operands, the prefix is `None` as well
+    fn decode_not_prefixed(opcode: u8) -> Option<Instruction> {
+        Instruction::decode_not_prefixed(opcode, || 0)
+    }
+
+    /// Machine cycles of every unprefixed opcode with conditional branches not taken.
+    /// The prefix and illegal opcodes are 0
+    #[rustfmt::skip]
+    const M_CYCLES: [u8; 256] = [
+        1, 3, 2, 2, 1, 1, 2, 1, 5, 2, 2, 2, 1, 1, 2, 1,
+        1, 3, 2, 2, 1, 1, 2, 1, 3, 2, 2, 2, 1, 1, 2, 1,
+        2, 3, 2, 2, 1, 1, 2, 1, 2, 2, 2, 2, 1, 1, 2, 1,
+        2, 3, 2, 2, 3, 3, 3, 1, 2, 2, 2, 2, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        2, 2, 2, 2, 2, 2, 1, 2, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        2, 3, 3, 4, 3, 4, 2, 4, 2, 4, 3, 0, 3, 6, 2, 4,
+        2, 3, 3, 0, 3, 4, 2, 4, 2, 4, 3, 0, 3, 0, 2, 4,
+        3, 3, 2, 0, 0, 4, 2, 4, 4, 1, 4, 0, 0, 0, 2, 4,
+        3, 3, 2, 1, 0, 4, 2, 4, 3, 2, 4, 1, 0, 0, 2, 4,
+    ];
+
+    /// Length in bytes of every unprefixed opcode. The prefix and illegal opcodes are 0
+    #[rustfmt::skip]
+    const LENGTHS: [u8; 256] = [
+        1, 3, 1, 1, 1, 1, 2, 1, 3, 1, 1, 1, 1, 1, 2, 1,
+        2, 3, 1, 1, 1, 1, 2, 1, 2, 1, 1, 1, 1, 1, 2, 1,
+        2, 3, 1, 1, 1, 1, 2, 1, 2, 1, 1, 1, 1, 1, 2, 1,
+        2, 3, 1, 1, 1, 1, 2, 1, 2, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 3, 3, 3, 1, 2, 1, 1, 1, 3, 0, 3, 3, 2, 1,
+        1, 1, 3, 0, 3, 1, 2, 1, 1, 1, 3, 0, 3, 0, 2, 1,
+        2, 1, 1, 0, 0, 1, 2, 1, 2, 1, 3, 0, 0, 0, 2, 1,
+        2, 1, 1, 1, 0, 1, 2, 1, 2, 1, 3, 1, 0, 0, 2, 1,
+    ];
+
+    /// Machine cycles of the conditional opcodes when the branch is taken
+    const M_CYCLES_BRANCH_TAKEN: [(u8, u8); 16] = [
+        (0x20, 3),
+        (0x28, 3),
+        (0x30, 3),
+        (0x38, 3),
+        (0xC0, 5),
+        (0xC8, 5),
+        (0xD0, 5),
+        (0xD8, 5),
+        (0xC2, 4),
+        (0xCA, 4),
+        (0xD2, 4),
+        (0xDA, 4),
+        (0xC4, 6),
+        (0xCC, 6),
+        (0xD4, 6),
+        (0xDC, 6),
+    ];
+
+    #[test]
+    fn m_cycles_not_prefixed() {
+        for opcode in 0..=0xFF {
+            let expected = M_CYCLES[opcode as usize];
+            let Some(instruction) = decode_not_prefixed(opcode) else {
+                assert_eq!(expected, 0, "Opcode 0x{opcode:02X} failed to decode");
+                continue;
+            };
+            assert_eq!(
+                instruction.m_cycles(false),
+                expected,
+                "Wrong cycles for 0x{opcode:02X} {instruction:?}"
+            );
+            let expected_taken = M_CYCLES_BRANCH_TAKEN
+                .iter()
+                .find(|(conditional, _)| *conditional == opcode)
+                .map_or(expected, |(_, cycles)| *cycles);
+            assert_eq!(
+                instruction.m_cycles(true),
+                expected_taken,
+                "Wrong cycles for taken 0x{opcode:02X} {instruction:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn m_cycles_prefixed() {
+        for opcode in 0..=0xFF {
+            let instruction = Instruction::decode_prefixed(opcode);
+            let expected = match (opcode >> 6, opcode & R8_MASK) {
+                (1, 6) => 3,
+                (_, 6) => 4,
+                _ => 2,
+            };
+            assert_eq!(
+                instruction.m_cycles(false),
+                expected,
+                "Wrong cycles for 0xCB 0x{opcode:02X} {instruction:?}"
+            );
+            assert_eq!(instruction.m_cycles(true), expected);
+        }
+    }
+
+    #[test]
+    fn length() {
+        for opcode in 0..=0xFF {
+            let expected = LENGTHS[opcode as usize];
+            match decode_not_prefixed(opcode) {
+                Some(instruction) => assert_eq!(
+                    instruction.length(),
+                    expected,
+                    "Wrong length for 0x{opcode:02X} {instruction:?}"
+                ),
+                None => assert_eq!(expected, 0, "Opcode 0x{opcode:02X} failed to decode"),
+            }
+            assert_eq!(Instruction::decode_prefixed(opcode).length(), 2);
+        }
+    }
+
+    #[test]
+    fn decode_operands() {
+        let decode = |bytes: &[u8]| {
+            let mut bytes = bytes.iter().copied();
+            let opcode = bytes.next().unwrap();
+            let instruction = Instruction::decode(opcode, || bytes.next().unwrap());
+            assert_eq!(bytes.next(), None, "Reads all bytes of {instruction:?}");
+            instruction
+        };
+        assert_eq!(decode(&[0x3E, 0x42]), Some(Instruction::LdImm(R8::A, 0x42)));
+        assert_eq!(
+            decode(&[0x21, 0x34, 0x12]),
+            Some(Instruction::LdR16Imm(R16::Hl, 0x1234))
+        );
+        assert_eq!(
+            decode(&[0xC2, 0x50, 0x01]),
+            Some(Instruction::Jp(JumpCondition::NotZero, 0x0150))
+        );
+        assert_eq!(
+            decode(&[0x18, 0xFE]),
+            Some(Instruction::Jr(JumpCondition::Always, -2))
+        );
+        assert_eq!(decode(&[0xE8, 0x80]), Some(Instruction::AddSpImm(-128)));
+        assert_eq!(decode(&[0xEF]), Some(Instruction::Rst(RstVector::V28)));
+        assert_eq!(decode(&[0x10, 0x00]), Some(Instruction::Stop));
+        assert_eq!(decode(&[0xCB, 0x7E]), Some(Instruction::Bit(7, R8::Hl)));
+        assert_eq!(decode(&[0xD3]), None);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Instruction::AddA(R8::B).to_string(), "add a, b");
+        assert_eq!(Instruction::LdImm(R8::A, 0x42).to_string(), "ld a, $42");
+        assert_eq!(
+            Instruction::LdMemImmFromA(0xC000).to_string(),
+            "ld [$C000], a"
+        );
+        assert_eq!(
+            Instruction::LdMemOffsetImmToA(0x44).to_string(),
+            "ldh a, [$FF44]"
+        );
+        assert_eq!(Instruction::LdHlAdjSpImm(-3).to_string(), "ld hl, sp - 3");
+        assert_eq!(
+            Instruction::Call(JumpCondition::Always, 0x0200).to_string(),
+            "call $0200"
+        );
+        assert_eq!(Instruction::Ret(JumpCondition::Zero).to_string(), "ret z");
+        assert_eq!(Instruction::Rst(RstVector::V38).to_string(), "rst $38");
+        assert_eq!(Instruction::Bit(7, R8::Hl).to_string(), "bit 7, [hl]");
+        // Without their address relative jumps are written relative to it
+        assert_eq!(
+            Instruction::Jr(JumpCondition::NotZero, 5).to_string(),
+            "jr nz, @ + 7"
+        );
+        assert_eq!(
+            Instruction::Jr(JumpCondition::Always, -4).to_string(),
+            "jr @ - 2"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+pub(crate) const INTERRUPT_FLAG_ADDRESS: u16 = 0xFF0F;
+pub(crate) const INTERRUPT_ENABLE_ADDRESS: u16 = 0xFFFF;
+
+/// Only the lower 5 bits of IF are backed by hardware, the others always read as 1
+const INTERRUPT_FLAG_UNUSED_BITS: u8 = 0b1110_0000;
+const FIRST_INTERRUPT_VECTOR: u16 = 0x40;
+
+/// Interrupt sources, ordered from highest to lowest priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Interrupt {
+    VBlank,
+    LcdStat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    const BY_PRIORITY: [Interrupt; 5] = [
+        Self::VBlank,
+        Self::LcdStat,
+        Self::Timer,
+        Self::Serial,
+        Self::Joypad,
+    ];
+
+    /// Bit of the interrupt in the IE and IF registers
+    pub(crate) fn bit(self) -> u8 {
+        self as u8
+    }
+
+    /// Address the CPU jumps to when servicing the interrupt
+    pub(crate) fn vector(self) -> u16 {
+        FIRST_INTERRUPT_VECTOR + self as u16 * 8
+    }
+}
+
+/// Holds the IE (0xFFFF) and IF (0xFF0F) registers
+#[derive(Default)]
+pub(crate) struct InterruptController {
+    enable: u8,
+    flag: u8,
+}
+
+impl InterruptController {
+    pub(crate) fn read_enable(&self) -> u8 {
+        self.enable
+    }
+
+    pub(crate) fn write_enable(&mut self, byte: u8) {
+        self.enable = byte;
+    }
+
+    pub(crate) fn read_flag(&self) -> u8 {
+        self.flag | INTERRUPT_FLAG_UNUSED_BITS
+    }
+
+    pub(crate) fn write_flag(&mut self, byte: u8) {
+        self.flag = byte & !INTERRUPT_FLAG_UNUSED_BITS;
+    }
+
+    /// Sets the IF bit of the interrupt, used by the components raising it
+    pub(crate) fn request(&mut self, interrupt: Interrupt) {
+        self.flag |= 1 << interrupt.bit();
+    }
+
+    /// Clears the IF bit of the interrupt once the CPU services it
+    pub(crate) fn acknowledge(&mut self, interrupt: Interrupt) {
+        self.flag &= !(1 << interrupt.bit());
+    }
+
+    #[cfg(test)]
+    pub(crate) fn requested(&self, interrupt: Interrupt) -> bool {
+        self.flag >> interrupt.bit() & 1 != 0
+    }
+
+    /// Highest priority interrupt that is both requested and enabled
+    pub(crate) fn pending(&self) -> Option<Interrupt> {
+        let pending = self.enable & self.flag;
+        Interrupt::BY_PRIORITY
+            .into_iter()
+            .find(|interrupt| pending >> interrupt.bit() & 1 != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vectors() {
+        assert_eq!(Interrupt::VBlank.vector(), 0x40);
+        assert_eq!(Interrupt::LcdStat.vector(), 0x48);
+        assert_eq!(Interrupt::Timer.vector(), 0x50);
+        assert_eq!(Interrupt::Serial.vector(), 0x58);
+        assert_eq!(Interrupt::Joypad.vector(), 0x60);
+    }
+
+    #[test]
+    fn pending_respects_enable_and_priority() {
+        let mut interrupts = InterruptController::default();
+        interrupts.request(Interrupt::Joypad);
+        interrupts.request(Interrupt::Timer);
+        assert_eq!(interrupts.pending(), None, "Nothing is enabled");
+
+        interrupts.write_enable(0x1F);
+        assert_eq!(interrupts.pending(), Some(Interrupt::Timer));
+        interrupts.acknowledge(Interrupt::Timer);
+        assert_eq!(interrupts.pending(), Some(Interrupt::Joypad));
+        assert_eq!(interrupts.read_flag(), 0xF0);
+    }
+}
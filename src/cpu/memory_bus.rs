@@ -0,0 +1,408 @@
+use std::cell::Cell;
+
+use crate::apu::{Apu, SOUND_CONTROL_ADDRESS, SOUND_END, SOUND_START};
+use crate::cartridge::Cartridge;
+use crate::joypad::{Buttons, JOYPAD_ADDRESS, Joypad};
+use crate::ppu::{
+    BG_PALETTE_ADDRESS, LCD_CONTROL_ADDRESS, LY_COMPARE_ADDRESS, OAM_END, OAM_START, Ppu, VRAM_END,
+    VRAM_START, WINDOW_X_ADDRESS,
+};
+use crate::serial::{SERIAL_CONTROL_ADDRESS, SERIAL_DATA_ADDRESS, Serial};
+use crate::timer::{DIVIDER_ADDRESS, TIMER_CONTROL_ADDRESS, Timer};
+
+use super::boot::{BOOT_ROM_DISABLE_ADDRESS, BOOT_ROM_END, BootRom, Model};
+use super::dma::{DMA_ADDRESS, OamDma};
+use super::interrupt::{
+    INTERRUPT_ENABLE_ADDRESS, INTERRUPT_FLAG_ADDRESS, Interrupt, InterruptController,
+};
+
+const ROM_END: u16 = 0x7FFF;
+const EXTERNAL_RAM_START: u16 = 0xA000;
+const EXTERNAL_RAM_END: u16 = 0xBFFF;
+const WRAM_START: u16 = 0xC000;
+const WRAM_END: u16 = 0xDFFF;
+/// Mirrors 0xC000-0xDDFF
+const ECHO_RAM_START: u16 = 0xE000;
+const ECHO_RAM_END: u16 = 0xFDFF;
+const UNUSABLE_START: u16 = 0xFEA0;
+const UNUSABLE_END: u16 = 0xFEFF;
+const IO_START: u16 = 0xFF00;
+const IO_END: u16 = 0xFF7F;
+const HRAM_START: u16 = 0xFF80;
+const HRAM_END: u16 = 0xFFFE;
+
+const WRAM_SIZE: usize = (WRAM_END - WRAM_START + 1) as usize;
+const IO_SIZE: usize = (IO_END - IO_START + 1) as usize;
+const HRAM_SIZE: usize = (HRAM_END - HRAM_START + 1) as usize;
+
+/// Value read from addresses nothing drives, e.g. the ROM area without a cartridge
+const OPEN_BUS: u8 = 0xFF;
+
+/// NR52 at the end of the boot ROM, which powers the APU on before setting up the other sound
+/// registers
+const POST_BOOT_SOUND_CONTROL: u8 = 0xF1;
+/// NR10-NR51 at the end of the boot ROM, NR14 triggers channel 1 for the startup chime
+const POST_BOOT_SOUND: [u8; (SOUND_CONTROL_ADDRESS - SOUND_START) as usize] = [
+    0x80, 0xBF, 0xF3, 0xFF, 0xBF, 0xFF, 0x3F, 0x00, 0xFF, 0xBF, 0x7F, 0xFF, 0x9F, 0xFF, 0xBF, 0xFF,
+    0xFF, 0x00, 0x00, 0xBF, 0x77, 0xF3,
+];
+/// The remaining I/O registers the boot ROM leaves different from power on. It leaves both joypad
+/// lines selected and the VBlank interrupt of its last frame pending
+const POST_BOOT_IO: [(u16, u8); 4] = [
+    (JOYPAD_ADDRESS, 0x00),
+    (INTERRUPT_FLAG_ADDRESS, 0xE1),
+    (LCD_CONTROL_ADDRESS, 0x91),
+    (BG_PALETTE_ADDRESS, 0xFC),
+];
+
+/// The DMG has separate buses for the cartridge and WRAM, and for VRAM. OAM DMA only blocks the
+/// one it reads from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bus {
+    External,
+    Video,
+}
+
+impl Bus {
+    fn of(address: u16) -> Option<Self> {
+        match address {
+            0x0000..=ROM_END | EXTERNAL_RAM_START..=ECHO_RAM_END => Some(Bus::External),
+            VRAM_START..=VRAM_END => Some(Bus::Video),
+            _ => None,
+        }
+    }
+}
+
+/// Routes CPU reads and writes to the component owning the address
+pub(crate) struct MemoryBus {
+    cartridge: Option<Cartridge>,
+    /// Mapped over the start of the cartridge ROM until the boot ROM unmaps itself
+    boot_rom: Option<BootRom>,
+    wram: [u8; WRAM_SIZE],
+    /// I/O registers without an emulated component yet
+    io: [u8; IO_SIZE],
+    hram: [u8; HRAM_SIZE],
+    pub(crate) interrupts: InterruptController,
+    joypad: Joypad,
+    timer: Timer,
+    pub(crate) serial: Serial,
+    pub(crate) apu: Apu,
+    pub(crate) ppu: Ppu,
+    dma: OamDma,
+    /// Set when a joypad line goes low, the next one ends STOP
+    joypad_pressed: bool,
+    /// Record accesses to the prohibited area instead of only ignoring them
+    strict_access: bool,
+    /// First access to the prohibited area since the last check, reads record it through `&self`
+    invalid_access: Cell<Option<u16>>,
+}
+
+impl Default for MemoryBus {
+    fn default() -> Self {
+        Self {
+            cartridge: None,
+            boot_rom: None,
+            wram: [0; WRAM_SIZE],
+            io: [0; IO_SIZE],
+            hram: [0; HRAM_SIZE],
+            interrupts: InterruptController::default(),
+            joypad: Joypad::default(),
+            timer: Timer::default(),
+            serial: Serial::default(),
+            apu: Apu::default(),
+            ppu: Ppu::default(),
+            dma: OamDma::default(),
+            joypad_pressed: false,
+            strict_access: false,
+            invalid_access: Cell::new(None),
+        }
+    }
+}
+
+impl MemoryBus {
+    pub(crate) fn insert_cartridge(&mut self, cartridge: Cartridge) {
+        self.cartridge = Some(cartridge);
+    }
+
+    pub(crate) fn map_boot_rom(&mut self, boot_rom: BootRom) {
+        self.boot_rom = Some(boot_rom);
+    }
+
+    /// Sets up the I/O registers like the boot ROM of `model` leaves them
+    pub(crate) fn skip_boot_rom(&mut self, model: Model) {
+        self.timer.set_counter(model.divider_counter());
+        self.write_byte(SOUND_CONTROL_ADDRESS, POST_BOOT_SOUND_CONTROL);
+        self.copy_bytes(SOUND_START, &POST_BOOT_SOUND);
+        for (address, byte) in POST_BOOT_IO {
+            self.write_byte(address, byte);
+        }
+    }
+
+    pub(crate) fn cartridge(&self) -> Option<&Cartridge> {
+        self.cartridge.as_ref()
+    }
+
+    pub(crate) fn cartridge_mut(&mut self) -> Option<&mut Cartridge> {
+        self.cartridge.as_mut()
+    }
+
+    /// Updates the pressed buttons, pressing one the game is polling requests the Joypad interrupt
+    pub(crate) fn set_buttons(&mut self, buttons: Buttons) {
+        if self.joypad.set_buttons(buttons) {
+            self.press_joypad();
+        }
+    }
+
+    fn press_joypad(&mut self) {
+        self.joypad_pressed = true;
+        self.interrupts.request(Interrupt::Joypad);
+    }
+
+    /// Whether a button in the selected groups is held down
+    pub(crate) fn joypad_line_low(&self) -> bool {
+        self.joypad.line_low()
+    }
+
+    pub(crate) fn set_strict_access(&mut self, strict: bool) {
+        self.strict_access = strict;
+    }
+
+    /// Access to the prohibited area since the last call, only recorded with strict access
+    pub(crate) fn take_invalid_access(&mut self) -> Option<u16> {
+        self.invalid_access.take()
+    }
+
+    fn record_invalid_access(&self, address: u16) {
+        if self.strict_access && self.invalid_access.get().is_none() {
+            self.invalid_access.set(Some(address));
+        }
+    }
+
+    /// Whether a joypad line went low since the last call
+    pub(crate) fn take_joypad_press(&mut self) -> bool {
+        std::mem::take(&mut self.joypad_pressed)
+    }
+
+    /// Advances the components clocked alongside the CPU
+    pub(crate) fn tick(&mut self, m_cycles: u8) {
+        for _ in 0..m_cycles {
+            if self.timer.tick() {
+                self.interrupts.request(Interrupt::Timer);
+            }
+            let div = self.timer.read(DIVIDER_ADDRESS);
+            if self.serial.tick(div) {
+                self.interrupts.request(Interrupt::Serial);
+            }
+            self.apu.tick(div);
+            if let Some((source, offset)) = self.dma.tick() {
+                let byte = self.read_mapped(source);
+                self.ppu.write_oam_dma(offset, byte);
+            }
+            let ppu_interrupts = self.ppu.tick();
+            if ppu_interrupts.vblank {
+                self.interrupts.request(Interrupt::VBlank);
+            }
+            if ppu_interrupts.stat {
+                self.interrupts.request(Interrupt::LcdStat);
+            }
+        }
+    }
+
+    /// What the CPU sees instead of the address while OAM DMA keeps it from accessing it: open
+    /// bus in OAM and the byte being transferred on the bus the transfer reads from
+    fn dma_conflict(&self, address: u16) -> Option<u8> {
+        let source = self.dma.source_address()?;
+        match address {
+            OAM_START..=OAM_END => Some(OPEN_BUS),
+            _ if Bus::of(address).is_some() && Bus::of(address) == Bus::of(source) => {
+                Some(self.read_mapped(source))
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn read_byte(&self, address: u16) -> u8 {
+        self.dma_conflict(address)
+            .unwrap_or_else(|| self.read_mapped(address))
+    }
+
+    pub(crate) fn write_byte(&mut self, address: u16, byte: u8) {
+        if self.dma_conflict(address).is_none() {
+            self.write_mapped(address, byte);
+        }
+    }
+
+    /// Reads through the memory map without the restrictions of OAM DMA
+    // The fallback to `io` starts at the joypad register, which has its own arm
+    #[allow(clippy::match_overlapping_arm)]
+    fn read_mapped(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=ROM_END => match &self.boot_rom {
+                Some(boot_rom) if address <= BOOT_ROM_END => boot_rom.read(address),
+                _ => self
+                    .cartridge
+                    .as_ref()
+                    .map_or(OPEN_BUS, |cartridge| cartridge.read_rom(address)),
+            },
+            VRAM_START..=VRAM_END => self.ppu.read_vram(address),
+            EXTERNAL_RAM_START..=EXTERNAL_RAM_END => {
+                self.cartridge.as_ref().map_or(OPEN_BUS, |cartridge| {
+                    cartridge.read_ram(address - EXTERNAL_RAM_START)
+                })
+            }
+            WRAM_START..=WRAM_END => self.wram[(address - WRAM_START) as usize],
+            ECHO_RAM_START..=ECHO_RAM_END => self.wram[(address - ECHO_RAM_START) as usize],
+            OAM_START..=OAM_END => self.ppu.read_oam(address),
+            UNUSABLE_START..=UNUSABLE_END => {
+                self.record_invalid_access(address);
+                0x00
+            }
+            JOYPAD_ADDRESS => self.joypad.read(),
+            SERIAL_DATA_ADDRESS..=SERIAL_CONTROL_ADDRESS => self.serial.read(address),
+            DIVIDER_ADDRESS..=TIMER_CONTROL_ADDRESS => self.timer.read(address),
+            SOUND_START..=SOUND_END => self.apu.read(address),
+            DMA_ADDRESS => self.dma.read(),
+            LCD_CONTROL_ADDRESS..=LY_COMPARE_ADDRESS | BG_PALETTE_ADDRESS..=WINDOW_X_ADDRESS => {
+                self.ppu.read_register(address)
+            }
+            INTERRUPT_FLAG_ADDRESS => self.interrupts.read_flag(),
+            BOOT_ROM_DISABLE_ADDRESS => OPEN_BUS,
+            IO_START..=IO_END => self.io[(address - IO_START) as usize],
+            HRAM_START..=HRAM_END => self.hram[(address - HRAM_START) as usize],
+            INTERRUPT_ENABLE_ADDRESS => self.interrupts.read_enable(),
+        }
+    }
+
+    // The fallback to `io` starts at the joypad register, which has its own arm
+    #[allow(clippy::match_overlapping_arm)]
+    fn write_mapped(&mut self, address: u16, byte: u8) {
+        match address {
+            0x0000..=ROM_END => {
+                if let Some(cartridge) = self.cartridge.as_mut() {
+                    cartridge.write_rom(address, byte);
+                }
+            }
+            VRAM_START..=VRAM_END => self.ppu.write_vram(address, byte),
+            EXTERNAL_RAM_START..=EXTERNAL_RAM_END => {
+                if let Some(cartridge) = self.cartridge.as_mut() {
+                    cartridge.write_ram(address - EXTERNAL_RAM_START, byte);
+                }
+            }
+            WRAM_START..=WRAM_END => self.wram[(address - WRAM_START) as usize] = byte,
+            ECHO_RAM_START..=ECHO_RAM_END => self.wram[(address - ECHO_RAM_START) as usize] = byte,
+            OAM_START..=OAM_END => self.ppu.write_oam(address, byte),
+            UNUSABLE_START..=UNUSABLE_END => self.record_invalid_access(address),
+            JOYPAD_ADDRESS => {
+                if self.joypad.write(byte) {
+                    self.press_joypad();
+                }
+            }
+            SERIAL_DATA_ADDRESS..=SERIAL_CONTROL_ADDRESS => self.serial.write(address, byte),
+            DIVIDER_ADDRESS..=TIMER_CONTROL_ADDRESS => self.timer.write(address, byte),
+            SOUND_START..=SOUND_END => self.apu.write(address, byte),
+            DMA_ADDRESS => self.dma.write(byte),
+            LCD_CONTROL_ADDRESS..=LY_COMPARE_ADDRESS | BG_PALETTE_ADDRESS..=WINDOW_X_ADDRESS => {
+                self.ppu.write_register(address, byte)
+            }
+            INTERRUPT_FLAG_ADDRESS => self.interrupts.write_flag(byte),
+            BOOT_ROM_DISABLE_ADDRESS => {
+                if byte != 0 {
+                    self.boot_rom = None;
+                }
+            }
+            IO_START..=IO_END => self.io[(address - IO_START) as usize] = byte,
+            HRAM_START..=HRAM_END => self.hram[(address - HRAM_START) as usize] = byte,
+            INTERRUPT_ENABLE_ADDRESS => self.interrupts.write_enable(byte),
+        }
+    }
+
+    /// Writes the bytes through the memory map, stopping at the end of the address space
+    pub(crate) fn copy_bytes(&mut self, start_address: u16, bytes: &[u8]) {
+        for (address, byte) in (start_address..=0xFFFF).zip(bytes) {
+            self.write_byte(address, *byte);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::test_rom;
+
+    #[test]
+    fn rom_is_read_only() {
+        let mut bus = MemoryBus::default();
+        assert_eq!(bus.read_byte(0x0200), OPEN_BUS, "No cartridge inserted");
+
+        bus.insert_cartridge(Cartridge::new(test_rom(0x00, 0x00, 0x00, 0x12)).unwrap());
+        bus.write_byte(0x0200, 0x34);
+        assert_eq!(bus.read_byte(0x0200), 0x12);
+        assert_eq!(bus.read_byte(0x7FFF), 0x12);
+        assert_eq!(bus.read_byte(0xA000), OPEN_BUS, "Cartridge has no RAM");
+    }
+
+    #[test]
+    fn echo_ram_mirrors_wram() {
+        let mut bus = MemoryBus::default();
+        bus.write_byte(0xC123, 0x42);
+        assert_eq!(bus.read_byte(0xE123), 0x42);
+        bus.write_byte(0xFDFF, 0x24);
+        assert_eq!(bus.read_byte(0xDDFF), 0x24);
+        // 0xDE00-0xDFFF is not mirrored
+        bus.write_byte(0xDE00, 0x11);
+        assert_eq!(bus.read_byte(0xFE00), 0x00);
+    }
+
+    #[test]
+    fn unusable_region_ignores_writes() {
+        let mut bus = MemoryBus::default();
+        bus.write_byte(0xFEA0, 0x42);
+        bus.write_byte(0xFEFF, 0x42);
+        assert_eq!(bus.read_byte(0xFEA0), 0x00);
+        assert_eq!(bus.read_byte(0xFEFF), 0x00);
+    }
+
+    #[test]
+    fn ram_regions() {
+        let mut bus = MemoryBus::default();
+        for address in [
+            0x8000, 0x9FFF, 0xC000, 0xDFFF, 0xFE00, 0xFE9F, 0xFF80, 0xFFFE,
+        ] {
+            bus.write_byte(address, 0x5A);
+            assert_eq!(bus.read_byte(address), 0x5A, "0x{address:04X}");
+        }
+        bus.write_byte(0xFFFF, 0x1F);
+        assert_eq!(bus.read_byte(0xFFFF), 0x1F, "IE register");
+    }
+
+    #[test]
+    fn oam_dma() {
+        let mut bus = MemoryBus::default();
+        bus.insert_cartridge(Cartridge::new(test_rom(0x00, 0x00, 0x00, 0x12)).unwrap());
+        for i in 0..0xA0 {
+            bus.write_byte(0xC100 + i, i as u8 + 1);
+        }
+        bus.write_byte(0x8000, 0x42);
+        bus.write_byte(DMA_ADDRESS, 0xC1);
+        assert_eq!(bus.read_byte(DMA_ADDRESS), 0xC1);
+        bus.tick(3);
+
+        // The CPU sees the byte being transferred anywhere on the external bus
+        assert_eq!(bus.read_byte(0xC000), 0x03);
+        assert_eq!(bus.read_byte(0x0200), 0x03);
+        bus.write_byte(0xC100, 0x99);
+        assert_eq!(bus.read_mapped(0xC100), 0x01, "Writes are ignored");
+        assert_eq!(bus.read_byte(0xFE00), OPEN_BUS);
+        // VRAM, I/O registers and HRAM are on other buses
+        assert_eq!(bus.read_byte(0x8000), 0x42);
+        bus.write_byte(0xFF80, 0x24);
+        assert_eq!(bus.read_byte(0xFF80), 0x24);
+
+        bus.tick(157);
+        assert_eq!(bus.read_byte(0xC000), 0xA0);
+        bus.tick(1);
+        assert_eq!(bus.read_byte(0xC000), 0x00);
+        assert_eq!(bus.read_byte(0xFE00), 0x01);
+        assert_eq!(bus.read_byte(0xFE9F), 0xA0);
+    }
+}
@@ -0,0 +1,176 @@
+pub(crate) const JOYPAD_ADDRESS: u16 = 0xFF00;
+
+/// Selects the direction buttons when cleared
+const SELECT_DIRECTIONS_BIT: u8 = 0b0001_0000;
+/// Selects the action buttons when cleared
+const SELECT_ACTIONS_BIT: u8 = 0b0010_0000;
+const SELECT_MASK: u8 = SELECT_DIRECTIONS_BIT | SELECT_ACTIONS_BIT;
+/// Bits 6 and 7 are not connected and read as 1
+const JOYPAD_UNUSED_BITS: u8 = 0b1100_0000;
+/// The 4 input lines, a pressed button pulls its line low
+const LINES_MASK: u8 = 0b0000_1111;
+
+/// Pressed buttons, independent of where the input comes from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Buttons {
+    pub(crate) right: bool,
+    pub(crate) left: bool,
+    pub(crate) up: bool,
+    pub(crate) down: bool,
+    pub(crate) a: bool,
+    pub(crate) b: bool,
+    pub(crate) select: bool,
+    pub(crate) start: bool,
+}
+
+/// One bit per button, the directions in the lower and the actions in the upper nibble in the
+/// order of the input lines
+impl From<u8> for Buttons {
+    fn from(byte: u8) -> Self {
+        Self {
+            right: byte & 0b0000_0001 != 0,
+            left: byte & 0b0000_0010 != 0,
+            up: byte & 0b0000_0100 != 0,
+            down: byte & 0b0000_1000 != 0,
+            a: byte & 0b0001_0000 != 0,
+            b: byte & 0b0010_0000 != 0,
+            select: byte & 0b0100_0000 != 0,
+            start: byte & 0b1000_0000 != 0,
+        }
+    }
+}
+
+impl From<Buttons> for u8 {
+    fn from(buttons: Buttons) -> Self {
+        [
+            buttons.right,
+            buttons.left,
+            buttons.up,
+            buttons.down,
+            buttons.a,
+            buttons.b,
+            buttons.select,
+            buttons.start,
+        ]
+        .into_iter()
+        .enumerate()
+        .fold(0, |byte, (bit, pressed)| byte | ((pressed as u8) << bit))
+    }
+}
+
+/// P1/JOYP (0xFF00), the button matrix selected by bits 4 and 5 is read from bits 0-3
+#[derive(Debug)]
+pub(crate) struct Joypad {
+    /// Select bits as written
+    select: u8,
+    buttons: Buttons,
+}
+
+impl Default for Joypad {
+    fn default() -> Self {
+        Self {
+            select: SELECT_MASK,
+            buttons: Buttons::default(),
+        }
+    }
+}
+
+impl Joypad {
+    /// The input lines, low for pressed buttons in the selected groups
+    fn lines(&self) -> u8 {
+        let pressed = u8::from(self.buttons);
+        let mut lines = LINES_MASK;
+        if self.select & SELECT_DIRECTIONS_BIT == 0 {
+            lines &= !pressed;
+        }
+        if self.select & SELECT_ACTIONS_BIT == 0 {
+            lines &= !(pressed >> 4);
+        }
+        lines & LINES_MASK
+    }
+
+    /// Applies a change to the lines, returns whether the Joypad interrupt is requested because a
+    /// line went low
+    fn update(&mut self, change: impl FnOnce(&mut Self)) -> bool {
+        let old_lines = self.lines();
+        change(self);
+        old_lines & !self.lines() != 0
+    }
+
+    /// Returns whether the Joypad interrupt is requested
+    pub(crate) fn set_buttons(&mut self, buttons: Buttons) -> bool {
+        self.update(|joypad| joypad.buttons = buttons)
+    }
+
+    /// Whether a button in the selected groups pulls its line low
+    pub(crate) fn line_low(&self) -> bool {
+        self.lines() != LINES_MASK
+    }
+
+    pub(crate) fn read(&self) -> u8 {
+        JOYPAD_UNUSED_BITS | self.select | self.lines()
+    }
+
+    /// Returns whether the Joypad interrupt is requested, selecting a group with a pressed button
+    /// pulls a line low as well
+    pub(crate) fn write(&mut self, byte: u8) -> bool {
+        self.update(|joypad| joypad.select = byte & SELECT_MASK)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buttons_byte_round_trip() {
+        let buttons = Buttons {
+            up: true,
+            b: true,
+            start: true,
+            ..Default::default()
+        };
+        assert_eq!(u8::from(buttons), 0b1010_0100);
+        assert_eq!(Buttons::from(0b1010_0100), buttons);
+    }
+
+    #[test]
+    fn selected_groups() {
+        let mut joypad = Joypad::default();
+        joypad.set_buttons(Buttons {
+            down: true,
+            a: true,
+            ..Default::default()
+        });
+        assert_eq!(joypad.read(), 0xFF, "Nothing selected");
+        joypad.write(0x20);
+        assert_eq!(joypad.read(), 0xE7, "Directions");
+        joypad.write(0x10);
+        assert_eq!(joypad.read(), 0xDE, "Actions");
+        joypad.write(0x00);
+        assert_eq!(joypad.read(), 0xC6, "Both");
+    }
+
+    #[test]
+    fn interrupt_on_press() {
+        let mut joypad = Joypad::default();
+        let start = Buttons {
+            start: true,
+            ..Default::default()
+        };
+        assert!(!joypad.set_buttons(start), "Actions are not selected");
+        assert!(joypad.write(0x10), "Selecting pulls the Start line low");
+        assert!(!joypad.set_buttons(Buttons::default()), "Releasing");
+        assert!(joypad.set_buttons(start));
+
+        joypad.write(0x00);
+        assert!(
+            !joypad.set_buttons(Buttons {
+                down: true,
+                ..start
+            }),
+            "Down shares the line with Start"
+        );
+        assert!(joypad.set_buttons(Buttons { a: true, ..start }));
+    }
+}
@@ -1,13 +1,352 @@
+mod apu;
+mod cartridge;
 mod cpu;
+mod joypad;
+mod ppu;
+mod serial;
+mod timer;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use apu::CHANNELS;
+use cartridge::Cartridge;
+use cpu::{Boot, BootRom, Cpu, ExecutionError, Model};
+use joypad::Buttons;
+use ppu::{Renderer, SCREEN_HEIGHT, SCREEN_WIDTH};
 use raylib::prelude::*;
+use serial::StdoutSink;
+
+const USAGE: &str = "Usage: gb-emulator [--pixel-fifo] [--audio-sync] [--serial] \
+                     [--lock-on-illegal] [--strict-bus] \
+                     [--boot-rom <dmg_boot.bin> | --model <dmg0|dmg|mgb|sgb|sgb2>] \
+                     [--link-listen <address> | --link-connect <address>] <rom.gb>";
+
+/// Instructions listed from the address of an execution error
+const CRASH_LISTING_LENGTH: usize = 4;
+
+/// How often battery-backed RAM is written to disk while running
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Size of a Game Boy pixel on screen
+const SCALE: i32 = 4;
+/// The DMG runs at about 59.7 frames per second
+const FRAMES_PER_SECOND: u32 = 60;
+/// Colors of the shade indices, from lightest to darkest
+const SHADES: [Color; 4] = [
+    Color::new(0xE0, 0xF8, 0xD0, 0xFF),
+    Color::new(0x88, 0xC0, 0x70, 0xFF),
+    Color::new(0x34, 0x68, 0x56, 0xFF),
+    Color::new(0x08, 0x18, 0x20, 0xFF),
+];
+
+const SAMPLE_RATE: u32 = 48_000;
+const AUDIO_CHANNELS: usize = 2;
+const BITS_PER_SAMPLE: u32 = 32;
+/// Frames raylib plays from each of its two buffers, about 21 ms
+const AUDIO_BUFFER_FRAMES: usize = 1024;
+/// Emulated audio beyond this is dropped, which keeps the latency low when emulation runs ahead
+const MAX_QUEUED_FRAMES: usize = 4 * AUDIO_BUFFER_FRAMES;
+/// With audio sync, frames are emulated until this much audio is queued
+const AUDIO_SYNC_QUEUED_FRAMES: usize = 2 * AUDIO_BUFFER_FRAMES;
+/// Bounds the frames emulated per displayed frame with audio sync, a stopped CPU produces no audio
+const MAX_AUDIO_SYNC_FRAMES: u32 = 4;
+/// Toggle the sound channels 1-4
+const CHANNEL_KEYS: [KeyboardKey; CHANNELS] = [
+    KeyboardKey::KEY_ONE,
+    KeyboardKey::KEY_TWO,
+    KeyboardKey::KEY_THREE,
+    KeyboardKey::KEY_FOUR,
+];
+const MUTE_KEY: KeyboardKey = KeyboardKey::KEY_M;
+
+/// Streams the emulated audio to raylib, always in whole buffers since raylib pads partial ones
+/// with silence, which crackles
+struct AudioOutput<'a> {
+    stream: AudioStream<'a>,
+    /// Interleaved left and right samples not handed to raylib yet
+    queue: VecDeque<f32>,
+    /// Last frame handed to raylib, repeated when emulation falls behind
+    last_frame: [f32; AUDIO_CHANNELS],
+    muted: bool,
+}
+
+impl<'a> AudioOutput<'a> {
+    fn new(audio: &'a RaylibAudio) -> Self {
+        audio.set_audio_stream_buffer_size_default(AUDIO_BUFFER_FRAMES as i32);
+        let stream = audio.new_audio_stream(SAMPLE_RATE, BITS_PER_SAMPLE, AUDIO_CHANNELS as u32);
+        stream.play();
+        Self {
+            stream,
+            queue: VecDeque::with_capacity(MAX_QUEUED_FRAMES * AUDIO_CHANNELS),
+            last_frame: [0.0; AUDIO_CHANNELS],
+            muted: false,
+        }
+    }
+
+    fn queued_frames(&self) -> usize {
+        self.queue.len() / AUDIO_CHANNELS
+    }
+
+    fn push(&mut self, samples: &[f32]) {
+        self.queue.extend(samples);
+        let excess = self
+            .queue
+            .len()
+            .saturating_sub(MAX_QUEUED_FRAMES * AUDIO_CHANNELS);
+        self.queue.drain(..excess);
+    }
+
+    /// Hands a buffer to raylib whenever it finished playing one
+    fn update(&mut self) {
+        while self.stream.is_processed() {
+            let len = (AUDIO_BUFFER_FRAMES * AUDIO_CHANNELS).min(self.queue.len());
+            let mut buffer: Vec<f32> = self.queue.drain(..len).collect();
+            if let Some(frame) = buffer.last_chunk() {
+                self.last_frame = *frame;
+            }
+            // Repeating the last frame is less audible than a gap of silence
+            while buffer.len() < AUDIO_BUFFER_FRAMES * AUDIO_CHANNELS {
+                buffer.extend(self.last_frame);
+            }
+            self.stream.update(&buffer);
+        }
+    }
+
+    fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        self.stream.set_volume(if self.muted { 0.0 } else { 1.0 });
+    }
+}
+
+/// Reads the buttons from the keyboard
+fn pressed_buttons(rl: &RaylibHandle) -> Buttons {
+    Buttons {
+        right: rl.is_key_down(KeyboardKey::KEY_RIGHT),
+        left: rl.is_key_down(KeyboardKey::KEY_LEFT),
+        up: rl.is_key_down(KeyboardKey::KEY_UP),
+        down: rl.is_key_down(KeyboardKey::KEY_DOWN),
+        a: rl.is_key_down(KeyboardKey::KEY_X),
+        b: rl.is_key_down(KeyboardKey::KEY_Z),
+        select: rl.is_key_down(KeyboardKey::KEY_BACKSPACE),
+        start: rl.is_key_down(KeyboardKey::KEY_ENTER),
+    }
+}
+
+fn flush_save(cpu: &mut Cpu) {
+    if let Some(Err(error)) = cpu.cartridge_mut().map(Cartridge::flush_save) {
+        eprintln!("{error}");
+    }
+}
+
+/// Prints the error together with the instructions at the address it occurred
+fn print_crash_listing(cpu: &Cpu, error: &ExecutionError) {
+    eprintln!("{error}");
+    let mut address = error.address();
+    for _ in 0..CRASH_LISTING_LENGTH {
+        let disassembly = cpu.disassemble(address);
+        eprintln!("  {address:04X}  {disassembly}");
+        address = address.wrapping_add(disassembly.length() as u16);
+    }
+}
+
+/// Status shown while sound is muted, e.g. "Sound: 1 - 3 4" with channel 2 muted
+fn sound_status(
+    audio: Option<&AudioOutput>,
+    enabled_channels: &[bool; CHANNELS],
+) -> Option<String> {
+    if audio.is_some_and(|audio| audio.muted) {
+        return Some("Sound: muted".to_string());
+    }
+    if enabled_channels.iter().all(|&enabled| enabled) {
+        return None;
+    }
+    let channels: Vec<String> = enabled_channels
+        .iter()
+        .enumerate()
+        .map(|(channel, &enabled)| {
+            if enabled {
+                (channel + 1).to_string()
+            } else {
+                "-".to_string()
+            }
+        })
+        .collect();
+    Some(format!("Sound: {}", channels.join(" ")))
+}
+
+/// Whether to emulate another frame before drawing. With audio sync the emulation keeps the audio
+/// queue filled, otherwise one frame is emulated per displayed frame
+fn wants_frame(audio: Option<&AudioOutput>, audio_sync: bool, frames: u32) -> bool {
+    match audio {
+        Some(audio) if audio_sync => {
+            frames < MAX_AUDIO_SYNC_FRAMES && audio.queued_frames() < AUDIO_SYNC_QUEUED_FRAMES
+        }
+        _ => frames == 0,
+    }
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{USAGE}");
+    std::process::exit(1);
+}
 
 fn main() {
-    let (mut rl, thread) = raylib::init().size(640, 480).title("Hello, World").build();
+    let mut renderer = Renderer::Scanline;
+    let mut audio_sync = false;
+    let mut serial_to_stdout = false;
+    let mut lock_on_illegal_opcode = false;
+    let mut strict_bus_access = false;
+    let mut link = None;
+    let mut boot_rom_path = None;
+    let mut model = Model::default();
+    let mut path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pixel-fifo" => renderer = Renderer::PixelFifo,
+            "--audio-sync" => audio_sync = true,
+            "--serial" => serial_to_stdout = true,
+            "--lock-on-illegal" => lock_on_illegal_opcode = true,
+            "--strict-bus" => strict_bus_access = true,
+            "--boot-rom" => {
+                let Some(boot_rom) = args.next() else {
+                    exit_with_usage();
+                };
+                boot_rom_path = Some(boot_rom);
+            }
+            "--model" => {
+                let Some(parsed) = args.next().and_then(|name| Model::from_name(&name)) else {
+                    exit_with_usage();
+                };
+                model = parsed;
+            }
+            "--link-listen" | "--link-connect" => {
+                let Some(address) = args.next() else {
+                    exit_with_usage();
+                };
+                link = Some((arg == "--link-listen", address));
+            }
+            _ => path = Some(arg),
+        }
+    }
+    let Some(path) = path else {
+        exit_with_usage();
+    };
+    let cartridge = match Cartridge::load(path) {
+        Ok(cartridge) => cartridge,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    let header = cartridge.header();
+    if !header.global_checksum_valid {
+        eprintln!(
+            "Warning: the global checksum 0x{:04X} doesn't match the ROM, starting anyway",
+            header.global_checksum
+        );
+    }
+    let title = header.title.clone();
+    let boot = match boot_rom_path.map(BootRom::load) {
+        Some(Ok(boot_rom)) => Boot::Rom(boot_rom),
+        Some(Err(error)) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+        None => Boot::Skip(model),
+    };
+    let mut cpu = Cpu::with_options(cartridge, renderer, boot);
+    cpu.set_sample_rate(SAMPLE_RATE);
+    cpu.set_lock_on_illegal_opcode(lock_on_illegal_opcode);
+    cpu.set_strict_bus_access(strict_bus_access);
+    if serial_to_stdout {
+        cpu.set_serial_sink(Box::new(StdoutSink));
+    }
+    // Both emulators connect before the first cycle, so their sync points line up
+    let link = link.map(|(listen, address)| {
+        if listen {
+            eprintln!("Waiting for the link partner on {address}");
+            serial::listen(&address)
+        } else {
+            serial::connect(&address)
+        }
+    });
+    match link {
+        Some(Ok(link)) => cpu.set_link(link),
+        Some(Err(error)) => {
+            eprintln!("Link cable: {error}");
+            std::process::exit(1);
+        }
+        None => {}
+    }
+
+    let (mut rl, thread) = raylib::init()
+        .size(SCREEN_WIDTH as i32 * SCALE, SCREEN_HEIGHT as i32 * SCALE)
+        .title(&title)
+        .build();
+    rl.set_target_fps(FRAMES_PER_SECOND);
+    // Without an audio device the emulator keeps running silently, paced by the frame rate
+    let raylib_audio = RaylibAudio::init_audio_device()
+        .inspect_err(|error| eprintln!("{error}"))
+        .ok();
+    let mut audio = raylib_audio.as_ref().map(AudioOutput::new);
+    let mut enabled_channels = [true; CHANNELS];
 
+    let mut linked = cpu.is_linked();
+    let mut running = true;
+    let mut last_save = Instant::now();
     while !rl.window_should_close() {
+        if rl.is_key_pressed(MUTE_KEY)
+            && let Some(audio) = audio.as_mut()
+        {
+            audio.toggle_mute();
+        }
+        for (channel, key) in CHANNEL_KEYS.into_iter().enumerate() {
+            if rl.is_key_pressed(key) {
+                enabled_channels[channel] = !enabled_channels[channel];
+                cpu.set_audio_channel_enabled(channel, enabled_channels[channel]);
+            }
+        }
+        cpu.set_buttons(pressed_buttons(&rl));
+
+        let mut frames = 0;
+        while running && wants_frame(audio.as_ref(), audio_sync, frames) {
+            if let Err(error) = cpu.run_frame() {
+                print_crash_listing(&cpu, &error);
+                running = false;
+            }
+            frames += 1;
+            let samples = cpu.take_samples();
+            if let Some(audio) = audio.as_mut() {
+                audio.push(&samples);
+            }
+        }
+        if let Some(audio) = audio.as_mut() {
+            audio.update();
+        }
+        if linked && !cpu.is_linked() {
+            eprintln!("Link cable: the partner disconnected");
+            linked = false;
+        }
+        if last_save.elapsed() >= SAVE_INTERVAL {
+            flush_save(&mut cpu);
+            last_save = Instant::now();
+        }
+
         let mut d = rl.begin_drawing(&thread);
 
-        d.clear_background(Color::WHITE);
-        d.draw_text("Hello, world!", 12, 12, 20, Color::BLACK);
+        for (i, shade) in cpu.framebuffer().iter().enumerate() {
+            let x = (i % SCREEN_WIDTH) as i32 * SCALE;
+            let y = (i / SCREEN_WIDTH) as i32 * SCALE;
+            d.draw_rectangle(x, y, SCALE, SCALE, SHADES[*shade as usize]);
+        }
+        if let Some(status) = sound_status(audio.as_ref(), &enabled_channels) {
+            d.draw_text(&status, SCALE, SCALE, 5 * SCALE, Color::RED);
+        }
+        if cpu.cartridge().is_some_and(Cartridge::rumble) {
+            let y = (SCREEN_HEIGHT as i32 - 6) * SCALE;
+            d.draw_text("Rumble", SCALE, y, 5 * SCALE, Color::RED);
+        }
     }
+    flush_save(&mut cpu);
 }
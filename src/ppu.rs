@@ -0,0 +1,947 @@
+mod fifo;
+use fifo::PixelFifo;
+
+pub(crate) const VRAM_START: u16 = 0x8000;
+pub(crate) const VRAM_END: u16 = 0x9FFF;
+pub(crate) const OAM_START: u16 = 0xFE00;
+pub(crate) const OAM_END: u16 = 0xFE9F;
+
+pub(crate) const LCD_CONTROL_ADDRESS: u16 = 0xFF40;
+pub(crate) const LCD_STATUS_ADDRESS: u16 = 0xFF41;
+pub(crate) const SCROLL_Y_ADDRESS: u16 = 0xFF42;
+pub(crate) const SCROLL_X_ADDRESS: u16 = 0xFF43;
+pub(crate) const LY_ADDRESS: u16 = 0xFF44;
+pub(crate) const LY_COMPARE_ADDRESS: u16 = 0xFF45;
+pub(crate) const BG_PALETTE_ADDRESS: u16 = 0xFF47;
+pub(crate) const OBJECT_PALETTE_0_ADDRESS: u16 = 0xFF48;
+pub(crate) const OBJECT_PALETTE_1_ADDRESS: u16 = 0xFF49;
+pub(crate) const WINDOW_Y_ADDRESS: u16 = 0xFF4A;
+pub(crate) const WINDOW_X_ADDRESS: u16 = 0xFF4B;
+
+const VRAM_SIZE: usize = (VRAM_END - VRAM_START + 1) as usize;
+const OAM_SIZE: usize = (OAM_END - OAM_START + 1) as usize;
+
+/// Offsets into VRAM
+const LOW_TILE_MAP: u16 = 0x1800;
+const HIGH_TILE_MAP: u16 = 0x1C00;
+/// Tile 0 when LCDC bit 4 is clear, tiles are addressed with a signed index from here
+const SIGNED_TILE_DATA: u16 = 0x1000;
+const TILE_SIZE: u16 = 16;
+const TILE_WIDTH: u8 = 8;
+/// Tile maps are 32×32 tiles
+const TILE_MAP_WIDTH: u16 = 32;
+/// WX is the window position plus 7
+const WINDOW_X_OFFSET: u8 = 7;
+
+/// Each OAM entry is Y, X, tile and attributes
+const OBJECT_SIZE: usize = 4;
+const MAX_OBJECTS_PER_LINE: usize = 10;
+/// Object coordinates are offset so that objects can be partially off screen at the top left
+const OBJECT_Y_OFFSET: u8 = 16;
+const OBJECT_X_OFFSET: u8 = 8;
+const BG_OVER_OBJECT_BIT: u8 = 0b1000_0000;
+const Y_FLIP_BIT: u8 = 0b0100_0000;
+const X_FLIP_BIT: u8 = 0b0010_0000;
+const OBJECT_PALETTE_BIT: u8 = 0b0001_0000;
+
+pub(crate) const SCREEN_WIDTH: usize = 160;
+pub(crate) const SCREEN_HEIGHT: usize = 144;
+/// One shade index from 0 (lightest) to 3 (darkest) per pixel, row by row
+pub(crate) type Framebuffer = [u8; SCREEN_WIDTH * SCREEN_HEIGHT];
+
+/// The PPU draws one pixel per dot, a dot is one T-cycle
+const DOTS_PER_M_CYCLE: u16 = 4;
+const OAM_SCAN_DOTS: u16 = 80;
+/// Drawing takes at least 172 dots, more with scrolling, the window and sprites
+const DRAWING_DOTS: u16 = 172;
+const DOTS_PER_LINE: u16 = 456;
+/// Lines 144-153 are the vertical blank
+const LINES_PER_FRAME: u8 = 154;
+pub(crate) const T_CYCLES_PER_FRAME: u64 = DOTS_PER_LINE as u64 * LINES_PER_FRAME as u64;
+
+const LCD_ENABLE_BIT: u8 = 0b1000_0000;
+const WINDOW_TILE_MAP_BIT: u8 = 0b0100_0000;
+const WINDOW_ENABLE_BIT: u8 = 0b0010_0000;
+const TILE_DATA_BIT: u8 = 0b0001_0000;
+const BG_TILE_MAP_BIT: u8 = 0b0000_1000;
+/// Objects are 8×16 instead of 8×8 pixels
+const OBJECT_SIZE_BIT: u8 = 0b0000_0100;
+const OBJECT_ENABLE_BIT: u8 = 0b0000_0010;
+/// On the DMG this turns both the background and the window blank
+const BG_WINDOW_ENABLE_BIT: u8 = 0b0000_0001;
+
+const LY_COMPARE_SELECT_BIT: u8 = 0b0100_0000;
+const OAM_SCAN_SELECT_BIT: u8 = 0b0010_0000;
+const VBLANK_SELECT_BIT: u8 = 0b0001_0000;
+const HBLANK_SELECT_BIT: u8 = 0b0000_1000;
+const LY_COMPARE_FLAG_BIT: u8 = 0b0000_0100;
+/// Bit 7 of STAT always reads as 1
+const LCD_STATUS_UNUSED_BITS: u8 = 0b1000_0000;
+const LCD_STATUS_WRITABLE_BITS: u8 =
+    LY_COMPARE_SELECT_BIT | OAM_SCAN_SELECT_BIT | VBLANK_SELECT_BIT | HBLANK_SELECT_BIT;
+
+const INACCESSIBLE: u8 = 0xFF;
+
+/// PPU mode as reported in the lower two bits of STAT
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mode {
+    /// Also reported while the LCD is off
+    #[default]
+    HBlank = 0,
+    VBlank = 1,
+    OamScan = 2,
+    Drawing = 3,
+}
+
+/// How the PPU turns VRAM and OAM into pixels
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Renderer {
+    /// Draws each line at once at the end of a fixed length mode 3. Fast, but register writes
+    /// during mode 3 only take effect on the next line
+    #[default]
+    Scanline,
+    /// Draws one pixel per dot through a pixel FIFO like the hardware does, so mode 3 takes as
+    /// long as the scroll, the window and the objects make it take
+    PixelFifo,
+}
+
+/// Interrupts the PPU requested during a tick
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PpuInterrupts {
+    pub(crate) vblank: bool,
+    pub(crate) stat: bool,
+}
+
+/// An OAM entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Object {
+    y: u8,
+    x: u8,
+    tile: u8,
+    attributes: u8,
+}
+
+impl Object {
+    fn from_oam(bytes: &[u8]) -> Self {
+        Self {
+            y: bytes[0],
+            x: bytes[1],
+            tile: bytes[2],
+            attributes: bytes[3],
+        }
+    }
+
+    /// Row of the object on line `ly`, outside of `0..height` if the object is not on the line
+    fn row(&self, ly: u8) -> u8 {
+        (ly + OBJECT_Y_OFFSET).wrapping_sub(self.y)
+    }
+}
+
+/// Pixel processing unit owning VRAM, OAM and the LCD registers at 0xFF40-0xFF45
+pub(crate) struct Ppu {
+    vram: [u8; VRAM_SIZE],
+    oam: [u8; OAM_SIZE],
+    /// LCDC
+    control: u8,
+    /// Interrupt select bits of STAT
+    status: u8,
+    scroll_y: u8,
+    scroll_x: u8,
+    ly: u8,
+    /// LYC
+    ly_compare: u8,
+    /// BGP
+    bg_palette: u8,
+    /// OBP0 and OBP1
+    object_palettes: [u8; 2],
+    window_y: u8,
+    window_x: u8,
+    /// Set once LY matched WY during the frame, the window is only drawn afterwards
+    window_y_triggered: bool,
+    /// Line of the window to draw next, only advances on lines the window was drawn on
+    window_line: u8,
+    mode: Mode,
+    /// Objects on the current line found by the OAM scan, ordered by their drawing priority
+    line_objects: Vec<Object>,
+    renderer: Renderer,
+    /// Mode 3 state of the pixel FIFO renderer
+    fifo: PixelFifo,
+    /// Dot within the current line
+    dot: u16,
+    /// The STAT interrupt is requested on the rising edge of this line
+    stat_line: bool,
+    framebuffer: Framebuffer,
+    /// Set when a frame is complete, the frontend clears it when it takes the frame
+    frame_ready: bool,
+}
+
+impl Default for Ppu {
+    fn default() -> Self {
+        Self::new(Renderer::default())
+    }
+}
+
+impl Ppu {
+    pub(crate) fn new(renderer: Renderer) -> Self {
+        Self {
+            vram: [0; VRAM_SIZE],
+            oam: [0; OAM_SIZE],
+            control: 0,
+            status: 0,
+            scroll_y: 0,
+            scroll_x: 0,
+            ly: 0,
+            ly_compare: 0,
+            bg_palette: 0,
+            object_palettes: [0; 2],
+            window_y: 0,
+            window_x: 0,
+            window_y_triggered: false,
+            window_line: 0,
+            mode: Mode::default(),
+            line_objects: Vec::with_capacity(MAX_OBJECTS_PER_LINE),
+            renderer,
+            fifo: PixelFifo::default(),
+            dot: 0,
+            stat_line: false,
+            framebuffer: [0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            frame_ready: false,
+        }
+    }
+
+    fn lcd_enabled(&self) -> bool {
+        self.control & LCD_ENABLE_BIT != 0
+    }
+
+    pub(crate) fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    /// Returns whether a frame was completed since the last call
+    pub(crate) fn take_frame(&mut self) -> bool {
+        std::mem::take(&mut self.frame_ready)
+    }
+
+    /// Advances the PPU by one machine cycle
+    pub(crate) fn tick(&mut self) -> PpuInterrupts {
+        let mut interrupts = PpuInterrupts::default();
+        if !self.lcd_enabled() {
+            return interrupts;
+        }
+
+        for _ in 0..DOTS_PER_M_CYCLE {
+            interrupts.vblank |= self.tick_dot();
+        }
+
+        let stat_line = self.stat_line();
+        interrupts.stat = stat_line && !self.stat_line;
+        self.stat_line = stat_line;
+        interrupts
+    }
+
+    /// Advances the PPU by one dot, returns whether the VBlank interrupt is requested
+    fn tick_dot(&mut self) -> bool {
+        self.dot += 1;
+        match self.mode {
+            Mode::OamScan if self.dot == OAM_SCAN_DOTS => self.start_drawing(),
+            Mode::Drawing => {
+                let line_done = match self.renderer {
+                    Renderer::Scanline if self.dot == OAM_SCAN_DOTS + DRAWING_DOTS => {
+                        self.render_line();
+                        true
+                    }
+                    Renderer::Scanline => false,
+                    Renderer::PixelFifo => self.fifo_dot(),
+                };
+                if line_done {
+                    self.mode = Mode::HBlank;
+                }
+            }
+            _ if self.dot == DOTS_PER_LINE => {
+                self.dot = 0;
+                self.ly = (self.ly + 1) % LINES_PER_FRAME;
+                if self.ly as usize == SCREEN_HEIGHT {
+                    self.frame_ready = true;
+                    self.window_y_triggered = false;
+                    self.window_line = 0;
+                    self.mode = Mode::VBlank;
+                    return true;
+                }
+                self.mode = if (self.ly as usize) < SCREEN_HEIGHT {
+                    Mode::OamScan
+                } else {
+                    Mode::VBlank
+                };
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Ends the OAM scan and enters mode 3
+    fn start_drawing(&mut self) {
+        if self.ly == self.window_y {
+            self.window_y_triggered = true;
+        }
+        self.scan_oam();
+        if self.renderer == Renderer::PixelFifo {
+            self.fifo.start_line(self.scroll_x);
+        }
+        self.mode = Mode::Drawing;
+    }
+
+    /// The enabled STAT interrupt sources ORed together
+    fn stat_line(&self) -> bool {
+        let mode_selected = match self.mode {
+            Mode::HBlank => self.status & HBLANK_SELECT_BIT != 0,
+            Mode::VBlank => self.status & VBLANK_SELECT_BIT != 0,
+            Mode::OamScan => self.status & OAM_SCAN_SELECT_BIT != 0,
+            Mode::Drawing => false,
+        };
+        let ly_compare_selected =
+            self.status & LY_COMPARE_SELECT_BIT != 0 && self.ly == self.ly_compare;
+        mode_selected || ly_compare_selected
+    }
+
+    /// Draws line LY into the framebuffer
+    fn render_line(&mut self) {
+        let mut colors = [0; SCREEN_WIDTH];
+        if self.control & BG_WINDOW_ENABLE_BIT != 0 {
+            self.render_background(&mut colors);
+            self.render_window(&mut colors);
+        }
+
+        let mut line = [0; SCREEN_WIDTH];
+        for (x, (pixel, color)) in line.iter_mut().zip(colors).enumerate() {
+            *pixel = self
+                .object_shade(x as u8, color)
+                .unwrap_or_else(|| shade(self.bg_palette, color));
+        }
+        let start = self.ly as usize * SCREEN_WIDTH;
+        self.framebuffer[start..start + SCREEN_WIDTH].copy_from_slice(&line);
+    }
+
+    fn object_height(&self) -> u8 {
+        if self.control & OBJECT_SIZE_BIT != 0 {
+            2 * TILE_WIDTH
+        } else {
+            TILE_WIDTH
+        }
+    }
+
+    /// Selects the first 10 objects in OAM that overlap line LY. Objects off screen horizontally
+    /// count towards the limit as well
+    fn scan_oam(&mut self) {
+        let height = self.object_height();
+        let ly = self.ly;
+        self.line_objects.clear();
+        self.line_objects.extend(
+            self.oam
+                .chunks_exact(OBJECT_SIZE)
+                .map(Object::from_oam)
+                .filter(|object| object.row(ly) < height)
+                .take(MAX_OBJECTS_PER_LINE),
+        );
+        // The object with the smaller X is drawn on top, the stable sort keeps the OAM order on
+        // ties
+        self.line_objects.sort_by_key(|object| object.x);
+    }
+
+    /// Shade of the object pixel at `x`, or `None` if the background shows through
+    fn object_shade(&self, x: u8, bg_color: u8) -> Option<u8> {
+        if self.control & OBJECT_ENABLE_BIT == 0 {
+            return None;
+        }
+        let (object, color) = self.line_objects.iter().find_map(|object| {
+            let column = (x + OBJECT_X_OFFSET).wrapping_sub(object.x);
+            if column >= TILE_WIDTH {
+                return None;
+            }
+            // Color 0 is transparent and reveals the objects below
+            let color = self.object_color(object, column);
+            (color != 0).then_some((object, color))
+        })?;
+        self.mix_object(color, object.attributes, bg_color)
+    }
+
+    /// Shade of an opaque object pixel over the background, `None` if the background has priority
+    fn mix_object(&self, color: u8, attributes: u8, bg_color: u8) -> Option<u8> {
+        if attributes & BG_OVER_OBJECT_BIT != 0 && bg_color != 0 {
+            return None;
+        }
+        let palette = self.object_palettes[(attributes & OBJECT_PALETTE_BIT != 0) as usize];
+        Some(shade(palette, color))
+    }
+
+    /// Color index of an object pixel on line LY, objects always use the tiles from 0x8000
+    fn object_color(&self, object: &Object, column: u8) -> u8 {
+        let height = self.object_height();
+        // The size can change after the OAM scan, which only leaves the lower bits of the row
+        let mut row = object.row(self.ly) & (height - 1);
+        if object.attributes & Y_FLIP_BIT != 0 {
+            row = height - 1 - row;
+        }
+        let column = if object.attributes & X_FLIP_BIT != 0 {
+            TILE_WIDTH - 1 - column
+        } else {
+            column
+        };
+        // 8×16 objects ignore bit 0 of the tile index and use the following tile for the bottom
+        let tile = if height == TILE_WIDTH {
+            object.tile
+        } else {
+            (object.tile & 0xFE) + row / TILE_WIDTH
+        };
+        self.tile_color(tile as u16 * TILE_SIZE, column, row % TILE_WIDTH)
+    }
+
+    /// Fills the line with the color indices of the background, scrolled by SCX and SCY
+    fn render_background(&self, colors: &mut [u8; SCREEN_WIDTH]) {
+        let tile_map = self.tile_map(BG_TILE_MAP_BIT);
+        let y = self.ly.wrapping_add(self.scroll_y);
+        for (x, color) in colors.iter_mut().enumerate() {
+            let x = (x as u8).wrapping_add(self.scroll_x);
+            *color = self.tile_map_color(tile_map, x, y);
+        }
+    }
+
+    /// Draws the window over the background from WX - 7 to the end of the line
+    fn render_window(&mut self, colors: &mut [u8; SCREEN_WIDTH]) {
+        if self.control & WINDOW_ENABLE_BIT == 0
+            || !self.window_y_triggered
+            || self.window_x as usize >= SCREEN_WIDTH + WINDOW_X_OFFSET as usize
+        {
+            return;
+        }
+        let tile_map = self.tile_map(WINDOW_TILE_MAP_BIT);
+        let start = self.window_x.saturating_sub(WINDOW_X_OFFSET);
+        for (x, color) in colors.iter_mut().enumerate().skip(start as usize) {
+            let x = x as u8 + WINDOW_X_OFFSET - self.window_x;
+            *color = self.tile_map_color(tile_map, x, self.window_line);
+        }
+        self.window_line += 1;
+    }
+
+    /// The tile map selected by the given LCDC bit
+    fn tile_map(&self, select_bit: u8) -> u16 {
+        if self.control & select_bit != 0 {
+            HIGH_TILE_MAP
+        } else {
+            LOW_TILE_MAP
+        }
+    }
+
+    /// Color index of the pixel at `x` and `y` within the 256×256 pixels of a tile map
+    fn tile_map_color(&self, tile_map: u16, x: u8, y: u8) -> u8 {
+        let map_offset =
+            tile_map + (y / TILE_WIDTH) as u16 * TILE_MAP_WIDTH + (x / TILE_WIDTH) as u16;
+        let tile = self.bg_tile_offset(self.vram[map_offset as usize]);
+        self.tile_color(tile, x % TILE_WIDTH, y % TILE_WIDTH)
+    }
+
+    /// Offset of a background or window tile, addressed in the mode selected by LCDC bit 4
+    fn bg_tile_offset(&self, tile: u8) -> u16 {
+        if self.control & TILE_DATA_BIT != 0 {
+            tile as u16 * TILE_SIZE
+        } else {
+            SIGNED_TILE_DATA.wrapping_add_signed(tile as i8 as i16 * TILE_SIZE as i16)
+        }
+    }
+
+    /// Color index of a pixel within a tile, each row is two bytes holding the low and high bits
+    fn tile_color(&self, tile_offset: u16, x: u8, y: u8) -> u8 {
+        let row = (tile_offset + y as u16 * 2) as usize;
+        let bit = 7 - x;
+        let low = (self.vram[row] >> bit) & 1;
+        let high = (self.vram[row + 1] >> bit) & 1;
+        (high << 1) | low
+    }
+
+    /// VRAM is inaccessible to the CPU while the PPU is drawing
+    fn vram_accessible(&self) -> bool {
+        self.mode != Mode::Drawing
+    }
+
+    /// OAM is inaccessible to the CPU while the PPU is scanning it or drawing
+    fn oam_accessible(&self) -> bool {
+        !matches!(self.mode, Mode::OamScan | Mode::Drawing)
+    }
+
+    pub(crate) fn read_vram(&self, address: u16) -> u8 {
+        if !self.vram_accessible() {
+            return INACCESSIBLE;
+        }
+        self.vram[(address - VRAM_START) as usize]
+    }
+
+    pub(crate) fn write_vram(&mut self, address: u16, byte: u8) {
+        if self.vram_accessible() {
+            self.vram[(address - VRAM_START) as usize] = byte;
+        }
+    }
+
+    pub(crate) fn read_oam(&self, address: u16) -> u8 {
+        if !self.oam_accessible() {
+            return INACCESSIBLE;
+        }
+        self.oam[(address - OAM_START) as usize]
+    }
+
+    pub(crate) fn write_oam(&mut self, address: u16, byte: u8) {
+        if self.oam_accessible() {
+            self.oam[(address - OAM_START) as usize] = byte;
+        }
+    }
+
+    /// OAM DMA writes regardless of the mode
+    pub(crate) fn write_oam_dma(&mut self, offset: u8, byte: u8) {
+        self.oam[offset as usize] = byte;
+    }
+
+    pub(crate) fn read_register(&self, address: u16) -> u8 {
+        match address {
+            LCD_CONTROL_ADDRESS => self.control,
+            LCD_STATUS_ADDRESS => {
+                let mut status = self.status | LCD_STATUS_UNUSED_BITS | self.mode as u8;
+                if self.ly == self.ly_compare {
+                    status |= LY_COMPARE_FLAG_BIT;
+                }
+                status
+            }
+            SCROLL_Y_ADDRESS => self.scroll_y,
+            SCROLL_X_ADDRESS => self.scroll_x,
+            LY_ADDRESS => self.ly,
+            LY_COMPARE_ADDRESS => self.ly_compare,
+            BG_PALETTE_ADDRESS => self.bg_palette,
+            OBJECT_PALETTE_0_ADDRESS => self.object_palettes[0],
+            OBJECT_PALETTE_1_ADDRESS => self.object_palettes[1],
+            WINDOW_Y_ADDRESS => self.window_y,
+            _ => self.window_x,
+        }
+    }
+
+    pub(crate) fn write_register(&mut self, address: u16, byte: u8) {
+        match address {
+            LCD_CONTROL_ADDRESS => {
+                let was_enabled = self.lcd_enabled();
+                self.control = byte;
+                if was_enabled && !self.lcd_enabled() {
+                    // Turning the LCD off resets LY and the mode, the screen turns blank
+                    self.ly = 0;
+                    self.dot = 0;
+                    self.mode = Mode::HBlank;
+                    self.window_y_triggered = false;
+                    self.window_line = 0;
+                    self.framebuffer.fill(0);
+                    self.frame_ready = true;
+                } else if !was_enabled && self.lcd_enabled() {
+                    self.mode = Mode::OamScan;
+                }
+            }
+            LCD_STATUS_ADDRESS => self.status = byte & LCD_STATUS_WRITABLE_BITS,
+            SCROLL_Y_ADDRESS => self.scroll_y = byte,
+            SCROLL_X_ADDRESS => self.scroll_x = byte,
+            // LY is read only
+            LY_ADDRESS => {}
+            LY_COMPARE_ADDRESS => self.ly_compare = byte,
+            BG_PALETTE_ADDRESS => self.bg_palette = byte,
+            OBJECT_PALETTE_0_ADDRESS => self.object_palettes[0] = byte,
+            OBJECT_PALETTE_1_ADDRESS => self.object_palettes[1] = byte,
+            WINDOW_Y_ADDRESS => self.window_y = byte,
+            _ => self.window_x = byte,
+        }
+    }
+}
+
+/// Maps a color index to a shade through a palette register, which holds 2 bits per color
+fn shade(palette: u8, color: u8) -> u8 {
+    (palette >> (color * 2)) & 0b11
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: u32 = DOTS_PER_LINE as u32;
+
+    fn enabled_ppu() -> Ppu {
+        let mut ppu = Ppu::default();
+        ppu.write_register(LCD_CONTROL_ADDRESS, LCD_ENABLE_BIT);
+        ppu
+    }
+
+    /// Ticks for the given dots and returns all requested interrupts
+    fn tick_dots(ppu: &mut Ppu, dots: u32) -> Vec<PpuInterrupts> {
+        (0..dots / u32::from(DOTS_PER_M_CYCLE))
+            .map(|_| ppu.tick())
+            .filter(|interrupts| *interrupts != PpuInterrupts::default())
+            .collect()
+    }
+
+    #[test]
+    fn mode_timing() {
+        let mut ppu = enabled_ppu();
+        assert_eq!(ppu.mode, Mode::OamScan);
+        tick_dots(&mut ppu, 76);
+        assert_eq!(ppu.mode, Mode::OamScan);
+        tick_dots(&mut ppu, 4);
+        assert_eq!(ppu.mode, Mode::Drawing);
+        tick_dots(&mut ppu, 172);
+        assert_eq!(ppu.mode, Mode::HBlank);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 0);
+        tick_dots(&mut ppu, 204);
+        assert_eq!(ppu.mode, Mode::OamScan);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 1);
+    }
+
+    #[test]
+    fn vblank() {
+        let mut ppu = enabled_ppu();
+        let interrupts = tick_dots(&mut ppu, 144 * LINE);
+        assert_eq!(
+            interrupts,
+            [PpuInterrupts {
+                vblank: true,
+                stat: false
+            }]
+        );
+        assert_eq!(ppu.read_register(LY_ADDRESS), 144);
+        assert_eq!(ppu.mode, Mode::VBlank);
+        assert!(ppu.take_frame());
+        assert!(!ppu.take_frame());
+
+        tick_dots(&mut ppu, 9 * LINE);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 153);
+        assert_eq!(ppu.mode, Mode::VBlank);
+        tick_dots(&mut ppu, LINE);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 0);
+        assert_eq!(ppu.mode, Mode::OamScan);
+    }
+
+    #[test]
+    fn frame_length() {
+        let mut ppu = enabled_ppu();
+        let m_cycles = T_CYCLES_PER_FRAME / DOTS_PER_M_CYCLE as u64;
+        let vblanks = (0..2 * m_cycles).filter(|_| ppu.tick().vblank).count();
+        assert_eq!(vblanks, 2);
+        assert_eq!((ppu.read_register(LY_ADDRESS), ppu.dot), (0, 0));
+    }
+
+    #[test]
+    fn stat_interrupts() {
+        let mut ppu = enabled_ppu();
+        ppu.write_register(LY_COMPARE_ADDRESS, 2);
+        ppu.write_register(LCD_STATUS_ADDRESS, LY_COMPARE_SELECT_BIT);
+        let interrupts = tick_dots(&mut ppu, 2 * LINE);
+        assert_eq!(interrupts.len(), 1);
+        assert!(interrupts[0].stat);
+        assert_eq!(ppu.read_register(LCD_STATUS_ADDRESS), 0b1100_0110);
+
+        // The line stays high, so the HBlank of the same line doesn't request another interrupt
+        ppu.write_register(
+            LCD_STATUS_ADDRESS,
+            LY_COMPARE_SELECT_BIT | HBLANK_SELECT_BIT,
+        );
+        assert_eq!(tick_dots(&mut ppu, LINE - 4), []);
+        // But every following HBlank does
+        let interrupts = tick_dots(&mut ppu, 10 * LINE);
+        assert_eq!(interrupts.len(), 10);
+    }
+
+    #[test]
+    fn cpu_access_is_blocked_while_in_use() {
+        let mut ppu = Ppu::default();
+        ppu.write_vram(0x8000, 0x42);
+        ppu.write_oam(0xFE00, 0x24);
+        ppu.write_register(LCD_CONTROL_ADDRESS, LCD_ENABLE_BIT);
+
+        assert_eq!(ppu.read_vram(0x8000), 0x42);
+        assert_eq!(ppu.read_oam(0xFE00), INACCESSIBLE, "OAM scan");
+        tick_dots(&mut ppu, OAM_SCAN_DOTS.into());
+        ppu.write_vram(0x8000, 0x11);
+        assert_eq!(ppu.read_vram(0x8000), INACCESSIBLE, "Drawing");
+        assert_eq!(ppu.read_oam(0xFE00), INACCESSIBLE, "Drawing");
+        tick_dots(&mut ppu, DRAWING_DOTS.into());
+        assert_eq!(ppu.read_vram(0x8000), 0x42);
+        assert_eq!(ppu.read_oam(0xFE00), 0x24);
+    }
+
+    #[test]
+    fn turning_lcd_off_resets_ly() {
+        let mut ppu = enabled_ppu();
+        tick_dots(&mut ppu, 10 * LINE + 100);
+        ppu.write_register(LCD_CONTROL_ADDRESS, 0);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 0);
+        assert_eq!(ppu.read_register(LCD_STATUS_ADDRESS), 0b1000_0100);
+        assert_eq!(tick_dots(&mut ppu, LINE), []);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 0);
+    }
+
+    /// Fills a tile with a single color index
+    fn fill_tile(ppu: &mut Ppu, tile_offset: u16, color: u8) {
+        let row = [
+            if color & 1 != 0 { 0xFF } else { 0x00 },
+            if color & 2 != 0 { 0xFF } else { 0x00 },
+        ];
+        for y in 0..TILE_WIDTH as usize {
+            let offset = tile_offset as usize + y * 2;
+            ppu.vram[offset..offset + 2].copy_from_slice(&row);
+        }
+    }
+
+    fn render(ppu: &mut Ppu, ly: u8) -> &[u8] {
+        ppu.ly = ly;
+        ppu.start_drawing();
+        ppu.render_line();
+        let start = ly as usize * SCREEN_WIDTH;
+        &ppu.framebuffer[start..start + SCREEN_WIDTH]
+    }
+
+    /// Background with the identity palette
+    fn background_ppu(control: u8) -> Ppu {
+        Ppu {
+            control: control | BG_WINDOW_ENABLE_BIT,
+            bg_palette: 0b11_10_01_00,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tile_data_addressing() {
+        let mut ppu = background_ppu(TILE_DATA_BIT);
+        fill_tile(&mut ppu, TILE_SIZE, 1);
+        fill_tile(&mut ppu, SIGNED_TILE_DATA + TILE_SIZE, 2);
+        fill_tile(&mut ppu, SIGNED_TILE_DATA - TILE_SIZE, 3);
+        ppu.vram[LOW_TILE_MAP as usize] = 0x01;
+        ppu.vram[LOW_TILE_MAP as usize + 1] = 0xFF;
+
+        // Unsigned from 0x8000: tile 0xFF is the last one below 0x9000
+        assert_eq!(
+            render(&mut ppu, 0)[..16],
+            [1, 1, 1, 1, 1, 1, 1, 1, 3, 3, 3, 3, 3, 3, 3, 3]
+        );
+        // Signed from 0x9000
+        ppu.control &= !TILE_DATA_BIT;
+        assert_eq!(
+            render(&mut ppu, 0)[..16],
+            [2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 3]
+        );
+    }
+
+    #[test]
+    fn tile_rows() {
+        let mut ppu = background_ppu(TILE_DATA_BIT);
+        // Row 1 of tile 0, the first byte holds the low bits
+        ppu.vram[2..4].copy_from_slice(&[0b1010_0101, 0b1100_0011]);
+        assert_eq!(render(&mut ppu, 1)[..8], [3, 2, 1, 0, 0, 1, 2, 3]);
+        assert_eq!(render(&mut ppu, 0)[..8], [0; 8]);
+    }
+
+    #[test]
+    fn scrolling_wraps_around() {
+        let mut ppu = background_ppu(TILE_DATA_BIT | BG_TILE_MAP_BIT);
+        fill_tile(&mut ppu, TILE_SIZE, 3);
+        // Bottom right tile of the map
+        ppu.vram[HIGH_TILE_MAP as usize + 32 * 32 - 1] = 1;
+        ppu.scroll_x = 252;
+        ppu.scroll_y = 252;
+
+        assert_eq!(render(&mut ppu, 3)[..6], [3, 3, 3, 3, 0, 0]);
+        assert_eq!(render(&mut ppu, 4)[..6], [0; 6]);
+    }
+
+    #[test]
+    fn palette() {
+        let mut ppu = background_ppu(TILE_DATA_BIT);
+        ppu.vram[0..2].copy_from_slice(&[0b0101_0101, 0b0011_0011]);
+        ppu.bg_palette = 0b00_01_10_11;
+        assert_eq!(render(&mut ppu, 0)[..4], [3, 2, 1, 0]);
+
+        ppu.control &= !BG_WINDOW_ENABLE_BIT;
+        assert_eq!(render(&mut ppu, 0)[..4], [3; 4], "Blank with color index 0");
+    }
+
+    #[test]
+    fn window() {
+        let mut ppu = background_ppu(TILE_DATA_BIT | WINDOW_ENABLE_BIT | WINDOW_TILE_MAP_BIT);
+        fill_tile(&mut ppu, TILE_SIZE, 1);
+        fill_tile(&mut ppu, 2 * TILE_SIZE, 2);
+        // Window map: tile 1 in the first row, tile 2 in the second
+        ppu.vram[HIGH_TILE_MAP as usize] = 1;
+        ppu.vram[HIGH_TILE_MAP as usize + 32] = 2;
+        ppu.window_y = 2;
+        ppu.window_x = 100 + WINDOW_X_OFFSET;
+
+        assert_eq!(render(&mut ppu, 1)[100], 0, "Above WY");
+        let line = render(&mut ppu, 2);
+        assert_eq!(line[99..109], [0, 1, 1, 1, 1, 1, 1, 1, 1, 0]);
+
+        // The window line counter only advances on lines with the window
+        for ly in 3..9 {
+            render(&mut ppu, ly);
+        }
+        ppu.control &= !WINDOW_ENABLE_BIT;
+        assert_eq!(render(&mut ppu, 9)[100], 0);
+        ppu.control |= WINDOW_ENABLE_BIT;
+        assert_eq!(render(&mut ppu, 10)[100], 1, "Window line 7");
+        assert_eq!(render(&mut ppu, 11)[100], 2, "Window line 8");
+
+        // WX below 7 cuts off the left of the window
+        ppu.window_x = 0;
+        assert_eq!(render(&mut ppu, 12)[..2], [2, 0]);
+        // WX 167 and above hides it
+        ppu.window_x = 167;
+        assert_eq!(render(&mut ppu, 13)[0], 0);
+        ppu.window_x = 166;
+        assert_eq!(render(&mut ppu, 14)[159], 2);
+    }
+
+    fn set_object(ppu: &mut Ppu, index: usize, x: u8, y: u8, tile: u8, attributes: u8) {
+        let offset = index * OBJECT_SIZE;
+        ppu.oam[offset..offset + OBJECT_SIZE].copy_from_slice(&[
+            y + OBJECT_Y_OFFSET,
+            x + OBJECT_X_OFFSET,
+            tile,
+            attributes,
+        ]);
+    }
+
+    /// Objects on a background of color 2, with identity palettes
+    fn object_ppu(control: u8) -> Ppu {
+        let mut ppu = background_ppu(TILE_DATA_BIT | OBJECT_ENABLE_BIT | control);
+        ppu.object_palettes = [0b11_10_01_00; 2];
+        fill_tile(&mut ppu, 0, 2);
+        ppu
+    }
+
+    #[test]
+    fn objects() {
+        let mut ppu = object_ppu(0);
+        fill_tile(&mut ppu, TILE_SIZE, 1);
+        set_object(&mut ppu, 0, 10, 0, 1, 0);
+        set_object(&mut ppu, 1, 20, 0, 1, OBJECT_PALETTE_BIT);
+        ppu.object_palettes[1] = 0b00_00_11_00;
+
+        let line = render(&mut ppu, 0);
+        assert_eq!(line[9..19], [2, 1, 1, 1, 1, 1, 1, 1, 1, 2]);
+        assert_eq!(line[20], 3, "OBP1");
+        assert_eq!(render(&mut ppu, 8)[10], 2, "Below the object");
+
+        // Color 0 is transparent
+        fill_tile(&mut ppu, TILE_SIZE, 0);
+        assert_eq!(render(&mut ppu, 0)[10], 2);
+
+        ppu.control &= !OBJECT_ENABLE_BIT;
+        fill_tile(&mut ppu, TILE_SIZE, 1);
+        assert_eq!(render(&mut ppu, 0)[10], 2, "Objects disabled");
+    }
+
+    #[test]
+    fn partially_off_screen_objects() {
+        let mut ppu = object_ppu(0);
+        fill_tile(&mut ppu, TILE_SIZE, 1);
+        ppu.oam[..OBJECT_SIZE].copy_from_slice(&[12, 4, 1, 0]);
+        assert_eq!(render(&mut ppu, 3)[..5], [1, 1, 1, 1, 2]);
+        assert_eq!(render(&mut ppu, 4)[0], 2);
+    }
+
+    #[test]
+    fn flipped_objects() {
+        let mut ppu = object_ppu(0);
+        // Only the top left pixel is set
+        ppu.vram[16..18].copy_from_slice(&[0x80, 0x80]);
+        set_object(&mut ppu, 0, 0, 0, 1, 0);
+        set_object(&mut ppu, 1, 10, 0, 1, X_FLIP_BIT);
+        set_object(&mut ppu, 2, 20, 0, 1, Y_FLIP_BIT);
+        set_object(&mut ppu, 3, 30, 0, 1, X_FLIP_BIT | Y_FLIP_BIT);
+
+        let line = render(&mut ppu, 0);
+        assert_eq!([line[0], line[17], line[20], line[37]], [3, 3, 2, 2]);
+        let line = render(&mut ppu, 7);
+        assert_eq!([line[0], line[17], line[20], line[37]], [2, 2, 3, 3]);
+    }
+
+    #[test]
+    fn tall_objects() {
+        let mut ppu = object_ppu(OBJECT_SIZE_BIT);
+        fill_tile(&mut ppu, 2 * TILE_SIZE, 1);
+        fill_tile(&mut ppu, 3 * TILE_SIZE, 3);
+        // Bit 0 of the tile index is ignored
+        set_object(&mut ppu, 0, 0, 0, 3, 0);
+        set_object(&mut ppu, 1, 10, 0, 2, Y_FLIP_BIT);
+
+        let line = render(&mut ppu, 0);
+        assert_eq!([line[0], line[10]], [1, 3]);
+        let line = render(&mut ppu, 15);
+        assert_eq!([line[0], line[10]], [3, 1]);
+        let line = render(&mut ppu, 16);
+        assert_eq!([line[0], line[10]], [2, 2]);
+    }
+
+    #[test]
+    fn ten_objects_per_line() {
+        let mut ppu = object_ppu(0);
+        fill_tile(&mut ppu, TILE_SIZE, 1);
+        // Objects off screen horizontally count as well
+        ppu.oam[..OBJECT_SIZE].copy_from_slice(&[OBJECT_Y_OFFSET, 0, 1, 0]);
+        for index in 1..12 {
+            set_object(&mut ppu, index, index as u8 * 10, 2, 1, 0);
+        }
+
+        assert_eq!(render(&mut ppu, 0)[..20], [2; 20]);
+        let line = render(&mut ppu, 2);
+        let drawn: Vec<_> = (1..12).map(|index| line[index * 10]).collect();
+        assert_eq!(drawn, [1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2]);
+    }
+
+    #[test]
+    fn object_priority() {
+        let mut ppu = object_ppu(0);
+        fill_tile(&mut ppu, TILE_SIZE, 1);
+        fill_tile(&mut ppu, 2 * TILE_SIZE, 3);
+        // Left half of tile 3 is transparent
+        for row in 0..8 {
+            ppu.vram[3 * 16 + row * 2] = 0x0F;
+        }
+
+        // The smaller X wins, regardless of the OAM index
+        set_object(&mut ppu, 0, 4, 0, 1, 0);
+        set_object(&mut ppu, 1, 0, 0, 2, 0);
+        assert_eq!(
+            render(&mut ppu, 0)[..13],
+            [3, 3, 3, 3, 3, 3, 3, 3, 1, 1, 1, 1, 2]
+        );
+
+        // On the same X the smaller OAM index wins
+        set_object(&mut ppu, 1, 4, 0, 2, 0);
+        assert_eq!(render(&mut ppu, 0)[4], 1);
+        set_object(&mut ppu, 0, 4, 0, 2, 0);
+        set_object(&mut ppu, 1, 4, 0, 1, 0);
+        assert_eq!(render(&mut ppu, 0)[4], 3);
+
+        // Transparent pixels show the objects below
+        set_object(&mut ppu, 0, 4, 0, 3, 0);
+        assert_eq!(render(&mut ppu, 0)[4..12], [1, 1, 1, 1, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn bg_over_object() {
+        let mut ppu = object_ppu(0);
+        fill_tile(&mut ppu, TILE_SIZE, 1);
+        fill_tile(&mut ppu, 2 * TILE_SIZE, 3);
+        // Background tile 0 has color 2, tile 3 color 0
+        ppu.vram[LOW_TILE_MAP as usize + 1] = 3;
+        set_object(&mut ppu, 0, 4, 0, 1, BG_OVER_OBJECT_BIT);
+        set_object(&mut ppu, 1, 6, 0, 2, 0);
+
+        // The object wins over objects below even where the background hides it
+        assert_eq!(
+            render(&mut ppu, 0)[..16],
+            [2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 3, 3, 0, 0]
+        );
+
+        // With the background disabled its color is 0
+        ppu.control &= !BG_WINDOW_ENABLE_BIT;
+        assert_eq!(render(&mut ppu, 0)[4], 1);
+    }
+}
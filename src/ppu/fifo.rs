@@ -0,0 +1,371 @@
+use std::collections::VecDeque;
+
+use super::{
+    BG_TILE_MAP_BIT, BG_WINDOW_ENABLE_BIT, OBJECT_ENABLE_BIT, OBJECT_X_OFFSET, Object, Ppu,
+    SCREEN_WIDTH, TILE_MAP_WIDTH, TILE_WIDTH, WINDOW_ENABLE_BIT, WINDOW_TILE_MAP_BIT,
+    WINDOW_X_OFFSET, shade,
+};
+
+/// The first tile is fetched twice at the start of mode 3, the first result is thrown away
+const INITIAL_FETCH_DOTS: u8 = 6;
+/// Reading the tile index, the low and the high byte take 2 dots each, pushing takes another
+const FETCH_DOTS: u8 = 6;
+const OBJECT_FETCH_DOTS: u8 = 6;
+/// Object fetches wait for the background fetcher to finish the tile at the object's leftmost
+/// pixel, which costs up to this many dots
+const MAX_BG_FETCH_WAIT_DOTS: u8 = 5;
+
+/// A pixel in the object FIFO
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ObjectPixel {
+    color: u8,
+    attributes: u8,
+}
+
+/// Fetches tiles of the background or the window into the background FIFO
+#[derive(Debug, Default)]
+struct Fetcher {
+    /// Dots spent on the current tile
+    dots: u8,
+    /// Tile column, for the background relative to SCX / 8
+    tile_x: u8,
+    tile: u8,
+    low: u8,
+    high: u8,
+}
+
+/// Mode 3 state of the pixel FIFO renderer
+#[derive(Debug, Default)]
+pub(super) struct PixelFifo {
+    /// Color indices of the background or window
+    bg: VecDeque<u8>,
+    /// Object pixels, the front is at `x`
+    objects: VecDeque<ObjectPixel>,
+    fetcher: Fetcher,
+    /// Screen X of the next pixel
+    x: u8,
+    /// Pixels to throw away, SCX % 8 at the start of the line or the window left of the screen
+    discard: u8,
+    /// Dots the FIFO waits for the initial fetch and object fetches
+    stall: u8,
+    /// Fetching the window instead of the background
+    window: bool,
+    /// Index into `Ppu::line_objects` of the next object to fetch
+    next_object: usize,
+    /// Background tile the last object fetch waited for, later objects on it don't have to
+    waited_tile: Option<i16>,
+}
+
+impl PixelFifo {
+    /// Resets the FIFO for mode 3, the fine scroll is latched at its start
+    pub(super) fn start_line(&mut self, scroll_x: u8) {
+        self.bg.clear();
+        self.objects.clear();
+        self.fetcher = Fetcher::default();
+        self.x = 0;
+        self.discard = scroll_x % TILE_WIDTH;
+        self.stall = INITIAL_FETCH_DOTS;
+        self.window = false;
+        self.next_object = 0;
+        self.waited_tile = None;
+    }
+}
+
+impl Ppu {
+    /// Advances mode 3 by one dot, returns whether the line is complete
+    pub(super) fn fifo_dot(&mut self) -> bool {
+        self.start_window();
+        if self.fifo.discard == 0 {
+            self.fetch_objects();
+        }
+        if self.fifo.stall > 0 {
+            self.fifo.stall -= 1;
+            return false;
+        }
+        self.step_fetcher();
+        self.shift_pixel()
+    }
+
+    /// Restarts fetching at the window once the line reaches WX
+    fn start_window(&mut self) {
+        if self.fifo.window
+            || self.control & WINDOW_ENABLE_BIT == 0
+            || !self.window_y_triggered
+            || self.fifo.x + WINDOW_X_OFFSET < self.window_x
+        {
+            return;
+        }
+        self.fifo.window = true;
+        self.fifo.bg.clear();
+        self.fifo.fetcher = Fetcher::default();
+        // WX below 7 cuts off the left of the window
+        self.fifo.discard = WINDOW_X_OFFSET.saturating_sub(self.window_x);
+    }
+
+    /// Fetches the objects starting at the current pixel into the object FIFO, which stalls the
+    /// FIFO for the time the fetches take
+    fn fetch_objects(&mut self) {
+        if self.control & OBJECT_ENABLE_BIT == 0 {
+            return;
+        }
+        while let Some(object) = self.line_objects.get(self.fifo.next_object).copied()
+            && object.x <= self.fifo.x + OBJECT_X_OFFSET
+        {
+            self.fifo.next_object += 1;
+            // Objects left of the current pixel were skipped while objects were disabled
+            if self.fifo.x + OBJECT_X_OFFSET - object.x > TILE_WIDTH {
+                continue;
+            }
+            self.fifo.stall += self.object_fetch_dots(&object);
+            self.merge_object(&object);
+        }
+    }
+
+    /// Dots an object fetch takes, including the wait for the background fetcher
+    fn object_fetch_dots(&mut self, object: &Object) -> u8 {
+        let left = object.x as i16 - OBJECT_X_OFFSET as i16;
+        let position = if self.fifo.window {
+            left + WINDOW_X_OFFSET as i16 - self.window_x as i16
+        } else {
+            left + self.scroll_x as i16
+        };
+        let tile = position.div_euclid(TILE_WIDTH as i16);
+        if self.fifo.waited_tile == Some(tile) {
+            return OBJECT_FETCH_DOTS;
+        }
+        self.fifo.waited_tile = Some(tile);
+        let column = position.rem_euclid(TILE_WIDTH as i16) as u8;
+        OBJECT_FETCH_DOTS + MAX_BG_FETCH_WAIT_DOTS.saturating_sub(column)
+    }
+
+    /// Mixes the object into the object FIFO, where it only replaces transparent pixels of the
+    /// objects fetched before it
+    fn merge_object(&mut self, object: &Object) {
+        let skip = self.fifo.x + OBJECT_X_OFFSET - object.x;
+        for column in skip..TILE_WIDTH {
+            let pixel = ObjectPixel {
+                color: self.object_color(object, column),
+                attributes: object.attributes,
+            };
+            match self.fifo.objects.get_mut((column - skip) as usize) {
+                Some(existing) if existing.color == 0 => *existing = pixel,
+                Some(_) => {}
+                None => self.fifo.objects.push_back(pixel),
+            }
+        }
+    }
+
+    /// Advances the background fetcher by one dot, it pushes a tile once the FIFO is empty
+    fn step_fetcher(&mut self) {
+        let dots = (self.fifo.fetcher.dots + 1).min(FETCH_DOTS + 1);
+        self.fifo.fetcher.dots = dots;
+        match dots {
+            2 => self.fifo.fetcher.tile = self.vram[self.fetcher_map_offset() as usize],
+            4 => self.fifo.fetcher.low = self.vram[self.fetcher_row_offset() as usize],
+            6 => self.fifo.fetcher.high = self.vram[self.fetcher_row_offset() as usize + 1],
+            _ if dots > FETCH_DOTS && self.fifo.bg.is_empty() => self.push_tile(),
+            _ => {}
+        }
+    }
+
+    /// Line within the tile map the fetcher reads from
+    fn fetcher_y(&self) -> u8 {
+        if self.fifo.window {
+            self.window_line
+        } else {
+            self.ly.wrapping_add(self.scroll_y)
+        }
+    }
+
+    fn fetcher_map_offset(&self) -> u16 {
+        let (tile_map, tile_x) = if self.fifo.window {
+            (self.tile_map(WINDOW_TILE_MAP_BIT), self.fifo.fetcher.tile_x)
+        } else {
+            let tile_x = self.scroll_x / TILE_WIDTH + self.fifo.fetcher.tile_x;
+            (self.tile_map(BG_TILE_MAP_BIT), tile_x)
+        };
+        let tile_x = tile_x as u16 % TILE_MAP_WIDTH;
+        tile_map + (self.fetcher_y() / TILE_WIDTH) as u16 * TILE_MAP_WIDTH + tile_x
+    }
+
+    fn fetcher_row_offset(&self) -> u16 {
+        self.bg_tile_offset(self.fifo.fetcher.tile) + (self.fetcher_y() % TILE_WIDTH) as u16 * 2
+    }
+
+    fn push_tile(&mut self) {
+        let enabled = self.control & BG_WINDOW_ENABLE_BIT != 0;
+        let fetcher = &mut self.fifo.fetcher;
+        for bit in (0..TILE_WIDTH).rev() {
+            let color = if enabled {
+                (((fetcher.high >> bit) & 1) << 1) | ((fetcher.low >> bit) & 1)
+            } else {
+                0
+            };
+            self.fifo.bg.push_back(color);
+        }
+        fetcher.tile_x = fetcher.tile_x.wrapping_add(1);
+        fetcher.dots = 0;
+    }
+
+    /// Shifts out one pixel to the framebuffer, returns whether the line is complete
+    fn shift_pixel(&mut self) -> bool {
+        let Some(bg_color) = self.fifo.bg.pop_front() else {
+            return false;
+        };
+        if self.fifo.discard > 0 {
+            self.fifo.discard -= 1;
+            return false;
+        }
+        let object = self
+            .fifo
+            .objects
+            .pop_front()
+            .filter(|pixel| pixel.color != 0 && self.control & OBJECT_ENABLE_BIT != 0);
+        // Palettes are applied as the pixel leaves the FIFO, so writes during mode 3 take effect
+        // in the middle of the line
+        let shade = object
+            .and_then(|pixel| self.mix_object(pixel.color, pixel.attributes, bg_color))
+            .unwrap_or_else(|| shade(self.bg_palette, bg_color));
+        self.framebuffer[self.ly as usize * SCREEN_WIDTH + self.fifo.x as usize] = shade;
+
+        self.fifo.x += 1;
+        if (self.fifo.x as usize) < SCREEN_WIDTH {
+            return false;
+        }
+        if self.fifo.window {
+            self.window_line += 1;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ppu::{
+        BG_PALETTE_ADDRESS, LCD_CONTROL_ADDRESS, LCD_ENABLE_BIT, Mode, OBJECT_SIZE,
+        OBJECT_SIZE_BIT, OBJECT_Y_OFFSET, Renderer, TILE_DATA_BIT,
+    };
+
+    fn fifo_ppu(control: u8) -> Ppu {
+        let mut ppu = Ppu::new(Renderer::PixelFifo);
+        ppu.bg_palette = 0b11_10_01_00;
+        ppu.object_palettes = [0b11_10_01_00; 2];
+        ppu.write_register(LCD_CONTROL_ADDRESS, LCD_ENABLE_BIT | control);
+        ppu
+    }
+
+    /// Ticks until the next mode 3 and returns how many dots it took
+    fn drawing_dots(ppu: &mut Ppu) -> u16 {
+        while ppu.mode != Mode::Drawing {
+            ppu.tick_dot();
+        }
+        let mut dots = 0;
+        while ppu.mode == Mode::Drawing {
+            ppu.tick_dot();
+            dots += 1;
+        }
+        dots
+    }
+
+    fn set_object(ppu: &mut Ppu, index: usize, x: u8, attributes: u8) {
+        let offset = index * OBJECT_SIZE;
+        ppu.oam[offset..offset + OBJECT_SIZE].copy_from_slice(&[OBJECT_Y_OFFSET, x, 1, attributes]);
+    }
+
+    #[test]
+    fn mode_3_length() {
+        let control = BG_WINDOW_ENABLE_BIT | OBJECT_ENABLE_BIT;
+        let mut ppu = fifo_ppu(control);
+        assert_eq!(drawing_dots(&mut ppu), 172);
+
+        // Fine scroll
+        ppu.scroll_x = 11;
+        assert_eq!(drawing_dots(&mut ppu), 175);
+
+        // Window
+        let mut ppu = fifo_ppu(control | WINDOW_ENABLE_BIT);
+        ppu.window_x = 87;
+        assert_eq!(drawing_dots(&mut ppu), 178);
+
+        // Objects: 6 dots for the fetch, plus up to 5 for the background fetcher to finish
+        for (x, dots) in [(0, 183), (8, 183), (11, 180), (14, 178), (15, 178)] {
+            let mut ppu = fifo_ppu(control);
+            set_object(&mut ppu, 0, x, 0);
+            assert_eq!(drawing_dots(&mut ppu), dots, "Object at X {x}");
+        }
+        // Only the first object on a background tile waits for the fetcher
+        let mut ppu = fifo_ppu(control);
+        set_object(&mut ppu, 0, 8, 0);
+        set_object(&mut ppu, 1, 9, 0);
+        set_object(&mut ppu, 2, 16, 0);
+        assert_eq!(drawing_dots(&mut ppu), 172 + 11 + 6 + 11);
+
+        let mut ppu = fifo_ppu(BG_WINDOW_ENABLE_BIT);
+        set_object(&mut ppu, 0, 8, 0);
+        assert_eq!(drawing_dots(&mut ppu), 172, "Objects disabled");
+    }
+
+    /// Fills VRAM and OAM with a reproducible pseudo random scene
+    fn random_scene(ppu: &mut Ppu, seed: u32) {
+        let mut state = seed;
+        let mut next = move || {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (state >> 16) as u8
+        };
+        ppu.vram.fill_with(&mut next);
+        ppu.oam.fill_with(&mut next);
+        // Keep most objects on screen
+        for object in ppu.oam.chunks_exact_mut(OBJECT_SIZE) {
+            object[0] %= 160;
+            object[1] %= 168;
+        }
+        ppu.scroll_x = next();
+        ppu.scroll_y = next();
+        ppu.window_x = next() % 170;
+        ppu.window_y = next() % 150;
+    }
+
+    /// Runs one frame and returns it
+    fn frame(ppu: &mut Ppu) -> Vec<u8> {
+        while !ppu.take_frame() {
+            ppu.tick();
+        }
+        ppu.framebuffer().to_vec()
+    }
+
+    #[test]
+    fn matches_scanline_renderer() {
+        let control = LCD_ENABLE_BIT | WINDOW_ENABLE_BIT | BG_WINDOW_ENABLE_BIT | OBJECT_ENABLE_BIT;
+        for (seed, extra_control) in [(1, 0), (2, TILE_DATA_BIT), (3, OBJECT_SIZE_BIT)] {
+            let [scanline, fifo] = [Renderer::Scanline, Renderer::PixelFifo].map(|renderer| {
+                let mut ppu = Ppu::new(renderer);
+                random_scene(&mut ppu, seed);
+                ppu.bg_palette = 0b00_01_10_11;
+                ppu.object_palettes = [0b11_10_01_00, 0b01_11_00_10];
+                ppu.write_register(LCD_CONTROL_ADDRESS, control | extra_control);
+                frame(&mut ppu)
+            });
+            assert!(fifo.iter().any(|&shade| shade != fifo[0]), "Seed {seed}");
+            let mismatch = (0..fifo.len()).find(|&i| fifo[i] != scanline[i]);
+            assert_eq!(mismatch, None, "Seed {seed}");
+        }
+    }
+
+    #[test]
+    fn mid_line_palette_write() {
+        let mut ppu = fifo_ppu(BG_WINDOW_ENABLE_BIT | TILE_DATA_BIT);
+        // Tile 0 has color 1
+        for row in 0..TILE_WIDTH as usize {
+            ppu.vram[row * 2] = 0xFF;
+        }
+
+        while ppu.mode != Mode::Drawing || ppu.fifo.x < 80 {
+            ppu.tick_dot();
+        }
+        ppu.write_register(BG_PALETTE_ADDRESS, 0b11_10_11_00);
+        let line = &frame(&mut ppu)[..SCREEN_WIDTH];
+        assert_eq!(line[..80], [1; 80]);
+        assert_eq!(line[80..], [3; 80]);
+    }
+}
@@ -0,0 +1,260 @@
+mod link;
+
+use std::io::Write;
+
+use link::LinkState;
+#[cfg(test)]
+pub(crate) use link::StreamLink;
+pub(crate) use link::{Link, connect, listen};
+
+pub(crate) const SERIAL_DATA_ADDRESS: u16 = 0xFF01;
+pub(crate) const SERIAL_CONTROL_ADDRESS: u16 = 0xFF02;
+
+const TRANSFER_ENABLE_BIT: u8 = 0b1000_0000;
+/// Set when this Game Boy drives the clock, otherwise the link partner does
+const INTERNAL_CLOCK_BIT: u8 = 0b0000_0001;
+/// Only bits 0 and 7 of SC are backed by hardware, the others always read as 1
+const SERIAL_CONTROL_UNUSED_BITS: u8 = 0b0111_1110;
+/// The internal clock shifts one bit on each falling edge of this DIV bit, which results in
+/// 8192 Hz
+const CLOCK_DIV_BIT: u8 = 0;
+const BITS_PER_TRANSFER: u8 = 8;
+/// Bit shifted in without a link partner, the line is pulled high
+const DISCONNECTED_BIT: u8 = 1;
+/// M-cycles between the sync points of a link cable, as long as a transfer with the internal
+/// clock. The Game Boys only see each other at sync points, which keeps both deterministic
+const LINK_SYNC_M_CYCLES: u16 = 1024;
+/// A transfer with the internal clock completes at the second sync point after it started, so it
+/// never takes less time than on hardware
+const LINK_TRANSFER_SYNCS: u8 = 2;
+
+/// Receives the bytes the Game Boy transmits over the serial port
+pub(crate) trait SerialSink {
+    fn send(&mut self, byte: u8);
+}
+
+/// Echoes the transmitted bytes to stdout
+pub(crate) struct StdoutSink;
+
+impl SerialSink for StdoutSink {
+    fn send(&mut self, byte: u8) {
+        let mut stdout = std::io::stdout();
+        // Serial output is a debugging aid, losing it is not worth stopping the emulation
+        let _ = stdout.write_all(&[byte]).and_then(|()| stdout.flush());
+    }
+}
+
+/// Whether SC waits for the link partner to clock a transfer
+fn waits_for_external_clock(control: u8) -> bool {
+    control & TRANSFER_ENABLE_BIT != 0 && control & INTERNAL_CLOCK_BIT == 0
+}
+
+/// SB (0xFF01) and SC (0xFF02). Without a link partner, transfers with the internal clock shift in
+/// 1s and transfers with the external clock never finish
+#[derive(Default)]
+pub(crate) struct Serial {
+    /// SB, shifted out from the top while the bits received are shifted in from the bottom
+    data: u8,
+    /// SC
+    control: u8,
+    /// Bits left in the running transfer
+    remaining_bits: u8,
+    /// DIV bit that clocks the transfer, as of the last tick
+    div_bit: bool,
+    sink: Option<Box<dyn SerialSink>>,
+    link: Option<Box<dyn Link>>,
+    /// M-cycles since the last sync point of the link
+    link_m_cycles: u16,
+    /// Sync points left until the transfer with the internal clock completes over the link
+    link_transfer_syncs: u8,
+}
+
+impl Serial {
+    pub(crate) fn set_sink(&mut self, sink: Box<dyn SerialSink>) {
+        self.sink = Some(sink);
+    }
+
+    /// Connects the serial port to another Game Boy, which must connect at the same M-cycle
+    pub(crate) fn set_link(&mut self, link: Box<dyn Link>) {
+        self.link = Some(link);
+    }
+
+    /// False once the link partner disconnected
+    pub(crate) fn is_linked(&self) -> bool {
+        self.link.is_some()
+    }
+
+    /// Advances by one machine cycle, returns whether the Serial interrupt is requested. `div` is
+    /// the value of DIV, which drives the internal clock
+    pub(crate) fn tick(&mut self, div: u8) -> bool {
+        if self.link.is_some() {
+            return self.tick_link();
+        }
+        let div_bit = div >> CLOCK_DIV_BIT & 1 != 0;
+        let falling_edge = self.div_bit && !div_bit;
+        self.div_bit = div_bit;
+        if !falling_edge || self.remaining_bits == 0 || self.control & INTERNAL_CLOCK_BIT == 0 {
+            return false;
+        }
+        self.data = self.data << 1 | DISCONNECTED_BIT;
+        self.remaining_bits -= 1;
+        if self.remaining_bits > 0 {
+            return false;
+        }
+        self.control &= !TRANSFER_ENABLE_BIT;
+        true
+    }
+
+    /// Transfers bytes at the sync points of the link. The master receives the byte its partner
+    /// held at the sync point where the transfer completes, or 0xFF if the partner was not waiting
+    /// for it
+    fn tick_link(&mut self) -> bool {
+        self.link_m_cycles += 1;
+        if self.link_m_cycles < LINK_SYNC_M_CYCLES {
+            return false;
+        }
+        self.link_m_cycles = 0;
+        self.link_transfer_syncs = self.link_transfer_syncs.saturating_sub(1);
+        let state = LinkState {
+            data: self.data,
+            control: self.control,
+            transfer_due: self.link_transfer_syncs == 0
+                && self.control & TRANSFER_ENABLE_BIT != 0
+                && self.control & INTERNAL_CLOCK_BIT != 0,
+        };
+        let Some(Ok(peer)) = self.link.as_mut().map(|link| link.exchange(state)) else {
+            // The partner went away, continue as if the cable was pulled
+            self.link = None;
+            return false;
+        };
+        if state.transfer_due {
+            self.data = if waits_for_external_clock(peer.control) {
+                peer.data
+            } else {
+                u8::MAX
+            };
+        } else if peer.transfer_due && waits_for_external_clock(state.control) {
+            self.data = peer.data;
+        } else {
+            return false;
+        }
+        self.control &= !TRANSFER_ENABLE_BIT;
+        true
+    }
+
+    pub(crate) fn read(&self, address: u16) -> u8 {
+        match address {
+            SERIAL_DATA_ADDRESS => self.data,
+            _ => self.control | SERIAL_CONTROL_UNUSED_BITS,
+        }
+    }
+
+    pub(crate) fn write(&mut self, address: u16, byte: u8) {
+        match address {
+            SERIAL_DATA_ADDRESS => self.data = byte,
+            _ => {
+                self.control = byte & !SERIAL_CONTROL_UNUSED_BITS;
+                if self.control & TRANSFER_ENABLE_BIT != 0 {
+                    self.remaining_bits = BITS_PER_TRANSFER;
+                    self.link_transfer_syncs = LINK_TRANSFER_SYNCS;
+                    if let Some(sink) = self.sink.as_mut() {
+                        sink.send(self.data);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Keeps the transmitted bytes, clones share them so they can be read while the CPU owns one
+    #[derive(Debug, Default, Clone)]
+    pub(crate) struct CaptureSink(Rc<RefCell<Vec<u8>>>);
+
+    impl CaptureSink {
+        pub(crate) fn bytes(&self) -> Vec<u8> {
+            self.0.borrow().clone()
+        }
+
+        /// The transmitted bytes as text, how test ROMs report their results
+        pub(crate) fn text(&self) -> String {
+            String::from_utf8_lossy(&self.0.borrow()).into_owned()
+        }
+    }
+
+    impl SerialSink for CaptureSink {
+        fn send(&mut self, byte: u8) {
+            self.0.borrow_mut().push(byte);
+        }
+    }
+
+    /// Ticks with the DIV values the timer produces after a reset, returns the M-cycles until the
+    /// Serial interrupt
+    fn ticks_until_interrupt(serial: &mut Serial) -> Option<u16> {
+        (1..=4096u16).find(|m_cycle| serial.tick(((m_cycle * 4) >> 8) as u8))
+    }
+
+    #[test]
+    fn internal_clock_transfer() {
+        let capture = CaptureSink::default();
+        let mut serial = Serial::default();
+        serial.set_sink(Box::new(capture.clone()));
+        serial.write(SERIAL_DATA_ADDRESS, 0x42);
+        serial.write(SERIAL_CONTROL_ADDRESS, 0x81);
+        assert_eq!(capture.bytes(), [0x42]);
+        assert_eq!(serial.read(SERIAL_CONTROL_ADDRESS), 0xFF);
+
+        // 8 bits at 512 T-cycles each
+        assert_eq!(ticks_until_interrupt(&mut serial), Some(1024));
+        assert_eq!(serial.read(SERIAL_CONTROL_ADDRESS), 0x7F);
+        assert_eq!(serial.read(SERIAL_DATA_ADDRESS), 0xFF, "Nothing connected");
+    }
+
+    #[test]
+    fn external_clock_never_finishes() {
+        let mut serial = Serial::default();
+        serial.write(SERIAL_CONTROL_ADDRESS, 0x80);
+        assert_eq!(ticks_until_interrupt(&mut serial), None);
+        assert_eq!(serial.read(SERIAL_CONTROL_ADDRESS), 0xFE);
+    }
+
+    /// Partner that never starts or waits for a transfer
+    struct IdleLink;
+
+    impl Link for IdleLink {
+        fn exchange(&mut self, _state: LinkState) -> std::io::Result<LinkState> {
+            Ok(LinkState {
+                data: 0x42,
+                control: 0x00,
+                transfer_due: false,
+            })
+        }
+    }
+
+    #[test]
+    fn link_partner_not_waiting() {
+        let mut serial = Serial::default();
+        serial.set_link(Box::new(IdleLink));
+        serial.write(SERIAL_CONTROL_ADDRESS, 0x81);
+
+        // Completes at the second sync point
+        assert_eq!(ticks_until_interrupt(&mut serial), Some(2048));
+        assert_eq!(serial.read(SERIAL_DATA_ADDRESS), 0xFF);
+        assert!(serial.is_linked());
+    }
+
+    #[test]
+    fn capture_text() {
+        let mut capture = CaptureSink::default();
+        for byte in b"Passed\n" {
+            capture.send(*byte);
+        }
+        assert_eq!(capture.text(), "Passed\n");
+    }
+}
@@ -0,0 +1,133 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+/// Addresses with this prefix are paths of Unix domain sockets, all others TCP addresses
+#[cfg(unix)]
+const UNIX_SOCKET_PREFIX: &str = "unix:";
+const STATE_SIZE: usize = 3;
+
+/// Serial port registers at a sync point of the link
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LinkState {
+    /// SB
+    pub(crate) data: u8,
+    /// SC
+    pub(crate) control: u8,
+    /// Whether a transfer with the internal clock completes at this sync point
+    pub(crate) transfer_due: bool,
+}
+
+impl LinkState {
+    fn to_bytes(self) -> [u8; STATE_SIZE] {
+        [self.data, self.control, self.transfer_due as u8]
+    }
+
+    fn from_bytes(bytes: [u8; STATE_SIZE]) -> Self {
+        Self {
+            data: bytes[0],
+            control: bytes[1],
+            transfer_due: bytes[2] != 0,
+        }
+    }
+}
+
+/// Connection to the serial port of another Game Boy
+pub(crate) trait Link {
+    /// Sends the state at a sync point and waits for the state of the peer at the same point
+    fn exchange(&mut self, state: LinkState) -> io::Result<LinkState>;
+}
+
+/// Link over a socket
+pub(crate) struct StreamLink<S>(S);
+
+impl<S: Read + Write> StreamLink<S> {
+    pub(crate) fn new(stream: S) -> Self {
+        Self(stream)
+    }
+}
+
+impl<S: Read + Write> Link for StreamLink<S> {
+    fn exchange(&mut self, state: LinkState) -> io::Result<LinkState> {
+        self.0.write_all(&state.to_bytes())?;
+        self.0.flush()?;
+        let mut bytes = [0; STATE_SIZE];
+        self.0.read_exact(&mut bytes)?;
+        Ok(LinkState::from_bytes(bytes))
+    }
+}
+
+/// Waits for the other emulator to connect to `address`, which is a TCP address like
+/// `127.0.0.1:5000` or `unix:<path>`
+pub(crate) fn listen(address: &str) -> io::Result<Box<dyn Link>> {
+    #[cfg(unix)]
+    if let Some(path) = address.strip_prefix(UNIX_SOCKET_PREFIX) {
+        // A socket file left behind by an earlier run blocks binding
+        if std::fs::metadata(path).is_ok() {
+            std::fs::remove_file(path)?;
+        }
+        let (stream, _) = UnixListener::bind(path)?.accept()?;
+        return Ok(Box::new(StreamLink::new(stream)));
+    }
+    let (stream, _) = TcpListener::bind(address)?.accept()?;
+    // Each sync point waits for a round trip, Nagle's algorithm would delay it
+    stream.set_nodelay(true)?;
+    Ok(Box::new(StreamLink::new(stream)))
+}
+
+/// Connects to the other emulator listening on `address`, see `listen`
+pub(crate) fn connect(address: &str) -> io::Result<Box<dyn Link>> {
+    #[cfg(unix)]
+    if let Some(path) = address.strip_prefix(UNIX_SOCKET_PREFIX) {
+        return Ok(Box::new(StreamLink::new(UnixStream::connect(path)?)));
+    }
+    let stream = TcpStream::connect(address)?;
+    stream.set_nodelay(true)?;
+    Ok(Box::new(StreamLink::new(stream)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exchange_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let state = LinkState {
+            data: 0x42,
+            control: 0x81,
+            transfer_due: true,
+        };
+        let peer = std::thread::spawn(move || {
+            let mut link = connect(&address).unwrap();
+            link.exchange(LinkState {
+                data: 0x24,
+                control: 0x80,
+                transfer_due: false,
+            })
+            .unwrap()
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let received = StreamLink::new(stream).exchange(state).unwrap();
+
+        assert_eq!(received.data, 0x24);
+        assert_eq!(received.control, 0x80);
+        assert!(!received.transfer_due);
+        assert_eq!(peer.join().unwrap(), state);
+    }
+
+    #[test]
+    fn closed_link() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        drop(listener.accept().unwrap());
+        let state = LinkState {
+            data: 0x00,
+            control: 0x00,
+            transfer_due: false,
+        };
+        assert!(StreamLink::new(stream).exchange(state).is_err());
+    }
+}
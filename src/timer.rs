@@ -0,0 +1,205 @@
+pub(crate) const DIVIDER_ADDRESS: u16 = 0xFF04;
+pub(crate) const TIMER_COUNTER_ADDRESS: u16 = 0xFF05;
+pub(crate) const TIMER_MODULO_ADDRESS: u16 = 0xFF06;
+pub(crate) const TIMER_CONTROL_ADDRESS: u16 = 0xFF07;
+
+const T_CYCLES_PER_M_CYCLE: u16 = 4;
+
+const TIMER_ENABLE_BIT: u8 = 0b100;
+const CLOCK_SELECT_MASK: u8 = 0b011;
+/// Only the lower 3 bits of TAC are backed by hardware, the others always read as 1
+const TIMER_CONTROL_UNUSED_BITS: u8 = 0b1111_1000;
+/// Bit of the internal counter whose falling edge increments TIMA, indexed by the clock select
+/// bits of TAC. Results in 4096 Hz, 262144 Hz, 65536 Hz and 16384 Hz
+const CLOCK_SELECT_COUNTER_BITS: [u8; 4] = [9, 3, 5, 7];
+
+/// What happens to TIMA after it overflowed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Reload {
+    #[default]
+    None,
+    /// TIMA overflowed and reads 0x00 for one machine cycle before TMA is loaded
+    Pending,
+    /// TMA was loaded into TIMA during the last machine cycle, writes to TIMA are ignored and
+    /// writes to TMA reach TIMA as well
+    Reloading,
+}
+
+/// DIV (0xFF04), TIMA (0xFF05), TMA (0xFF06) and TAC (0xFF07)
+#[derive(Default)]
+pub(crate) struct Timer {
+    /// Counts T-cycles, DIV is its upper byte
+    counter: u16,
+    /// TIMA
+    counter_register: u8,
+    /// TMA, loaded into TIMA when it overflows
+    modulo: u8,
+    /// TAC
+    control: u8,
+    reload: Reload,
+}
+
+impl Timer {
+    /// Advances the timer by one machine cycle, returns whether the Timer interrupt is requested
+    pub(crate) fn tick(&mut self) -> bool {
+        let mut interrupt = false;
+        self.reload = match self.reload {
+            Reload::Pending => {
+                self.counter_register = self.modulo;
+                interrupt = true;
+                Reload::Reloading
+            }
+            Reload::None | Reload::Reloading => Reload::None,
+        };
+        let signal = self.signal();
+        self.counter = self.counter.wrapping_add(T_CYCLES_PER_M_CYCLE);
+        self.increment_on_falling_edge(signal);
+        interrupt
+    }
+
+    /// The selected counter bit ANDed with the enable bit, TIMA counts its falling edges
+    fn signal(&self) -> bool {
+        let bit = CLOCK_SELECT_COUNTER_BITS[(self.control & CLOCK_SELECT_MASK) as usize];
+        self.control & TIMER_ENABLE_BIT != 0 && self.counter >> bit & 1 != 0
+    }
+
+    /// Besides counting, resetting DIV or changing TAC can cause a falling edge as well
+    fn increment_on_falling_edge(&mut self, old_signal: bool) {
+        if !old_signal || self.signal() {
+            return;
+        }
+        let (counter_register, overflow) = self.counter_register.overflowing_add(1);
+        self.counter_register = counter_register;
+        if overflow {
+            self.reload = Reload::Pending;
+        }
+    }
+
+    /// Sets the internal counter without the falling edges a change can cause, for starting in the
+    /// state the boot ROM leaves behind
+    pub(crate) fn set_counter(&mut self, counter: u16) {
+        self.counter = counter;
+    }
+
+    pub(crate) fn read(&self, address: u16) -> u8 {
+        match address {
+            DIVIDER_ADDRESS => (self.counter >> 8) as u8,
+            TIMER_COUNTER_ADDRESS => self.counter_register,
+            TIMER_MODULO_ADDRESS => self.modulo,
+            _ => self.control | TIMER_CONTROL_UNUSED_BITS,
+        }
+    }
+
+    pub(crate) fn write(&mut self, address: u16, byte: u8) {
+        let signal = self.signal();
+        match address {
+            // Any write resets the whole internal counter
+            DIVIDER_ADDRESS => self.counter = 0,
+            TIMER_COUNTER_ADDRESS => match self.reload {
+                // Writing during the cycle after the overflow cancels the reload
+                Reload::Pending => {
+                    self.reload = Reload::None;
+                    self.counter_register = byte;
+                }
+                Reload::Reloading => {}
+                Reload::None => self.counter_register = byte,
+            },
+            TIMER_MODULO_ADDRESS => {
+                self.modulo = byte;
+                if self.reload == Reload::Reloading {
+                    self.counter_register = byte;
+                }
+            }
+            _ => self.control = byte & !TIMER_CONTROL_UNUSED_BITS,
+        }
+        self.increment_on_falling_edge(signal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(timer: &mut Timer, m_cycles: usize) -> usize {
+        (0..m_cycles).filter(|_| timer.tick()).count()
+    }
+
+    #[test]
+    fn divider_counts_t_cycles() {
+        let mut timer = Timer::default();
+        tick(&mut timer, 63);
+        assert_eq!(timer.read(DIVIDER_ADDRESS), 0);
+        tick(&mut timer, 1);
+        assert_eq!(timer.read(DIVIDER_ADDRESS), 1);
+        timer.write(DIVIDER_ADDRESS, 0x42);
+        assert_eq!(timer.read(DIVIDER_ADDRESS), 0);
+    }
+
+    #[test]
+    fn frequencies() {
+        for (control, m_cycles) in [(0b100, 256), (0b101, 4), (0b110, 16), (0b111, 64)] {
+            let mut timer = Timer::default();
+            timer.write(TIMER_CONTROL_ADDRESS, control);
+            tick(&mut timer, m_cycles - 1);
+            assert_eq!(timer.read(TIMER_COUNTER_ADDRESS), 0, "TAC {control:03b}");
+            tick(&mut timer, 1);
+            assert_eq!(timer.read(TIMER_COUNTER_ADDRESS), 1, "TAC {control:03b}");
+        }
+
+        let mut timer = Timer::default();
+        timer.write(TIMER_CONTROL_ADDRESS, 0b001);
+        tick(&mut timer, 16);
+        assert_eq!(timer.read(TIMER_COUNTER_ADDRESS), 0, "Timer is disabled");
+        assert_eq!(timer.read(TIMER_CONTROL_ADDRESS), 0xF9);
+    }
+
+    #[test]
+    fn overflow_reloads_after_one_cycle() {
+        let mut timer = Timer::default();
+        timer.write(TIMER_MODULO_ADDRESS, 0xAB);
+        timer.write(TIMER_COUNTER_ADDRESS, 0xFF);
+        timer.write(TIMER_CONTROL_ADDRESS, 0b101);
+        assert_eq!(tick(&mut timer, 4), 0);
+        assert_eq!(timer.read(TIMER_COUNTER_ADDRESS), 0x00);
+        assert!(timer.tick(), "Interrupt is requested with the reload");
+        assert_eq!(timer.read(TIMER_COUNTER_ADDRESS), 0xAB);
+
+        // Writes to TIMA are ignored during the reload, writes to TMA go through
+        timer.write(TIMER_COUNTER_ADDRESS, 0x12);
+        assert_eq!(timer.read(TIMER_COUNTER_ADDRESS), 0xAB);
+        timer.write(TIMER_MODULO_ADDRESS, 0x34);
+        assert_eq!(timer.read(TIMER_COUNTER_ADDRESS), 0x34);
+    }
+
+    #[test]
+    fn writing_tima_after_overflow_cancels_reload() {
+        let mut timer = Timer::default();
+        timer.write(TIMER_MODULO_ADDRESS, 0xAB);
+        timer.write(TIMER_COUNTER_ADDRESS, 0xFF);
+        timer.write(TIMER_CONTROL_ADDRESS, 0b101);
+        tick(&mut timer, 4);
+        timer.write(TIMER_COUNTER_ADDRESS, 0x12);
+        assert!(!timer.tick());
+        assert_eq!(timer.read(TIMER_COUNTER_ADDRESS), 0x12);
+    }
+
+    #[test]
+    fn falling_edges_from_writes() {
+        // Resetting DIV while the selected bit is set
+        let mut timer = Timer::default();
+        timer.write(TIMER_CONTROL_ADDRESS, 0b101);
+        tick(&mut timer, 2);
+        timer.write(DIVIDER_ADDRESS, 0);
+        assert_eq!(timer.read(TIMER_COUNTER_ADDRESS), 1);
+
+        // Disabling the timer while the selected bit is set
+        tick(&mut timer, 2);
+        timer.write(TIMER_CONTROL_ADDRESS, 0b001);
+        assert_eq!(timer.read(TIMER_COUNTER_ADDRESS), 2);
+
+        // Selecting a bit that is clear while the old one is set
+        timer.write(TIMER_CONTROL_ADDRESS, 0b101);
+        timer.write(TIMER_CONTROL_ADDRESS, 0b100);
+        assert_eq!(timer.read(TIMER_COUNTER_ADDRESS), 3);
+    }
+}
@@ -26,6 +26,9 @@ EntryPoint:
     add a, a
     ld hl, $0f0f
 
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
 SECTION "Test", WRAM0
     test: db
 
@@ -0,0 +1,140 @@
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld sp, $E000
+    ld de, wResults
+
+    ; ADD
+    ld a, $3A
+    ld b, $C6
+    add a, b
+    call StoreAF
+    ld hl, OperandData
+    ld a, $08
+    add a, [hl]
+    call StoreAF
+
+    ; ADC
+    ld a, $E1
+    ld c, $0F
+    scf
+    adc a, c
+    call StoreAF
+    ld a, $E1
+    scf
+    adc a, $1E
+    call StoreAF
+
+    ; SUB
+    ld a, $3E
+    ld c, $3E
+    sub c
+    call StoreAF
+    ld a, $3E
+    sub $0F
+    call StoreAF
+
+    ; SBC
+    ld a, $3B
+    ld h, $2A
+    scf
+    sbc a, h
+    call StoreAF
+    ld a, $3B
+    scf
+    sbc a, $4F
+    call StoreAF
+
+    ; AND
+    ld a, $5A
+    ld l, $3F
+    and l
+    call StoreAF
+    ld a, $5A
+    and $00
+    call StoreAF
+
+    ; XOR
+    ld a, $5A
+    xor a
+    call StoreAF
+    ld a, $FF
+    xor $0F
+    call StoreAF
+
+    ; OR
+    ld a, $5A
+    ld hl, OperandData
+    or [hl]
+    call StoreAF
+    xor a
+    or $00
+    call StoreAF
+
+    ; CP
+    ld a, $3C
+    ld b, $2F
+    cp b
+    call StoreAF
+    ld a, $3C
+    cp $3C
+    call StoreAF
+    ld a, $3C
+    cp $40
+    call StoreAF
+
+    ; INC
+    ld a, $FF
+    scf
+    inc a
+    call StoreAF
+    ld a, $50
+    or a
+    inc a
+    call StoreAF
+
+    ; DEC
+    ld a, $01
+    dec a
+    call StoreAF
+    xor a
+    dec a
+    call StoreAF
+
+    ; INC (HL) / DEC (HL)
+    ld hl, wScratch
+    ld [hl], $0F
+    inc [hl]
+    ld a, [hl]
+    call StoreAF
+    ld hl, wScratch
+    dec [hl]
+    ld a, [hl]
+    call StoreAF
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+; Stores A and F at [DE] and advances DE. Clobbers HL
+StoreAF:
+    push af
+    pop hl
+    ld a, h
+    ld [de], a
+    inc de
+    ld a, l
+    ld [de], a
+    inc de
+    ret
+
+OperandData:
+    db $10
+
+SECTION "Results", WRAM0[$C000]
+wResults: ds 46
+wScratch: db
@@ -0,0 +1,50 @@
+include "hardware.inc"
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld hl, wResults
+
+    ; Registers are only writable while the APU is on
+    ld a, $80
+    ldh [rNR52], a
+    ld a, $77
+    ldh [rNR50], a
+    ldh a, [rNR50]
+    ld [hl+], a
+
+    ; Channel 1 with full volume and a length of 1
+    ld a, $F0
+    ldh [rNR12], a
+    ld a, $3F
+    ldh [rNR11], a
+    ld a, $C0
+    ldh [rNR14], a
+    ldh a, [rNR52]
+    ld [hl+], a
+
+    ; The frame sequencer, clocked by DIV, lets the length expire
+.waitForLength:
+    ldh a, [rNR52]
+    and $01
+    jr nz, .waitForLength
+    ldh a, [rNR52]
+    ld [hl+], a
+
+    ; Turning the APU off clears the registers
+    xor a
+    ldh [rNR52], a
+    ldh a, [rNR50]
+    ld [hl+], a
+    ldh a, [rNR52]
+    ld [hl+], a
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+SECTION "Results", WRAM0[$C000]
+wResults: ds 5
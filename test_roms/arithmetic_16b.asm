@@ -0,0 +1,104 @@
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld sp, $E000
+    ld de, wResults
+
+    ; ADD HL, rr leaves the zero flag untouched
+    xor a
+    ld hl, $8A23
+    ld bc, $0605
+    add hl, bc
+    call StoreHLAndF
+    ld hl, $8A23
+    add hl, hl
+    call StoreHLAndF
+    push de
+    ld hl, $0FFF
+    ld de, $0001
+    or $01
+    add hl, de
+    pop de
+    call StoreHLAndF
+    ld hl, $0001
+    add hl, sp
+    call StoreHLAndF
+
+    ; INC rr / DEC rr do not touch any flags
+    ld bc, $FFFF
+    inc bc
+    ld a, c
+    ld [wIncDec], a
+    ld a, b
+    ld [wIncDec + 1], a
+    push de
+    ld de, $0000
+    dec de
+    ld a, e
+    ld [wIncDec + 2], a
+    ld a, d
+    ld [wIncDec + 3], a
+    pop de
+    ld hl, $00FF
+    inc hl
+    ld a, l
+    ld [wIncDec + 4], a
+    ld a, h
+    ld [wIncDec + 5], a
+    inc sp
+    inc sp
+    dec sp
+    ld [wIncDec + 6], sp
+    ld sp, $E000
+
+    ; LD HL, SP+e
+    ld sp, $FFF8
+    ld hl, sp+2
+    call StoreHLAndF
+    ld hl, sp-8
+    call StoreHLAndF
+
+    ; ADD SP, e
+    ld sp, $D0F8
+    add sp, $08
+    ld [wAddSp], sp
+    push af
+    pop hl
+    ld a, l
+    ld [wAddSp + 2], a
+    ld sp, $D000
+    add sp, -1
+    ld [wAddSp + 3], sp
+    push af
+    pop hl
+    ld a, l
+    ld [wAddSp + 5], a
+    ld sp, $E000
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+; Stores HL (little endian) and F at [DE] and advances DE. Clobbers A and HL
+StoreHLAndF:
+    push af
+    ld a, l
+    ld [de], a
+    inc de
+    ld a, h
+    ld [de], a
+    inc de
+    pop hl
+    ld a, l
+    ld [de], a
+    inc de
+    ret
+
+SECTION "Results", WRAM0[$C000]
+wResults: ds 18
+wIncDec: ds 8
+wAddSp: ds 6
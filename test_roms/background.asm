@@ -0,0 +1,66 @@
+include "hardware.inc"
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ; Clear VRAM while the LCD is off
+    ld hl, _VRAM
+    ld bc, $2000
+.clearVram:
+    xor a
+    ld [hl+], a
+    dec bc
+    ld a, b
+    or c
+    jr nz, .clearVram
+
+    ; Tile 1 has color 3, tile 2 color 1
+    ld hl, _VRAM + 16
+    ld b, 16
+.tile1:
+    ld a, $FF
+    ld [hl+], a
+    dec b
+    jr nz, .tile1
+    ld b, 8
+.tile2:
+    ld a, $FF
+    ld [hl+], a
+    xor a
+    ld [hl+], a
+    dec b
+    jr nz, .tile2
+
+    ; Background tile 1 at tile (1, 1), scrolled to (4, 4) on screen
+    ld a, 1
+    ld [_SCRN0 + 32 + 1], a
+    ld a, 4
+    ldh [rSCX], a
+    ldh [rSCY], a
+
+    ; Window tile 2 at the top left of the window, which starts at (80, 100)
+    ld a, 2
+    ld [_SCRN1], a
+    ld a, 80 + 7
+    ldh [rWX], a
+    ld a, 100
+    ldh [rWY], a
+
+    ld a, %11100100
+    ldh [rBGP], a
+    ld a, LCDCF_ON | LCDCF_WIN9C00 | LCDCF_WINON | LCDCF_BG8000 | LCDCF_BG9800 | LCDCF_BGON
+    ldh [rLCDC], a
+
+    ; Wait for the first frame to be drawn
+    ld a, IEF_VBLANK
+    ldh [rIE], a
+    xor a
+    ldh [rIF], a
+    halt
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
@@ -0,0 +1,68 @@
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld sp, $E000
+    ld de, wResults
+
+    ; BIT b, r keeps the carry flag
+    ld a, $80
+    scf
+    bit 7, a
+    call StoreAF
+    ld a, $80
+    bit 0, a
+    call StoreAF
+    ld b, $EF
+    and a
+    bit 4, b
+    ld a, b
+    call StoreAF
+
+    ; RES b, r / SET b, r do not touch any flags
+    ld a, $FF
+    res 0, a
+    res 7, a
+    call StoreAF
+    ld c, $00
+    xor a
+    set 3, c
+    set 6, c
+    ld a, c
+    call StoreAF
+
+    ; (HL) variants
+    ld hl, wScratch
+    ld [hl], $F0
+    set 0, [hl]
+    res 7, [hl]
+    bit 6, [hl]
+    ld a, [hl]
+    call StoreAF
+    ld hl, wScratch
+    bit 7, [hl]
+    ld a, [hl]
+    call StoreAF
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+; Stores A and F at [DE] and advances DE. Clobbers HL
+StoreAF:
+    push af
+    pop hl
+    ld a, h
+    ld [de], a
+    inc de
+    ld a, l
+    ld [de], a
+    inc de
+    ret
+
+SECTION "Results", WRAM0[$C000]
+wResults: ds 14
+wScratch: db
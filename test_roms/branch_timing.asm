@@ -0,0 +1,25 @@
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld sp, $E000
+    xor a
+    jr nz, EntryPoint
+    jr z, .relative
+.relative:
+    jp nz, EntryPoint
+    jp z, .absolute
+.absolute:
+    call nz, EntryPoint
+    call z, Subroutine
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+Subroutine:
+    ret nz
+    ret z
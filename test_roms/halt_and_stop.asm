@@ -0,0 +1,60 @@
+include "hardware.inc"
+
+SECTION "Start", ROM0[$0]
+    ; The tests start executing at $0000, skip over the interrupt vectors
+    jp $100
+
+SECTION "TimerInterrupt", ROM0[$50]
+    push af
+    ld a, $50
+    ld [hl+], a
+    pop af
+    reti
+
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld sp, $E000
+    ld hl, wLog
+    ld a, IEF_TIMER
+    ldh [rIE], a
+
+    ; HALT with IME disabled resumes without servicing the interrupt
+    halt
+    ld a, $01
+    ld [hl+], a
+
+    ; The timer is still requested, so this HALT is skipped and INC A is executed twice
+    xor a
+    halt
+    inc a
+    ld [hl+], a
+
+    ; HALT with IME enabled services the interrupt before resuming
+    xor a
+    ldh [rIF], a
+    ei
+    halt
+    ld a, $03
+    ld [hl+], a
+    di
+
+    ; STOP waits for a button press, a Joypad interrupt requested before doesn't end it
+    ld a, IEF_HILO
+    ldh [rIF], a
+    xor a
+    ldh [rP1], a
+    stop
+    ld a, $04
+    ld [hl+], a
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+SECTION "Results", WRAM0[$C000]
+wLog: ds 5
@@ -0,0 +1,15 @@
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld a, $01
+    ; $D3 is not part of the instruction set
+    db $D3
+    ld a, $02
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
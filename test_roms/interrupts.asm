@@ -0,0 +1,93 @@
+include "hardware.inc"
+
+SECTION "Start", ROM0[$0]
+    ; The tests start executing at $0000, skip over the interrupt vectors
+    jp $100
+
+; Every handler appends its vector to the log HL points to
+SECTION "VBlankInterrupt", ROM0[$40]
+    push af
+    ld a, $40
+    ld [hl+], a
+    pop af
+    reti
+
+SECTION "StatInterrupt", ROM0[$48]
+    push af
+    ld a, $48
+    ld [hl+], a
+    pop af
+    reti
+
+SECTION "TimerInterrupt", ROM0[$50]
+    push af
+    ld a, $50
+    ld [hl+], a
+    pop af
+    reti
+
+SECTION "SerialInterrupt", ROM0[$58]
+    push af
+    ld a, $58
+    ld [hl+], a
+    pop af
+    reti
+
+SECTION "JoypadInterrupt", ROM0[$60]
+    push af
+    ld a, $60
+    ld [hl+], a
+    pop af
+    reti
+
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld sp, $E000
+    ld hl, wInterruptLog
+
+    ; Requested and enabled interrupts are not serviced while IME is disabled
+    ld a, $1F
+    ldh [rIE], a
+    ld a, IEF_TIMER
+    ldh [rIF], a
+    nop
+    ld a, $01
+    ld [hl+], a
+
+    ; EI only takes effect after the following instruction
+    ld a, $02
+    ei
+    ld [hl+], a
+    di
+
+    ; Pending interrupts are serviced in priority order and acknowledged in IF
+    ld a, $1F
+    ldh [rIF], a
+    ei
+    nop
+    di
+    ldh a, [rIF]
+    ld [hl+], a
+
+    ; Interrupts not enabled in IE stay pending
+    ld a, IEF_TIMER
+    ldh [rIE], a
+    ld a, IEF_VBLANK | IEF_HILO
+    ldh [rIF], a
+    ei
+    nop
+    di
+    ldh a, [rIF]
+    ld [hl+], a
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+SECTION "Results", WRAM0[$C000]
+wInterruptLog: ds 10
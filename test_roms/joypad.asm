@@ -0,0 +1,61 @@
+include "hardware.inc"
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+; The test harness holds A and Down
+EntryPoint:
+    ld hl, wResults
+
+    ; Nothing selected, all lines read high
+    ld a, P1F_GET_NONE
+    ldh [rP1], a
+    ldh a, [rP1]
+    ld [hl+], a
+
+    ; Directions, Down pulls line 3 low
+    ld a, P1F_GET_DPAD
+    ldh [rP1], a
+    ldh a, [rP1]
+    ld [hl+], a
+
+    ; Actions, A pulls line 0 low
+    ld a, P1F_GET_BTN
+    ldh [rP1], a
+    ldh a, [rP1]
+    ld [hl+], a
+
+    ; Both groups
+    xor a
+    ldh [rP1], a
+    ldh a, [rP1]
+    ld [hl+], a
+
+    ; Selecting a group with a pressed button requests the Joypad interrupt
+    ld a, P1F_GET_NONE
+    ldh [rP1], a
+    xor a
+    ldh [rIF], a
+    ld a, P1F_GET_BTN
+    ldh [rP1], a
+    ldh a, [rIF]
+    and IEF_HILO
+    ld [hl+], a
+
+    ; Selecting it again keeps the line low, no new request
+    xor a
+    ldh [rIF], a
+    ld a, P1F_GET_BTN
+    ldh [rP1], a
+    ldh a, [rIF]
+    and IEF_HILO
+    ld [hl+], a
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+SECTION "Results", WRAM0[$C000]
+wResults: ds 6
@@ -0,0 +1,52 @@
+include "hardware.inc"
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ; The harness writes the role before running, WRAM starts out as 0
+    ld a, [wRole]
+    and a
+    ld a, $80 ; Wait for the partner's clock
+    ld de, SlaveBytes
+    jr z, .start
+    ld a, $81 ; Drive the clock
+    ld de, MasterBytes
+.start:
+    ld b, a
+    ld hl, wResults
+    ld c, 3
+
+    ; Exchange the bytes and store the ones received
+.nextByte:
+    ld a, [de]
+    inc de
+    ldh [rSB], a
+    ld a, b
+    ldh [rSC], a
+.waitForTransfer:
+    ldh a, [rSC]
+    and $80
+    jr nz, .waitForTransfer
+    ldh a, [rSB]
+    ld [hl+], a
+    dec c
+    jr nz, .nextByte
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+MasterBytes:
+    db $11, $22, $33
+SlaveBytes:
+    db $AA, $BB, $CC
+
+SECTION "Results", WRAM0[$C000]
+wResults: ds 3
+
+SECTION "Role", WRAM0[$C0FF]
+; 1 for the Game Boy driving the clock
+wRole: ds 1
@@ -0,0 +1,98 @@
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld sp, $E000
+
+    ; LD r, r'
+    ld b, $12
+    ld c, b
+    ld d, c
+    ld e, d
+    ld h, e
+    ld l, h
+    ld a, l
+    ld [wRegisterChain], a
+
+    ; LD (HL), n / LD r, (HL) / LD (HL), r
+    ld hl, wHlImmediate
+    ld [hl], $34
+    ld b, [hl]
+    ld hl, wHlRegister
+    ld [hl], b
+
+    ; LD (BC), A / LD (DE), A
+    ld bc, wBc
+    ld a, $56
+    ld [bc], a
+    ld de, wDe
+    ld a, $78
+    ld [de], a
+
+    ; LD (HL+), A / LD (HL-), A
+    ld hl, wHlIncrement
+    ld a, $9A
+    ld [hl+], a
+    ld a, $BC
+    ld [hl+], a
+    ld a, $DE
+    ld [hl-], a
+    ld a, $F0
+    ld [hl-], a
+
+    ; LD A, (BC) / LD A, (DE) / LD A, (HL+) / LD A, (HL-)
+    ld a, [bc]
+    ld [wIndirectLoads], a
+    ld a, [de]
+    ld [wIndirectLoads + 1], a
+    ld hl, wHlIncrement
+    ld a, [hl+]
+    ld [wIndirectLoads + 2], a
+    ld a, [hl-]
+    ld [wIndirectLoads + 3], a
+    ld a, [hl-]
+    ld [wIndirectLoads + 4], a
+
+    ; LD A, (nn)
+    ld a, [ConstantData]
+    ld [wMemImmediate], a
+
+    ; LDH (n), A / LDH A, (n) / LD ($FF00+C), A / LD A, ($FF00+C)
+    ld a, $42
+    ldh [$FF80], a
+    ld c, $81
+    ld a, $24
+    ld [$FF00+c], a
+    ldh a, [$FF81]
+    ld [wHighRam], a
+    ld c, $80
+    ld a, [$FF00+c]
+    ld [wHighRam + 1], a
+
+    ; LD (nn), SP / LD SP, HL
+    ld hl, $ABCD
+    ld sp, hl
+    ld [wStackPointer], sp
+    ld sp, $E000
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+ConstantData:
+    db $C3
+
+SECTION "Results", WRAM0[$C000]
+wRegisterChain: db
+wHlImmediate: db
+wHlRegister: db
+wBc: db
+wDe: db
+wHlIncrement: ds 2
+wIndirectLoads: ds 5
+wMemImmediate: db
+wHighRam: ds 2
+wStackPointer: ds 2
@@ -0,0 +1,99 @@
+include "hardware.inc"
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $147 - @, 0 ; Make room for the header
+    ; rgbfix -v leaves the cartridge type and sizes alone
+    db CART_ROM_MBC1_RAM_BAT
+    db CART_ROM_64KB
+    db CART_SRAM_32KB
+    ds $150 - @, 0
+
+EntryPoint:
+    ld hl, wResults
+
+    ; Bank 1 is mapped to $4000-$7FFF after reset
+    ld a, [$4000]
+    ld [hl+], a
+    ld a, 2
+    ld [$2000], a
+    ld a, [$4000]
+    ld [hl+], a
+    ; Any address in $2000-$3FFF selects the ROM bank
+    ld a, 3
+    ld [$3FFF], a
+    ld a, [$4000]
+    ld [hl+], a
+
+    ; Bank 0 can't be mapped to $4000-$7FFF, selecting it maps bank 1
+    xor a
+    ld [$2000], a
+    ld a, [$4000]
+    ld [hl+], a
+    ; Only the lower 5 bits are checked
+    ld a, 2
+    ld [$2000], a
+    ld a, $20
+    ld [$2000], a
+    ld a, [$4000]
+    ld [hl+], a
+
+    ; Bank numbers wrap around the 4 banks of the ROM
+    ld a, 6
+    ld [$2000], a
+    ld a, [$4000]
+    ld [hl+], a
+
+    ; RAM reads $FF while disabled
+    ld a, [$A000]
+    ld [hl+], a
+    ld a, CART_SRAM_ENABLE
+    ld [$0000], a
+    ld a, $11
+    ld [$A000], a
+
+    ; Mode 1 maps the RAM bank selected with $4000-$5FFF
+    ld a, 1
+    ld [$6000], a
+    ld [$4000], a
+    ld a, $22
+    ld [$A000], a
+    ld a, [$A000]
+    ld [hl+], a
+    xor a
+    ld [$4000], a
+    ld a, [$A000]
+    ld [hl+], a
+
+    ; Mode 0 always maps RAM bank 0
+    ld a, 1
+    ld [$4000], a
+    xor a
+    ld [$6000], a
+    ld a, [$A000]
+    ld [hl+], a
+
+    ; Any value without $A in the lower nibble disables RAM
+    ld a, $1B
+    ld [$0000], a
+    ld a, [$A000]
+    ld [hl+], a
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+; Every bank starts with its number
+SECTION "Bank1", ROMX[$4000], BANK[1]
+    db 1
+
+SECTION "Bank2", ROMX[$4000], BANK[2]
+    db 2
+
+SECTION "Bank3", ROMX[$4000], BANK[3]
+    db 3
+
+SECTION "Results", WRAM0[$C000]
+wResults:
+    ds 11
@@ -0,0 +1,60 @@
+include "hardware.inc"
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $147 - @, 0 ; Make room for the header
+    ; rgbfix -v leaves the cartridge type and sizes alone
+    db CART_ROM_MBC2_BAT
+    db CART_ROM_64KB
+    db CART_SRAM_NONE
+    ds $150 - @, 0
+
+EntryPoint:
+    ld hl, wResults
+
+    ; Address bit 8 set selects the ROM bank register
+    ld a, 3
+    ld [$2100], a
+    ld a, [$4000]
+    ld [hl+], a
+    ; Address bit 8 clear selects the RAM enable register
+    ld a, 2
+    ld [$2000], a
+    ld a, [$4000]
+    ld [hl+], a
+
+    ; Only the lower nibble of the RAM exists, the upper one reads as 1s
+    ld a, CART_SRAM_ENABLE
+    ld [$0000], a
+    ld a, $5A
+    ld [$A000], a
+    ld a, [$A000]
+    ld [hl+], a
+    ; The 512 bytes of RAM repeat throughout $A000-$BFFF
+    ld a, [$A200]
+    ld [hl+], a
+
+    ; Selecting bank 0 maps bank 1
+    xor a
+    ld [$0100], a
+    ld a, [$4000]
+    ld [hl+], a
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+; Every bank starts with its number
+SECTION "Bank1", ROMX[$4000], BANK[1]
+    db 1
+
+SECTION "Bank2", ROMX[$4000], BANK[2]
+    db 2
+
+SECTION "Bank3", ROMX[$4000], BANK[3]
+    db 3
+
+SECTION "Results", WRAM0[$C000]
+wResults:
+    ds 5
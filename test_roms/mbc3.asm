@@ -0,0 +1,94 @@
+include "hardware.inc"
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $147 - @, 0 ; Make room for the header
+    ; rgbfix -v leaves the cartridge type and sizes alone
+    db CART_ROM_MBC3_RAM_BAT_RTC
+    db CART_ROM_64KB
+    db CART_SRAM_32KB
+    ds $150 - @, 0
+
+EntryPoint:
+    ld hl, wResults
+
+    ld a, 3
+    ld [$2000], a
+    ld a, [$4000]
+    ld [hl+], a
+
+    ld a, CART_SRAM_ENABLE
+    ld [$0000], a
+
+    ; Halt the clock, so the results don't depend on the time
+    ld a, $0C
+    ld [$4000], a
+    ld a, $40
+    ld [$A000], a
+    ld a, $08
+    ld [$4000], a
+    ld a, 42
+    ld [$A000], a
+    ld a, $09
+    ld [$4000], a
+    ld a, 7
+    ld [$A000], a
+
+    ; Writing $00 and then $01 latches the clock
+    xor a
+    ld [$6000], a
+    inc a
+    ld [$6000], a
+    ld a, $08
+    ld [$4000], a
+    ld a, [$A000]
+    ld [hl+], a
+    ld a, $09
+    ld [$4000], a
+    ld a, [$A000]
+    ld [hl+], a
+    ld a, $0C
+    ld [$4000], a
+    ld a, [$A000]
+    ld [hl+], a
+
+    ; Writes only reach the latched registers with the next latch
+    ld a, $08
+    ld [$4000], a
+    ld a, 10
+    ld [$A000], a
+    ld a, [$A000]
+    ld [hl+], a
+
+    ; RAM banks
+    ld a, 1
+    ld [$4000], a
+    ld a, $55
+    ld [$A000], a
+    xor a
+    ld [$4000], a
+    ld a, [$A000]
+    ld [hl+], a
+    ld a, 1
+    ld [$4000], a
+    ld a, [$A000]
+    ld [hl+], a
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+; Every bank starts with its number
+SECTION "Bank1", ROMX[$4000], BANK[1]
+    db 1
+
+SECTION "Bank2", ROMX[$4000], BANK[2]
+    db 2
+
+SECTION "Bank3", ROMX[$4000], BANK[3]
+    db 3
+
+SECTION "Results", WRAM0[$C000]
+wResults:
+    ds 7
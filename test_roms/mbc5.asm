@@ -0,0 +1,75 @@
+include "hardware.inc"
+SECTION "Start", ROM0[$0]
+    ; The tests start executing at $0000, this jp opcode is read back from bank 0 later on
+    jp $100
+
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $147 - @, 0 ; Make room for the header
+    ; rgbfix -v leaves the cartridge type and sizes alone
+    db CART_ROM_MBC5_RAM_BAT
+    db CART_ROM_64KB
+    db CART_SRAM_128KB
+    ds $150 - @, 0
+
+EntryPoint:
+    ld hl, wResults
+
+    ld a, 3
+    ld [$2000], a
+    ld a, [$4000]
+    ld [hl+], a
+
+    ; Unlike older MBCs bank 0 can be mapped to $4000-$7FFF
+    xor a
+    ld [$2000], a
+    ld a, [$4000]
+    ld [hl+], a
+
+    ; Bit 8 of the bank number is written to $3000-$3FFF, it wraps around the 4 banks of the ROM
+    ld a, 1
+    ld [$3000], a
+    ld a, 2
+    ld [$2000], a
+    ld a, [$4000]
+    ld [hl+], a
+
+    ; 16 RAM banks
+    ld a, CART_SRAM_ENABLE
+    ld [$0000], a
+    ld a, $0F
+    ld [$4000], a
+    ld a, $F0
+    ld [$A000], a
+    xor a
+    ld [$4000], a
+    ld a, $11
+    ld [$A000], a
+    ld a, $0F
+    ld [$4000], a
+    ld a, [$A000]
+    ld [hl+], a
+    xor a
+    ld [$4000], a
+    ld a, [$A000]
+    ld [hl+], a
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+; Every bank starts with its number
+SECTION "Bank1", ROMX[$4000], BANK[1]
+    db 1
+
+SECTION "Bank2", ROMX[$4000], BANK[2]
+    db 2
+
+SECTION "Bank3", ROMX[$4000], BANK[3]
+    db 3
+
+SECTION "Results", WRAM0[$C000]
+wResults:
+    ds 5
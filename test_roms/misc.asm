@@ -0,0 +1,65 @@
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld sp, $E000
+    ld de, wResults
+
+    ; DAA after additions
+    ld a, $45
+    add a, $38
+    daa
+    call StoreAF
+    ld a, $99
+    add a, $01
+    daa
+    call StoreAF
+
+    ; DAA after subtractions
+    ld a, $83
+    sub $38
+    daa
+    call StoreAF
+    xor a
+    sub $01
+    daa
+    call StoreAF
+
+    ; CPL
+    xor a
+    ld a, $35
+    cpl
+    call StoreAF
+
+    ; SCF / CCF
+    xor a
+    scf
+    call StoreAF
+    xor a
+    scf
+    ccf
+    call StoreAF
+    ccf
+    call StoreAF
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+; Stores A and F at [DE] and advances DE. Clobbers HL
+StoreAF:
+    push af
+    pop hl
+    ld a, h
+    ld [de], a
+    inc de
+    ld a, l
+    ld [de], a
+    inc de
+    ret
+
+SECTION "Results", WRAM0[$C000]
+wResults: ds 16
@@ -0,0 +1,72 @@
+include "hardware.inc"
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld sp, $FFFE
+
+    ; Source data at $C100 holds its own offset
+    ld hl, $C100
+    xor a
+.fillSource:
+    ld [hl+], a
+    inc a
+    cp $A0
+    jr nz, .fillSource
+
+    ; The CPU can only run code from HRAM during the transfer
+    ld hl, hRunDma
+    ld de, RunDma
+    ld b, RunDmaEnd - RunDma
+.copyRoutine:
+    ld a, [de]
+    inc de
+    ld [hl+], a
+    dec b
+    jr nz, .copyRoutine
+    call hRunDma
+
+    ld hl, wResults
+    ldh a, [hOamRead]
+    ld [hl+], a
+    ldh a, [hRomRead]
+    ld [hl+], a
+    ; The LCD is off, so OAM is accessible after the transfer
+    ld a, [_OAMRAM]
+    ld [hl+], a
+    ld a, [_OAMRAM + $9F]
+    ld [hl+], a
+    ldh a, [rDMA]
+    ld [hl+], a
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+; Copied to HRAM, only uses relative jumps
+RunDma:
+    ld a, HIGH($C100)
+    ldh [rDMA], a
+    ; OAM reads $FF, the cartridge bus the byte being transferred
+    ld a, [_OAMRAM]
+    ldh [hOamRead], a
+    ld a, [$0000]
+    ldh [hRomRead], a
+    ; Wait for the rest of the 160 machine cycles
+    ld a, 38
+.wait:
+    dec a
+    jr nz, .wait
+    ret
+RunDmaEnd:
+
+SECTION "HRAM", HRAM[$FF80]
+hRunDma: ds RunDmaEnd - RunDma
+hOamRead: ds 1
+hRomRead: ds 1
+
+SECTION "Results", WRAM0[$C000]
+wResults: ds 5
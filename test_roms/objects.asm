@@ -0,0 +1,84 @@
+include "hardware.inc"
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ; Clear VRAM while the LCD is off
+    ld hl, _VRAM
+    ld bc, $2000
+.clearVram:
+    xor a
+    ld [hl+], a
+    dec bc
+    ld a, b
+    or c
+    jr nz, .clearVram
+
+    ; Tile 1 has color 3, tile 2 color 1
+    ld hl, _VRAM + 16
+    ld b, 16
+.tile1:
+    ld a, $FF
+    ld [hl+], a
+    dec b
+    jr nz, .tile1
+    ld b, 8
+.tile2:
+    ld a, $FF
+    ld [hl+], a
+    xor a
+    ld [hl+], a
+    dec b
+    jr nz, .tile2
+
+    ; Background tile 2 at (40, 40)
+    ld a, 2
+    ld [_SCRN0 + 5 * 32 + 5], a
+
+    ; Copy the objects and clear the rest of OAM
+    ld hl, _OAMRAM
+    ld de, Objects
+    ld b, ObjectsEnd - Objects
+.copyObjects:
+    ld a, [de]
+    inc de
+    ld [hl+], a
+    dec b
+    jr nz, .copyObjects
+    ld b, $A0 - (ObjectsEnd - Objects)
+.clearOam:
+    xor a
+    ld [hl+], a
+    dec b
+    jr nz, .clearOam
+
+    ld a, %11100100
+    ldh [rBGP], a
+    ldh [rOBP0], a
+    ld a, %01000000
+    ldh [rOBP1], a
+    ld a, LCDCF_ON | LCDCF_BG8000 | LCDCF_BG9800 | LCDCF_OBJ8 | LCDCF_OBJON | LCDCF_BGON
+    ldh [rLCDC], a
+
+    ; Wait for the first frame to be drawn
+    ld a, IEF_VBLANK
+    ldh [rIE], a
+    xor a
+    ldh [rIF], a
+    halt
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+Objects:
+    ; Y, X, tile and attributes, positions are offset by (8, 16)
+    db 20 + 16, 10 + 8, 1, OAMF_PAL0
+    db 20 + 16, 30 + 8, 1, OAMF_PAL1
+    ; Hidden behind the background, but still on top of the next object
+    db 40 + 16, 40 + 8, 1, OAMF_PRI
+    db 40 + 16, 44 + 8, 1, OAMF_PAL0
+ObjectsEnd:
@@ -0,0 +1,75 @@
+include "hardware.inc"
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld hl, wResults
+
+    ; VRAM is accessible while the LCD is off
+    ld a, $42
+    ld [_VRAM], a
+
+    ; IME is disabled, so every HALT resumes once an enabled interrupt is requested
+    ld a, LCDCF_ON
+    ldh [rLCDC], a
+    ld a, IEF_VBLANK
+    ldh [rIE], a
+    xor a
+    ldh [rIF], a
+    halt
+    ; VBlank starts at line 144 in mode 1
+    ldh a, [rLY]
+    ld [hl+], a
+    ldh a, [rSTAT]
+    ld [hl+], a
+
+    ; LY reaching LYC requests the STAT interrupt at the start of the line, in mode 2
+    ld a, 10
+    ldh [rLYC], a
+    ld a, STATF_LYC
+    ldh [rSTAT], a
+    ld a, IEF_STAT
+    ldh [rIE], a
+    xor a
+    ldh [rIF], a
+    halt
+    ldh a, [rLY]
+    ld [hl+], a
+    ldh a, [rSTAT]
+    ld [hl+], a
+
+    ; The HBlank interrupt of the same line, VRAM is accessible again
+    ld a, STATF_MODE00
+    ldh [rSTAT], a
+    xor a
+    ldh [rIF], a
+    halt
+    ldh a, [rSTAT]
+    ld [hl+], a
+    ld a, [_VRAM]
+    ld [hl+], a
+
+    ; The OAM scan interrupt of the next line, VRAM reads $FF once drawing started
+    ld a, STATF_MODE10
+    ldh [rSTAT], a
+    xor a
+    ldh [rIF], a
+    halt
+    ldh a, [rSTAT]
+    ld [hl+], a
+    ld b, 6
+.drawingDelay:
+    dec b
+    jr nz, .drawingDelay
+    ld a, [_VRAM]
+    ld [hl+], a
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+SECTION "Results", WRAM0[$C000]
+wResults: ds 8
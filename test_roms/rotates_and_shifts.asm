@@ -0,0 +1,122 @@
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld sp, $E000
+    ld de, wResults
+
+    ; RLCA / RRCA / RLA / RRA always reset the zero flag
+    ld a, $85
+    and a
+    rlca
+    call StoreAF
+    ld a, $3B
+    and a
+    rrca
+    call StoreAF
+    ld a, $95
+    and a
+    rla
+    call StoreAF
+    scf
+    ld a, $81
+    rra
+    call StoreAF
+    xor a
+    rlca
+    call StoreAF
+
+    ; RLC r / RRC r
+    ld b, $85
+    rlc b
+    ld a, b
+    call StoreAF
+    ld c, $00
+    rlc c
+    ld a, c
+    call StoreAF
+    push de
+    ld d, $01
+    rrc d
+    ld a, d
+    pop de
+    call StoreAF
+
+    ; RL r / RR r
+    and a
+    push de
+    ld e, $80
+    rl e
+    ld a, e
+    pop de
+    call StoreAF
+    scf
+    ld h, $01
+    rr h
+    ld a, h
+    call StoreAF
+
+    ; SLA r / SRA r / SRL r
+    ld l, $80
+    sla l
+    ld a, l
+    call StoreAF
+    ld a, $8A
+    sra a
+    call StoreAF
+    ld a, $01
+    srl a
+    call StoreAF
+    ld a, $FF
+    srl a
+    call StoreAF
+
+    ; SWAP r
+    ld a, $F0
+    swap a
+    call StoreAF
+    xor a
+    swap a
+    call StoreAF
+
+    ; (HL) variants
+    ld hl, wScratch
+    ld [hl], $85
+    rlc [hl]
+    ld a, [hl]
+    call StoreAF
+    ld hl, wScratch
+    sla [hl]
+    ld a, [hl]
+    call StoreAF
+    ld hl, wScratch
+    swap [hl]
+    ld a, [hl]
+    call StoreAF
+    ld hl, wScratch
+    srl [hl]
+    ld a, [hl]
+    call StoreAF
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+; Stores A and F at [DE] and advances DE. Clobbers HL
+StoreAF:
+    push af
+    pop hl
+    ld a, h
+    ld [de], a
+    inc de
+    ld a, l
+    ld [de], a
+    inc de
+    ret
+
+SECTION "Results", WRAM0[$C000]
+wResults: ds 40
+wScratch: db
@@ -0,0 +1,45 @@
+include "hardware.inc"
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld hl, wResults
+    xor a
+    ldh [rIF], a
+
+    ; Transfer each byte with the internal clock, like test ROMs print their results
+    ld de, Message
+.nextByte:
+    ld a, [de]
+    and a
+    jr z, .done
+    inc de
+    ldh [rSB], a
+    ld a, $81 ; Start with the internal clock
+    ldh [rSC], a
+.waitForTransfer:
+    ldh a, [rSC]
+    and $80
+    jr nz, .waitForTransfer
+    jr .nextByte
+
+.done:
+    ; Without a link partner 1s are shifted in
+    ldh a, [rSB]
+    ld [hl+], a
+    ldh a, [rIF]
+    and IEF_SERIAL
+    ld [hl+], a
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+Message:
+    db "Hi", 0
+
+SECTION "Results", WRAM0[$C000]
+wResults: ds 2
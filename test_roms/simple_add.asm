@@ -10,3 +10,5 @@ EntryPoint:
     ld a, 3
     add a, 5
 
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
@@ -0,0 +1,140 @@
+SECTION "Start", ROM0[$0]
+    ; The tests start executing at $0000, skip over the RST vectors
+    jp $100
+
+SECTION "Rst08", ROM0[$08]
+    inc b
+    ret
+
+SECTION "Rst38", ROM0[$38]
+    inc b
+    inc b
+    ret
+
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld sp, $E000
+
+    ; PUSH rr / POP rr
+    ld bc, $1234
+    push bc
+    pop de
+    ld a, e
+    ld [wPushPop], a
+    ld a, d
+    ld [wPushPop + 1], a
+    ; The lower nibble of F always reads as zero
+    ld bc, $12FF
+    push bc
+    pop af
+    push af
+    pop hl
+    ld a, l
+    ld [wPushPop + 2], a
+    ld a, h
+    ld [wPushPop + 3], a
+
+    ; B counts every taken path, C is set to $FF by every path that must not be taken
+    ld bc, $0000
+
+    ; CALL nn / CALL cc, nn
+    call Increment
+    xor a
+    call z, Increment
+    call nz, Fail
+    call c, Fail
+    call nc, Increment
+    scf
+    call c, Increment
+    call nc, Fail
+
+    ; RET cc
+    xor a
+    call ReturnConditional
+
+    ; JP nn / JP cc, nn
+    jp .absolute
+    call Fail
+.absolute:
+    xor a
+    jp nz, .failNotZero
+    jp z, .jumpZero
+.failNotZero:
+    call Fail
+.jumpZero:
+    inc b
+    scf
+    jp nc, .failNotCarry
+    jp c, .jumpCarry
+.failNotCarry:
+    call Fail
+.jumpCarry:
+    inc b
+
+    ; JR e / JR cc, e including backwards jumps
+    jr .relative
+    call Fail
+.relative:
+    ld a, 3
+.loop:
+    inc b
+    dec a
+    jr nz, .loop
+    jr z, .relativeZero
+    call Fail
+.relativeZero:
+    scf
+    jr nc, .relativeFail
+    jr c, .relativeCarry
+.relativeFail:
+    call Fail
+.relativeCarry:
+
+    ; JP HL
+    ld hl, .afterJpHl
+    jp hl
+    call Fail
+.afterJpHl:
+    inc b
+
+    ; RST
+    rst $08
+    rst $38
+
+    ld a, b
+    ld [wTakenPaths], a
+    ld a, c
+    ld [wFailedPaths], a
+    ld [wStackPointer], sp
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+; Increments B without touching the flags
+Increment:
+    push af
+    inc b
+    pop af
+    ret
+
+ReturnConditional:
+    ret nz
+    ret z
+    call Fail
+    ret
+
+Fail:
+    ld c, $FF
+    ret
+
+SECTION "Results", WRAM0[$C000]
+wPushPop: ds 4
+wTakenPaths: db
+wFailedPaths: db
+wStackPointer: ds 2
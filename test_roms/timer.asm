@@ -0,0 +1,62 @@
+include "hardware.inc"
+SECTION "Header", ROM0[$100]
+
+    jp EntryPoint
+    nop
+
+    ds $150 - @, 0 ; Make room for the header
+
+EntryPoint:
+    ld hl, wResults
+
+    ; DIV counts every 64 machine cycles, resetting it and waiting 204 cycles reads 3
+    xor a
+    ldh [rDIV], a
+    ld b, 50
+.divDelay:
+    dec b
+    jr nz, .divDelay
+    ldh a, [rDIV]
+    ld [hl+], a
+
+    ; At 262144 Hz TIMA counts every 4 machine cycles
+    ld a, TACF_START | TACF_262KHZ
+    ldh [rTAC], a
+    xor a
+    ldh [rDIV], a
+    ldh [rTIMA], a
+    ld b, 20
+.timaDelay:
+    dec b
+    jr nz, .timaDelay
+    ldh a, [rTIMA]
+    ld [hl+], a
+
+    ; The upper bits of TAC read as 1
+    ldh a, [rTAC]
+    ld [hl+], a
+
+    ; On overflow TIMA is reloaded with TMA and the Timer interrupt is requested
+    ld a, $AB
+    ldh [rTMA], a
+    ld a, $FE
+    ldh [rTIMA], a
+    ld a, IEF_TIMER
+    ldh [rIE], a
+    xor a
+    ldh [rIF], a
+    ld a, TACF_START | TACF_4KHZ
+    ldh [rTAC], a
+    ; IME is disabled, HALT resumes once the interrupt is requested
+    halt
+    ldh a, [rTIMA]
+    ld [hl+], a
+    ldh a, [rIF]
+    ld [hl+], a
+
+    ; Jump to the end of the address space, which stops the test harness
+    jp $FFFF
+
+SECTION "Results", WRAM0[$C000]
+wResults:
+    ds 5